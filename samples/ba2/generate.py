@@ -0,0 +1,185 @@
+# BA2 samples, written the way Archive2 lays them out.
+# Run from the repository root: python3 samples/ba2/generate.py
+import os, random, struct, zlib
+
+def bethesda_crc(data):
+    # standard CRC-32 table, initial value 0, no final xor
+    return (~zlib.crc32(data, 0xFFFFFFFF)) & 0xFFFFFFFF
+
+def hashes(name):
+    name = name.replace("/", "\\").lower()
+    folder, _, file_name = name.rpartition("\\")
+    stem, dot, ext = file_name.rpartition(".")
+    if not dot:
+        stem, ext = file_name, ""
+    return bethesda_crc(stem.encode()), ext.encode()[:4].ljust(4, b"\0"), bethesda_crc(folder.encode())
+
+def names_table(names):
+    out = b""
+    for name in names:
+        b = name.replace("/", "\\").encode("cp1252")
+        out += struct.pack("<H", len(b)) + b
+    return out
+
+def lz4_block(data):
+    # greedy LZ4 block compressor, the last 5 bytes are literals and no match starts in the last 12 bytes
+    out = bytearray()
+    table = {}
+    anchor = pos = 0
+    while pos + 12 <= len(data):
+        key = data[pos:pos + 4]
+        candidate = table.get(key)
+        table[key] = pos
+        if candidate is None or pos - candidate > 0xFFFF:
+            pos += 1
+            continue
+        length = 4
+        while pos + length < len(data) - 5 and data[candidate + length] == data[pos + length]:
+            length += 1
+        out += lz4_sequence(data[anchor:pos], length - 4) + struct.pack("<H", pos - candidate)
+        if length - 4 >= 15:
+            out += lz4_length(length - 4 - 15)
+        pos = anchor = pos + length
+    return bytes(out + lz4_sequence(data[anchor:], 0))
+
+def lz4_length(value):
+    return b"\xff" * (value // 255) + bytes([value % 255])
+
+def lz4_sequence(literals, match_length):
+    token = (min(len(literals), 15) << 4) | min(match_length, 15)
+    out = bytes([token])
+    if len(literals) >= 15:
+        out += lz4_length(len(literals) - 15)
+    return out + literals
+
+def header(version, archive_type, count, names_offset, compression):
+    out = b"BTDX" + struct.pack("<I4sIQ", version, archive_type, count, names_offset)
+    if version in (2, 3):
+        out += struct.pack("<Q", 1)
+    if version == 3:
+        out += struct.pack("<I", 3 if compression == "lz4" else 0)
+    return out
+
+def header_size(version):
+    return {2: 32, 3: 36}.get(version, 24)
+
+def compress(content, compression):
+    return lz4_block(content) if compression == "lz4" else zlib.compress(content, 9)
+
+def read_dir(root):
+    files = []
+    for dir_path, _, file_names in os.walk(root):
+        for file_name in file_names:
+            if file_name == ".gitkeep":
+                continue
+            path = os.path.join(dir_path, file_name)
+            files.append((os.path.relpath(path, root).replace(os.sep, "/"), open(path, "rb").read()))
+    return sorted(files)
+
+def general(path, files, version=1, compression="zlib"):
+    offset = header_size(version) + 36 * len(files)
+    records = b""
+    data = b""
+    for name, content in files:
+        name_hash, ext, dir_hash = hashes(name)
+        packed = compress(content, compression)
+        records += struct.pack("<I4sIIQIII", name_hash, ext, dir_hash, 0x00100100, offset + len(data),
+                               len(packed), len(content), 0xBAADF00D)
+        data += packed
+    out = header(version, b"GNRL", len(files), offset + len(data), compression)
+    open(path, "wb").write(out + records + data + names_table([name for name, _ in files]))
+
+def dds_header(width, height, mips, dxgi_format):
+    # block size and FourCC of the formats used below, other formats get a DX10 header
+    block_formats = {71: (8, b"DXT1"), 98: (16, None)}
+    flags = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000
+    if dxgi_format in block_formats:
+        block_size, fourcc = block_formats[dxgi_format]
+        flags |= 0x80000
+        pitch = max(1, (width + 3) // 4) * max(1, (height + 3) // 4) * block_size
+    else:
+        flags |= 0x8
+        pitch = width * 4
+        fourcc = None
+    caps = 0x1000
+    if mips > 1:
+        caps |= 0x8 | 0x400000
+    if dxgi_format == 28:
+        pixel_format = struct.pack("<8I", 32, 0x41, 0, 32, 0xFF, 0xFF00, 0xFF0000, 0xFF000000)
+    else:
+        pixel_format = struct.pack("<II4s5I", 32, 0x4, fourcc or b"DX10", 0, 0, 0, 0, 0)
+    header = b"DDS " + struct.pack("<7I", 124, flags, height, width, pitch, 0, mips) + bytes(44)
+    header += pixel_format + struct.pack("<5I", caps, 0, 0, 0, 0)
+    if dxgi_format not in (28, 71):
+        header += struct.pack("<5I", dxgi_format, 3, 0, 1, 0)
+    return header
+
+def dx10(path, textures, out_dir, version=1, compression="zlib"):
+    # textures: (name, width, height, mips, DXGI format, [(data, start mip, end mip, stored)])
+    offset = header_size(version) + sum(24 + 24 * len(chunks) for *_, chunks in textures)
+    records = b""
+    data = b""
+    for name, width, height, mips, dxgi_format, chunks in textures:
+        name_hash, ext, dir_hash = hashes(name)
+        records += struct.pack("<I4sIBBHHHBBH", name_hash, ext, dir_hash, 0, len(chunks), 24, height, width,
+                               mips, dxgi_format, 0x800)
+        payload = b""
+        for content, start_mip, end_mip, stored in chunks:
+            packed = content if stored else compress(content, compression)
+            records += struct.pack("<QIIHHI", offset + len(data), 0 if stored else len(packed), len(content),
+                                   start_mip, end_mip, 0xBAADF00D)
+            data += packed
+            payload += content
+        dds_path = os.path.join(out_dir, name)
+        os.makedirs(os.path.dirname(dds_path), exist_ok=True)
+        open(dds_path, "wb").write(dds_header(width, height, mips, dxgi_format) + payload)
+    out = header(version, b"DX10", len(textures), offset + len(data), compression)
+    open(path, "wb").write(out + records + data + names_table([name for name, *_ in textures]))
+
+def gnmf(path, textures, out_dir):
+    # textures: (name, width, height, [mip data]), the first mip is compressed
+    offset = 24 + sum(48 + 24 * len(mips) for *_, mips in textures)
+    records = b""
+    data = b""
+    for name, width, height, mips in textures:
+        name_hash, ext, dir_hash = hashes(name)
+        # sce::Gnm::Texture descriptor: data format, size and the last mip level
+        descriptor = struct.pack("<8I", 0, 35 << 20, (width - 1) | ((height - 1) << 14), (len(mips) - 1) << 16,
+                                 0, 0, 0, 0)
+        records += struct.pack("<I4sIBBH", name_hash, ext, dir_hash, 0, len(mips), 24) + descriptor
+        for index, mip in enumerate(mips):
+            packed = zlib.compress(mip, 9) if index == 0 else mip
+            records += struct.pack("<QIIHHI", offset + len(data), len(packed) if index == 0 else 0, len(mip),
+                                   index, index, 0xBAADF00D)
+            data += packed
+        size = 0x100 + sum(len(mip) for mip in mips)
+        gnf_header = (b"GNF " + struct.pack("<I4BI", 0xF8, 2, 1, 8, 0, size) + descriptor).ljust(0x100, b"\0")
+        gnf_path = os.path.join(out_dir, os.path.splitext(name)[0] + ".gnf")
+        os.makedirs(os.path.dirname(gnf_path), exist_ok=True)
+        open(gnf_path, "wb").write(gnf_header + b"".join(mips))
+    out = header(1, b"GNMF", len(textures), offset + len(data), "zlib")
+    open(path, "wb").write(out + records + data + names_table([name for name, *_ in textures]))
+
+def pattern(size, seed):
+    return bytes((i * 7 + seed) & 0xFF for i in range(size))
+
+TEXTURES = [
+    ("textures/bc1.dds", 16, 8, 3, 71, [(bytes(64), 0, 0, False), (pattern(24, 1), 1, 2, True)]),
+    ("textures/bc7.dds", 8, 8, 1, 98, [(pattern(64, 3), 0, 0, False)]),
+    ("textures/sub/rgba.dds", 4, 4, 1, 28, [(pattern(64, 5), 0, 0, False)]),
+]
+
+general("samples/ba2/correct_general.ba2", read_dir("samples/unpacked"))
+dx10("samples/ba2/correct_dx10.ba2", TEXTURES, "samples/ba2/dx10")
+
+# Starfield (2, 3) and Fallout 4 next-gen (7, 8) versions
+for version in (2, 7, 8):
+    general(f"samples/ba2/general_v{version}.ba2", read_dir("samples/ba2/general"), version)
+general("samples/ba2/general_v3_lz4.ba2", read_dir("samples/ba2/general"), 3, "lz4")
+dx10("samples/ba2/dx10_v3_lz4.ba2", TEXTURES, "samples/ba2/dx10", 3, "lz4")
+
+random.seed(31)
+gnmf("samples/ba2/correct_gnmf.ba2", [
+    ("textures/ps4.dds", 64, 32, [bytes(random.getrandbits(8) for _ in range(256)) * 4, b"\x11" * 256]),
+    ("textures/ui/logo.gnf", 16, 16, [bytes(range(256)) * 4]),
+], "samples/ba2/gnmf")
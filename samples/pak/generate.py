@@ -0,0 +1,30 @@
+# SiN and Daikatana .pak samples.
+# Run from the repository root: python3 samples/pak/generate.py
+import struct
+
+FILES = [("readme.txt", b"SiN and Daikatana archives\n"), ("maps/e1m1.txt", bytes(40) + b"map" + b"!" * 10),
+         ("empty", b"")]
+
+# Daikatana compression of `maps/e1m1.txt`, see src/lib/pak/daikatana.rs: 20 zero bytes, 20 bytes
+# copied from 20 bytes back, 3 literals, `!` repeated 10 times and the end of data
+E1M1_PACKED = bytes([62 + 20, 190 + 20, 20 - 2, 3 - 1]) + b"map" + bytes([126 + 10]) + b"!" + bytes([255])
+
+def build(signature, name_size, daikatana):
+    data = bytearray(12)
+    index = b""
+    for name, content in FILES:
+        offset = len(data)
+        if daikatana and name == "maps/e1m1.txt":
+            data += E1M1_PACKED
+            extra = struct.pack("<II", len(E1M1_PACKED), 1)
+        else:
+            data += content
+            extra = struct.pack("<II", len(content), 0) if daikatana else b""
+        index += name.encode().ljust(name_size, b"\0") + struct.pack("<II", offset, len(content)) + extra
+    offset = len(data)
+    data += index
+    data[:12] = signature + struct.pack("<II", offset, len(index))
+    return bytes(data)
+
+open("samples/pak/sin.pak", "wb").write(build(b"SPAK", 120, False))
+open("samples/pak/daikatana.pak", "wb").write(build(b"PACK", 56, True))
@@ -0,0 +1,63 @@
+# Ren'Py archive samples, written the way Ren'Py's archiver and its forks lay them out.
+# Run from the repository root: python3 samples/rpa/generate.py
+import pickle, zlib
+
+FILES = [("file001.txt", b"Hello, Ren'Py!\n"), ("dir1/file002.txt", b"second file\n" * 3), ("empty_file", b"")]
+KEY = 0x12345678
+
+def build(header, key, bytes_names, two_tuples, protocol):
+    # `header` formats the header from the index offset, `None` puts the index into a separate .rpi file
+    data = bytearray(64 if header else 0)
+    index = {}
+    for name, content in FILES:
+        data += b"Made with Ren'Py."
+        offset = len(data)
+        data += content
+        entry = (offset ^ key, len(content) ^ key) if two_tuples else (offset ^ key, len(content) ^ key, "")
+        index[name.encode() if bytes_names else name] = [entry]
+    index = zlib.compress(pickle.dumps(index, protocol))
+    if header is None:
+        return bytes(data), index
+    offset = len(data)
+    data += index
+    line = header(offset).encode() + b"\n"
+    assert len(line) <= 64
+    data[:len(line)] = line
+    return bytes(data), None
+
+open("samples/rpa/v2.rpa", "wb").write(build(lambda offset: "RPA-2.0 %016x" % offset, 0, True, True, 3)[0])
+rpa, rpi = build(None, 0, True, True, 3)
+open("samples/rpa/v1.rpa", "wb").write(rpa)
+open("samples/rpa/v1.rpi", "wb").write(rpi)
+# the field after the offset is unused, the key is split into two parts, which are XORed together
+open("samples/rpa/v3_2.rpa", "wb").write(build(
+    lambda offset: "RPA-3.2 %016x %08x %08x %08x" % (offset, 0xDEADBEEF, KEY ^ 0xFF00FF00, 0xFF00FF00),
+    KEY, False, False, 2)[0])
+open("samples/rpa/alt1.rpa", "wb").write(build(
+    lambda offset: "ALT-1.0 %08x %016x" % (KEY ^ 0xDABE8DF0, offset), KEY, False, False, 2)[0])
+# ZiX-12A key is computed by the game, the header value is not used
+open("samples/rpa/zix.rpa", "wb").write(build(
+    lambda offset: "ZiX-12A %016x %08x" % (offset, 0xCAFE), KEY, False, False, 2)[0])
+
+# prefixes and multi-segment entries of RPA-3.0, index entries are (offset ^ key, size ^ key[, prefix])
+KEY_3_0 = 0x42424242
+data = bytearray(40)
+index = {}
+
+def segment(content):
+    offset = len(data)
+    data.extend(content)
+    return offset ^ KEY_3_0, len(content) ^ KEY_3_0
+
+index["file001.txt"] = [(*segment(b"Ren'Py!\n"), b"Hello, ")]
+# text prefix, unpickled as Python 2 string
+index["dir1/file002.txt"] = [(*segment(b"nd file\n" + b"second file\n" * 2), "seco")]
+parts = [segment(b"multi "), segment(b"segment "), segment(b"file\n")]
+index["multi.txt"] = [(*parts[0], b">> "), parts[1], (*parts[2], b"")]
+index["empty_file"] = [(*segment(b""), b"")]
+index["prefix_only"] = [(*segment(b""), b"\xe9\x00\xff")]
+offset = len(data)
+data += zlib.compress(pickle.dumps(index, 2))
+line = ("RPA-3.0 %016x %08x\n" % (offset, KEY_3_0)).encode()
+data[:len(line)] = line
+open("samples/rpa/prefixes.rpa", "wb").write(bytes(data))
@@ -0,0 +1,235 @@
+# Unreal Engine .pak samples, written the way UnrealPak lays them out.
+# Run from the repository root: python3 samples/ue_pak/generate.py (needs `cryptography`).
+import gzip, hashlib, struct, zlib
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+
+MAGIC = 0x5A6F12E1
+KEY = bytes(range(32))
+MOUNT = "../../../MyGame/"
+BLOCK_SIZE = 0x10000
+
+FILES = [
+    ("Content/hello.txt", b"Hello from Unreal!\n"),
+    ("Content/Maps/big.bin", bytes((i * 7) % 251 for i in range(150000)) + b"tail" * 1000),
+    ("Config/empty.ini", b""),
+]
+
+def aes_encrypt(data):
+    assert len(data) % 16 == 0
+    enc = Cipher(algorithms.AES(KEY), modes.ECB()).encryptor()
+    return enc.update(data) + enc.finalize()
+
+def pad16(data):
+    return data + bytes(-len(data) % 16)
+
+def fstring(s):
+    if s.isascii():
+        b = s.encode() + b"\0"
+        return struct.pack("<i", len(b)) + b
+    b = (s + "\0").encode("utf-16-le")
+    return struct.pack("<i", -(len(b) // 2)) + b
+
+class Entry:
+    pass
+
+def entry_size(version, e, v8a=False):
+    size = 8 + 8 + 8 + 20
+    size += 1 if v8a else 4
+    if version >= 3:
+        size += 1 + 4
+        if e.method != 0:
+            size += 4 + 16 * len(e.blocks)
+    return size
+
+def ser_entry(version, e, offset, v8a=False):
+    out = struct.pack("<qqq", offset, e.size, e.usize)
+    out += struct.pack("<B", e.method) if v8a else struct.pack("<I", e.method)
+    out += e.hash
+    if version >= 3:
+        if e.method != 0:
+            out += struct.pack("<I", len(e.blocks))
+            for start, end in e.blocks:
+                out += struct.pack("<qq", start, end)
+        out += struct.pack("<BI", 1 if e.encrypted else 0, e.block_size)
+    return out
+
+def compress(method_name, data):
+    if method_name == "Zlib":
+        return zlib.compress(data)
+    if method_name == "Gzip":
+        return gzip.compress(data, mtime=0)
+    if method_name == "Oodle":
+        return b"\x8c\x06" + bytes(14)  # not real Oodle data, never decompressed
+    raise ValueError(method_name)
+
+def write_data(out, version, name, data, method, method_name, encrypted, v8a=False):
+    e = Entry()
+    e.usize = len(data)
+    e.method = method
+    e.encrypted = encrypted
+    e.offset = len(out)
+    if method == 0:
+        e.block_size = 0
+        e.blocks = []
+        stored = pad16(data) if encrypted else data
+        e.size = len(data)
+        payload = stored
+    else:
+        chunks = [data[i:i + BLOCK_SIZE] for i in range(0, len(data), BLOCK_SIZE)] or [b""]
+        e.block_size = BLOCK_SIZE if len(data) > BLOCK_SIZE else len(data)
+        packed = [compress(method_name, chunk) for chunk in chunks]
+        e.blocks = [None] * len(packed)
+        header_size = entry_size(version, e, v8a)
+        pos = header_size if version >= 5 else e.offset + header_size
+        payload = b""
+        for i, p in enumerate(packed):
+            e.blocks[i] = (pos, pos + len(p))
+            stored = pad16(p) if encrypted else p
+            pos += len(stored)
+            payload += stored
+        e.size = len(payload)
+    e.hash = hashlib.sha1(payload).digest()
+    if encrypted:
+        payload = aes_encrypt(payload)
+    out += ser_entry(version, e, 0, v8a) + payload
+    return e
+
+def footer(version, index_offset, index_size, index_hash, encrypted_index, methods, v8a=False, frozen=False):
+    out = b""
+    if version >= 7:
+        out += bytes(16)
+    if version >= 4:
+        out += struct.pack("<B", 1 if encrypted_index else 0)
+    out += struct.pack("<IIqq", MAGIC, version, index_offset, index_size) + index_hash
+    if version == 9:
+        out += struct.pack("<B", 1 if frozen else 0)
+    if version >= 8:
+        names = methods + [""] * ((4 if v8a else 5) - len(methods))
+        for n in names:
+            out += n.encode().ljust(32, b"\0")
+    return out
+
+def finish_index(index, encrypted_index):
+    index = pad16(index) if encrypted_index else index
+    digest = hashlib.sha1(index).digest()
+    return (aes_encrypt(index) if encrypted_index else index), digest
+
+def legacy_pak(path, version, method_name=None, encrypt=False, v8a=False, frozen=False):
+    out = bytearray()
+    methods = [method_name] if method_name else []
+    entries = []
+    for name, data in FILES:
+        if method_name is None or name.endswith(".ini"):
+            method = 0
+        elif version < 8:
+            method = {"Zlib": 1, "Gzip": 2, "Oodle": 4}[method_name]
+        else:
+            method = 1
+        entries.append((name, write_data(out, version, name, data, method, method_name, encrypt, v8a)))
+    index = fstring(MOUNT) + struct.pack("<i", len(entries))
+    for name, e in entries:
+        index += fstring(name) + ser_entry(version, e, e.offset, v8a)
+    index_offset = len(out)
+    index, digest = finish_index(index, encrypt)
+    out += index
+    out += footer(version, index_offset, len(index), digest, encrypt, methods, v8a, frozen)
+    open(path, "wb").write(out)
+
+def encode_entry(e):
+    block_bits = e.block_size >> 11
+    if block_bits > 0x3f or e.block_size & 0x7ff:
+        block_bits = 0x3f
+    value = (1 << 31 if e.offset <= 0xFFFFFFFF else 0) | (1 << 30 if e.usize <= 0xFFFFFFFF else 0) \
+        | (1 << 29 if e.size <= 0xFFFFFFFF else 0) | (e.method << 23) | ((1 if e.encrypted else 0) << 22) \
+        | (len(e.blocks) << 6) | block_bits
+    out = struct.pack("<I", value)
+    if block_bits == 0x3f:
+        out += struct.pack("<I", e.block_size)
+    out += struct.pack("<I", e.offset) + struct.pack("<I", e.usize)
+    if e.method != 0:
+        out += struct.pack("<I", e.size)
+    if len(e.blocks) > 1 or (e.encrypted and e.blocks):
+        for start, end in e.blocks:
+            out += struct.pack("<I", end - start)
+    return out
+
+def fnv64(data, seed):
+    h = (0xcbf29ce484222325 + seed) & 0xFFFFFFFFFFFFFFFF
+    for b in data:
+        h ^= b
+        h = (h * 0x100000001b3) & 0xFFFFFFFFFFFFFFFF
+    return h
+
+def v11_pak(path, method_name="Zlib", encrypt=False, full_directory=True, unencoded=()):
+    version = 11
+    out = bytearray()
+    methods = [method_name]
+    entries = []
+    for name, data in FILES:
+        method = 0 if name.endswith(".ini") else 1
+        entries.append((name, write_data(out, version, name, data, method, method_name, encrypt)))
+
+    encoded = b""
+    files = []
+    locations = {}
+    for name, e in entries:
+        if name in unencoded:
+            locations[name] = -len(files) - 1
+            files.append(e)
+        else:
+            locations[name] = len(encoded)
+            encoded += encode_entry(e)
+
+    seed = 0x1234
+    path_hash = struct.pack("<i", len(entries))
+    for name, _ in entries:
+        path_hash += struct.pack("<Qi", fnv64(name.lower().encode("utf-16-le"), seed), locations[name])
+    path_hash += struct.pack("<i", 0)  # pruned directory index
+
+    dirs = {}
+    for name, _ in entries:
+        d, _, f = name.rpartition("/")
+        dirs.setdefault(d + "/" if d else "/", []).append((f, locations[name]))
+    full = struct.pack("<i", len(dirs))
+    for d, fs in dirs.items():
+        full += fstring(d) + struct.pack("<i", len(fs))
+        for f, loc in fs:
+            full += fstring(f) + struct.pack("<i", loc)
+
+    index_offset = len(out)
+    # secondary indices follow the primary one, their offsets are needed in the primary index
+    primary_size = None
+    for _ in range(2):
+        path_hash_offset = index_offset + (primary_size or 0)
+        path_hash_data, path_hash_digest = finish_index(path_hash, encrypt)
+        full_offset = path_hash_offset + len(path_hash_data)
+        full_data, full_digest = finish_index(full, encrypt)
+        primary = fstring(MOUNT) + struct.pack("<iQ", len(entries), seed)
+        primary += struct.pack("<I", 1) + struct.pack("<qq", path_hash_offset, len(path_hash_data)) + path_hash_digest
+        if full_directory:
+            primary += struct.pack("<I", 1) + struct.pack("<qq", full_offset, len(full_data)) + full_digest
+        else:
+            primary += struct.pack("<I", 0)
+        primary += struct.pack("<i", len(encoded)) + encoded
+        primary += struct.pack("<i", len(files))
+        for e in files:
+            primary += ser_entry(version, e, e.offset)
+        primary_data, primary_digest = finish_index(primary, encrypt)
+        primary_size = len(primary_data)
+
+    out += primary_data + path_hash_data
+    if full_directory:
+        out += full_data
+    out += footer(version, index_offset, len(primary_data), primary_digest, encrypt, methods)
+    open(path, "wb").write(out)
+
+legacy_pak("samples/ue_pak/v3.pak", 3, "Zlib")
+legacy_pak("samples/ue_pak/v5_gzip.pak", 5, "Gzip")
+legacy_pak("samples/ue_pak/v7_encrypted.pak", 7, "Zlib", encrypt=True)
+legacy_pak("samples/ue_pak/v8a.pak", 8, "Zlib", v8a=True)
+legacy_pak("samples/ue_pak/v8b.pak", 8, "Zlib")
+legacy_pak("samples/ue_pak/v9.pak", 9, "Zlib")
+legacy_pak("samples/ue_pak/v7_oodle.pak", 7, "Oodle")
+v11_pak("samples/ue_pak/v11.pak", unencoded=("Content/hello.txt",))
+v11_pak("samples/ue_pak/v11_encrypted.pak", encrypt=True)
+v11_pak("samples/ue_pak/v11_path_hash.pak", full_directory=False)
@@ -0,0 +1,27 @@
+# Doom WAD sample with duplicate lumps of two maps and zero-length markers.
+# Run from the repository root: python3 samples/wad/generate.py
+import struct
+
+LUMPS = [
+    ("PLAYPAL", bytes(range(256)) * 3),
+    ("E1M1", b""),
+    ("THINGS", b"\x01" * 10),
+    ("LINEDEFS", b"\x02" * 14),
+    ("E1M2", b""),
+    ("THINGS", b"\x03" * 20),
+    ("LINEDEFS", b"\x04" * 28),
+    ("F_START", b""),
+    ("FLOOR0_1", bytes(range(64))),
+    ("F_END", b""),
+    ("DSPISTOL", bytes([0x03, 0x00, 0x11, 0x2B])),
+]
+
+data = b""
+directory = b""
+for name, content in LUMPS:
+    # markers are at the offset of the next lump
+    directory += struct.pack("<II8s", 12 + len(data), len(content), name.encode())
+    data += content
+
+header = b"IWAD" + struct.pack("<II", len(LUMPS), 12 + len(data))
+open("samples/wad/doom.wad", "wb").write(header + data + directory)
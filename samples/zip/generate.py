@@ -0,0 +1,82 @@
+# ZIP samples of the cases the zip crate does not write.
+# Run from the repository root: python3 samples/zip/generate.py
+import struct, zipfile, zlib
+
+# ZipCrypto encrypted archive, laid out the way Info-ZIP `zip -X -P secret` writes it
+PASSWORD = b"secret"
+FILES = [("hello.txt", b"Hello, world!\n"), ("dir/nested.txt", b"nested file\n")]
+# 2026-10-18 18:01:20
+DOS_TIME = (18 << 11) | (1 << 5) | (20 // 2)
+DOS_DATE = ((2026 - 1980) << 9) | (10 << 5) | 18
+
+def crc32_byte(crc, byte):
+    return zlib.crc32(bytes([byte]), crc ^ 0xFFFFFFFF) ^ 0xFFFFFFFF
+
+def zipcrypto(data):
+    keys = [0x12345678, 0x23456789, 0x34567890]
+
+    def update(byte):
+        keys[0] = crc32_byte(keys[0], byte)
+        keys[1] = ((keys[1] + (keys[0] & 0xFF)) * 134775813 + 1) & 0xFFFFFFFF
+        keys[2] = crc32_byte(keys[2], keys[1] >> 24)
+
+    for byte in PASSWORD:
+        update(byte)
+    out = bytearray()
+    for byte in data:
+        temp = (keys[2] | 2) & 0xFFFF
+        out.append(byte ^ (((temp * (temp ^ 1)) >> 8) & 0xFF))
+        update(byte)
+    return bytes(out)
+
+archive = b""
+central = b""
+for name, content in FILES:
+    crc = zlib.crc32(content)
+    # encryption header: 11 bytes that should be random and the check byte, which is the high byte
+    # of MS-DOS time when sizes are in the data descriptor
+    encrypted = zipcrypto(bytes(range(11)) + bytes([DOS_TIME >> 8]) + content)
+    # version needed 1.0, flags: encrypted, data descriptor
+    fields = struct.pack("<HHHHHIIIHH", 10, 0x9, 0, DOS_TIME, DOS_DATE, crc, len(encrypted), len(content),
+                         len(name), 0)
+    offset = len(archive)
+    archive += b"PK\x03\x04" + fields + name.encode() + encrypted
+    archive += b"PK\x07\x08" + struct.pack("<III", crc, len(encrypted), len(content))
+    # made by Unix, version 3.0, -rw-r--r--
+    central += b"PK\x01\x02" + struct.pack("<H", 0x031E) + fields
+    central += struct.pack("<HHHII", 0, 0, 0, 0o100644 << 16, offset) + name.encode()
+
+eocd = b"PK\x05\x06" + struct.pack("<HHHHIIH", 0, 0, len(FILES), len(FILES), len(central), len(archive), 0)
+open("samples/zip/zipcrypto.zip", "wb").write(archive + central + eocd)
+
+# ZIP64 archive whose only entry claims to be 5 GiB, data is not included
+name = b"huge.bin"
+size = 5 * 1024**3
+# ZIP64 extended information: uncompressed and compressed sizes
+zip64_extra = struct.pack("<HHQQ", 1, 16, size, size)
+local = struct.pack("<IHHHHHIIIHH", 0x04034B50, 45, 0, 0, 0, 0x21, 0, 0xFFFFFFFF, 0xFFFFFFFF, len(name),
+                    len(zip64_extra)) + name + zip64_extra
+central = struct.pack("<IHHHHHHIIIHHHHHII", 0x02014B50, 45, 45, 0, 0, 0, 0x21, 0, 0xFFFFFFFF, 0xFFFFFFFF,
+                      len(name), len(zip64_extra), 0, 0, 0, 0, 0) + name + zip64_extra
+central_offset = len(local)
+zip64_eocd_offset = central_offset + len(central)
+zip64_eocd = struct.pack("<IQHHIIQQQQ", 0x06064B50, 44, 45, 45, 0, 0, 1, 1, len(central), central_offset)
+locator = struct.pack("<IIQI", 0x07064B50, 0, zip64_eocd_offset, 1)
+eocd = struct.pack("<IHHHHIIH", 0x06054B50, 0, 0, 1, 1, len(central), central_offset, 0)
+open("samples/zip/zip64.zip", "wb").write(local + central + zip64_eocd + locator + eocd)
+
+# names in legacy code pages, without UTF-8 flag, the content of every file is its name in UTF-8
+class RawNameInfo(zipfile.ZipInfo):
+    def _encodeFilenameFlags(self):
+        return self.raw_name, self.flag_bits
+
+def legacy_names(path, encoding, names):
+    with zipfile.ZipFile(path, "w") as archive:
+        for name in names:
+            info = RawNameInfo(name, date_time=(2020, 1, 1, 0, 0, 0))
+            info.raw_name = name.encode(encoding)
+            archive.writestr(info, name.encode("utf-8"))
+
+legacy_names("samples/zip/shift_jis.zip", "shift_jis", ["データ/テクスチャ.txt", "セーブ.dat"])
+legacy_names("samples/zip/cp866.zip", "cp866", ["Моды/текстура.txt", "сохранение.dat"])
+legacy_names("samples/zip/cp437.zip", "cp437", ["café/résumé.txt", "naïve.dat"])
@@ -7,12 +7,20 @@ use clap::Args;
 
 use flpak::{io_error, reader, FileType, Registry};
 
+use super::parse_options;
+
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)]
 pub struct CheckArgs {
     /// Archive format. Use 'list-formats' to see supported formats. If omitted, the format will be guessed.
     #[arg(short, long)]
     format: Option<String>,
+    /// Options
+    #[arg(short, long)]
+    options: Option<String>,
+    /// Password for encrypted archives
+    #[arg(short, long)]
+    password: Option<String>,
     /// Path to archive
     input_file: PathBuf,
 }
@@ -24,7 +32,10 @@ pub fn check(args: CheckArgs, verbose: bool) -> Result<()> {
         .create_reader(
             args.format,
             &args.input_file,
-            reader::Options { strict: true },
+            reader::Options {
+                strict: true,
+                params: parse_options(args.options, args.password),
+            },
         )
         .map_err(|err| io_error!(Other, "{}", err))?;
 
@@ -70,5 +81,14 @@ pub fn check(args: CheckArgs, verbose: bool) -> Result<()> {
         }
     }
 
+    rdr.verify().map_err(|err| io_error!(Other, "{}", err))?;
+
+    let mut attrs: Vec<_> = rdr.attrs().into_iter().collect();
+    attrs.sort();
+
+    for (key, value) in attrs {
+        println!("{key}: {value}");
+    }
+
     Ok(())
 }
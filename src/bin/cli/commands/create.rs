@@ -1,9 +1,11 @@
-use std::{collections::HashMap, io::Result, path::PathBuf};
+use std::{io::Result, path::PathBuf};
 
 use clap::Args;
 
 use flpak::{io_error, InputFileListBuilder, Registry};
 
+use super::parse_options;
+
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)]
 pub struct CreateArgs {
@@ -15,6 +17,9 @@ pub struct CreateArgs {
     /// Options
     #[arg(short, long)]
     options: Option<String>,
+    /// Password for encrypted archives
+    #[arg(short, long)]
+    password: Option<String>,
     /// Input directory
     #[arg(short, long)]
     add_dir: Vec<PathBuf>,
@@ -42,29 +47,17 @@ pub fn create(args: CreateArgs) -> Result<()> {
 
     let input_files = file_list_builder.build();
 
-    let options = parse_options(args.options);
+    let options = parse_options(args.options, args.password);
 
-    let writer_fn = registry
-        .create_writer(&args.format)
+    let paths = registry
+        .create_archive(&args.format, input_files, &args.output_file, &options)
         .map_err(|err| io_error!(Other, "failed to create archive: {err}"))?;
 
-    writer_fn(input_files, &args.output_file, &options)
-        .map_err(|err| io_error!(Other, "failed to create archive: {err}"))?;
-
-    Ok(())
-}
-
-fn parse_options(options: Option<String>) -> HashMap<String, String> {
-    let mut map = HashMap::new();
-
-    if let Some(options) = options {
-        for option in options.split(',') {
-            let mut parts = option.splitn(2, '=');
-            let key = parts.next().expect("there should be key");
-            let val = parts.next().expect("there should be value");
-            map.insert(key.to_string(), val.to_string());
+    if options.contains_key("max-size") {
+        for path in paths {
+            println!("{}", path.display());
         }
     }
 
-    map
+    Ok(())
 }
@@ -0,0 +1,39 @@
+use std::{io::Result, path::PathBuf};
+
+use clap::Args;
+
+use flpak::{io_error, Registry};
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+pub struct DetectArgs {
+    /// Path to archive
+    input_file: PathBuf,
+}
+
+pub fn detect(args: DetectArgs) -> Result<()> {
+    let registry = Registry::new();
+
+    let candidates = registry
+        .detect(&args.input_file)
+        .map_err(|err| io_error!(Other, "{}", err))?;
+
+    for candidate in &candidates {
+        println!(
+            "{:<8}{:>4}  {}",
+            candidate.format,
+            candidate.score,
+            candidate.reasons.join(", ")
+        );
+    }
+
+    if !candidates.iter().any(|c| c.is_plausible()) {
+        return Err(io_error!(
+            Other,
+            "unable to detect format of '{}'",
+            args.input_file.display()
+        ));
+    }
+
+    Ok(())
+}
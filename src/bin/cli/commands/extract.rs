@@ -6,7 +6,9 @@ use std::{
 
 use clap::Args;
 
-use flpak::{io_error, reader, FileType, Registry, PathBufUtils};
+use flpak::{io_error, reader, FileType, PathBufUtils, Registry};
+
+use super::parse_options;
 
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)]
@@ -16,6 +18,12 @@ pub struct ExtractArgs {
     /// Archive format. Use 'list-formats' to see supported formats. If omitted, the format will be guessed.
     #[arg(short, long)]
     format: Option<String>,
+    /// Options
+    #[arg(short, long)]
+    options: Option<String>,
+    /// Password for encrypted archives
+    #[arg(short, long)]
+    password: Option<String>,
     /// Path to archive
     input_file: PathBuf,
     /// Output path
@@ -31,6 +39,7 @@ pub fn extract(args: ExtractArgs, verbose: bool) -> Result<()> {
             &args.input_file,
             reader::Options {
                 strict: args.strict,
+                params: parse_options(args.options, args.password),
             },
         )
         .map_err(|err| {
@@ -65,9 +74,10 @@ pub fn extract(args: ExtractArgs, verbose: bool) -> Result<()> {
 
                 let file_path = args.output_dir.join(&name);
                 let size = size.expect("regular file should have size");
+                let metadata = rdr.file_metadata(index);
 
                 if let Some(parent) = file_path.parent() {
-                    std::fs::create_dir_all(&parent).map_err(|err| {
+                    std::fs::create_dir_all(parent).map_err(|err| {
                         io_error!(
                             Other,
                             "failed to extract file '{}': failed to create directory '{}': {}",
@@ -117,6 +127,14 @@ pub fn extract(args: ExtractArgs, verbose: bool) -> Result<()> {
                         "failed to unpack file '{}': expected {size} bytes, got {bytes_written} bytes", name.display(),
                     ));
                 }
+
+                apply_metadata(&output_file, metadata).map_err(|err| {
+                    io_error!(
+                        Other,
+                        "failed to extract file '{}': failed to set file metadata: {err}",
+                        name.display(),
+                    )
+                })?;
             }
             FileType::Directory => {
                 if verbose {
@@ -141,3 +159,19 @@ pub fn extract(args: ExtractArgs, verbose: bool) -> Result<()> {
 
     Ok(())
 }
+
+/// Restores modification time and, on Unix, permissions stored in the archive.
+fn apply_metadata(file: &fs::File, metadata: reader::FileMetadata) -> Result<()> {
+    if let Some(modified) = metadata.modified {
+        file.set_modified(modified)?;
+    }
+
+    #[cfg(unix)]
+    if let Some(mode) = metadata.mode {
+        use std::os::unix::fs::PermissionsExt;
+        // setuid, setgid and sticky bits are never restored
+        file.set_permissions(fs::Permissions::from_mode(mode & 0o777))?;
+    }
+
+    Ok(())
+}
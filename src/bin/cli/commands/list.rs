@@ -7,6 +7,8 @@ use clap::Args;
 
 use flpak::{io_error, reader, FileType, Registry};
 
+use super::parse_options;
+
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)]
 pub struct ListArgs {
@@ -15,6 +17,12 @@ pub struct ListArgs {
     /// Archive format. Use 'list-formats' to see supported formats. If omitted, the format will be guessed.
     #[arg(short, long)]
     format: Option<String>,
+    /// Options
+    #[arg(short, long)]
+    options: Option<String>,
+    /// Password for encrypted archives
+    #[arg(short, long)]
+    password: Option<String>,
     /// Path to archive
     input_file: PathBuf,
 }
@@ -28,6 +36,7 @@ pub fn list(args: ListArgs) -> Result<()> {
             &args.input_file,
             reader::Options {
                 strict: args.strict,
+                params: parse_options(args.options, args.password),
             },
         )
         .map_err(|err| {
@@ -49,7 +58,17 @@ pub fn list(args: ListArgs) -> Result<()> {
         match file_type {
             FileType::RegularFile => {
                 let size = size.expect("regular file should have size");
-                println!("{size:>16} {}", name.display());
+                let attrs = rdr.file_attrs(index);
+
+                if attrs.is_empty() {
+                    println!("{size:>16} {}", name.display());
+                } else {
+                    let attrs: Vec<_> = attrs
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect();
+                    println!("{size:>16} {} ({})", name.display(), attrs.join(", "));
+                }
             }
             FileType::Directory => println!("{:>16} {}{}", " ", name.display(), MAIN_SEPARATOR),
         }
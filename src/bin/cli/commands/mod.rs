@@ -1,11 +1,36 @@
+use std::collections::HashMap;
+
 mod check;
 mod create;
+mod detect;
 mod extract;
 mod list;
 mod list_formats;
 
 pub use check::*;
 pub use create::*;
+pub use detect::*;
 pub use extract::*;
 pub use list::*;
 pub use list_formats::*;
+
+/// Parses `key=value,key=value` options. `--password` is a shortcut for `password=` option,
+/// which also allows commas in the password.
+pub fn parse_options(options: Option<String>, password: Option<String>) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+
+    if let Some(options) = options {
+        for option in options.split(',') {
+            let mut parts = option.splitn(2, '=');
+            let key = parts.next().expect("there should be key");
+            let val = parts.next().expect("there should be value");
+            map.insert(key.to_string(), val.to_string());
+        }
+    }
+
+    if let Some(password) = password {
+        map.insert(String::from("password"), password);
+    }
+
+    map
+}
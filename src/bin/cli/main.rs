@@ -27,6 +27,8 @@ pub enum Commands {
     Extract(commands::ExtractArgs),
     /// Create archive
     Create(commands::CreateArgs),
+    /// Detect archive format, the most likely first
+    Detect(commands::DetectArgs),
 }
 
 fn main() -> io::Result<()> {
@@ -53,6 +55,10 @@ fn main() -> io::Result<()> {
         Commands::Create(args) => {
             commands::create(args)?;
         }
+
+        Commands::Detect(args) => {
+            commands::detect(args)?;
+        }
     }
 
     Ok(())
@@ -1,8 +1,37 @@
+// https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dds-header
+
 use std::io;
 
-use crate::ReadEx;
+use crate::{io_error, ReadEx, WriteEx};
 
 pub const DDS_SIGNATURE: &str = "DDS ";
+pub const DDS_HEADER_SIZE: u32 = 124;
+pub const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+
+pub const DDSD_CAPS: u32 = 0x1;
+pub const DDSD_HEIGHT: u32 = 0x2;
+pub const DDSD_WIDTH: u32 = 0x4;
+pub const DDSD_PITCH: u32 = 0x8;
+pub const DDSD_PIXELFORMAT: u32 = 0x1000;
+pub const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+pub const DDSD_LINEARSIZE: u32 = 0x80000;
+
+pub const DDPF_ALPHAPIXELS: u32 = 0x1;
+pub const DDPF_ALPHA: u32 = 0x2;
+pub const DDPF_FOURCC: u32 = 0x4;
+pub const DDPF_RGB: u32 = 0x40;
+pub const DDPF_LUMINANCE: u32 = 0x20000;
+
+pub const DDSCAPS_COMPLEX: u32 = 0x8;
+pub const DDSCAPS_TEXTURE: u32 = 0x1000;
+pub const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+pub const DDSCAPS2_CUBEMAP: u32 = 0x200;
+pub const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+pub const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+pub const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+pub const DDS_DIMENSION_TEXTURE2D: u32 = 3;
 
 #[repr(C, packed)]
 pub struct Header {
@@ -53,7 +82,21 @@ impl Header {
     }
 
     pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
-        todo!();
+        w.write_u32_le(self.size)?;
+        w.write_u32_le(self.flags)?;
+        w.write_u32_le(self.height)?;
+        w.write_u32_le(self.width)?;
+        w.write_u32_le(self.pitch_or_linear_size)?;
+        w.write_u32_le(self.depth)?;
+        w.write_u32_le(self.mip_map_count)?;
+        w.write_u32_le_vec(&{ self.reserved_1 })?;
+        self.pixel_format.write(w)?;
+        w.write_u32_le(self.caps)?;
+        w.write_u32_le(self.caps2)?;
+        w.write_u32_le(self.caps3)?;
+        w.write_u32_le(self.caps4)?;
+        w.write_u32_le(self.reserved_2)?;
+        Ok(())
     }
 }
 
@@ -68,3 +111,387 @@ pub struct PixelFormat {
     bbit_mask: u32,
     abit_mask: u32,
 }
+
+impl PixelFormat {
+    const fn four_cc(four_cc: &[u8; 4]) -> Self {
+        Self {
+            size: DDS_PIXEL_FORMAT_SIZE,
+            flags: DDPF_FOURCC,
+            four_cc: u32::from_le_bytes(*four_cc),
+            rgb_bit_count: 0,
+            rbit_mask: 0,
+            gbit_mask: 0,
+            bbit_mask: 0,
+            abit_mask: 0,
+        }
+    }
+
+    const fn masks(flags: u32, rgb_bit_count: u32, masks: [u32; 4]) -> Self {
+        Self {
+            size: DDS_PIXEL_FORMAT_SIZE,
+            flags,
+            four_cc: 0,
+            rgb_bit_count,
+            rbit_mask: masks[0],
+            gbit_mask: masks[1],
+            bbit_mask: masks[2],
+            abit_mask: masks[3],
+        }
+    }
+
+    fn matches(&self, other: &Self) -> bool {
+        let (flags, other_flags) = (self.flags, other.flags);
+
+        if flags & DDPF_FOURCC != 0 || other_flags & DDPF_FOURCC != 0 {
+            return flags & DDPF_FOURCC == other_flags & DDPF_FOURCC && { self.four_cc } == {
+                other.four_cc
+            };
+        }
+
+        flags == other_flags
+            && { self.rgb_bit_count } == { other.rgb_bit_count }
+            && { self.rbit_mask } == { other.rbit_mask }
+            && { self.gbit_mask } == { other.gbit_mask }
+            && { self.bbit_mask } == { other.bbit_mask }
+            && { self.abit_mask } == { other.abit_mask }
+    }
+
+    pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_u32_le(self.size)?;
+        w.write_u32_le(self.flags)?;
+        w.write_u32_le(self.four_cc)?;
+        w.write_u32_le(self.rgb_bit_count)?;
+        w.write_u32_le(self.rbit_mask)?;
+        w.write_u32_le(self.gbit_mask)?;
+        w.write_u32_le(self.bbit_mask)?;
+        w.write_u32_le(self.abit_mask)?;
+        Ok(())
+    }
+}
+
+/// DX10 extended header. It follows [`Header`] if pixel format's four cc is `DX10`.
+pub struct Dx10Header {
+    pub dxgi_format: u32,
+    pub resource_dimension: u32,
+    pub misc_flag: u32,
+    pub array_size: u32,
+    pub misc_flags2: u32,
+}
+
+impl Dx10Header {
+    pub fn read(r: &mut impl io::BufRead) -> io::Result<Self> {
+        Ok(Self {
+            dxgi_format: r.read_u32_le()?,
+            resource_dimension: r.read_u32_le()?,
+            misc_flag: r.read_u32_le()?,
+            array_size: r.read_u32_le()?,
+            misc_flags2: r.read_u32_le()?,
+        })
+    }
+
+    pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_u32_le(self.dxgi_format)?;
+        w.write_u32_le(self.resource_dimension)?;
+        w.write_u32_le(self.misc_flag)?;
+        w.write_u32_le(self.array_size)?;
+        w.write_u32_le(self.misc_flags2)?;
+        Ok(())
+    }
+}
+
+enum Layout {
+    /// Block compressed format with given number of bytes per 4x4 block.
+    Block(u32),
+    /// Uncompressed format with given number of bits per pixel.
+    Bits(u32),
+}
+
+/// DXGI formats that can be described without DX10 extended header.
+const LEGACY_FORMATS: [u32; 14] = [28, 61, 65, 71, 74, 77, 80, 81, 83, 84, 85, 86, 87, 88];
+
+/// Returns memory layout and legacy pixel format for DXGI format.
+/// If there is no legacy pixel format, DX10 extended header should be used.
+fn format_info(dxgi_format: u32) -> Option<(Layout, Option<PixelFormat>)> {
+    let info = match dxgi_format {
+        // R32G32B32A32_FLOAT
+        2 => (Layout::Bits(128), None),
+        // R16G16B16A16_FLOAT, R16G16B16A16_UNORM
+        10 | 11 => (Layout::Bits(64), None),
+        // R10G10B10A2_UNORM, R16G16_FLOAT, R16G16_UNORM, R32_FLOAT
+        24 | 34 | 35 | 41 => (Layout::Bits(32), None),
+        // R8G8B8A8_UNORM
+        28 => (
+            Layout::Bits(32),
+            Some(PixelFormat::masks(
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                32,
+                [0x000000FF, 0x0000FF00, 0x00FF0000, 0xFF000000],
+            )),
+        ),
+        // R8G8B8A8_UNORM_SRGB
+        29 => (Layout::Bits(32), None),
+        // R8G8_UNORM, R16_FLOAT, R16_UNORM
+        49 | 54 | 56 => (Layout::Bits(16), None),
+        // R8_UNORM
+        61 => (
+            Layout::Bits(8),
+            Some(PixelFormat::masks(DDPF_LUMINANCE, 8, [0xFF, 0, 0, 0])),
+        ),
+        // A8_UNORM
+        65 => (
+            Layout::Bits(8),
+            Some(PixelFormat::masks(DDPF_ALPHA, 8, [0, 0, 0, 0xFF])),
+        ),
+        // BC1_UNORM
+        71 => (Layout::Block(8), Some(PixelFormat::four_cc(b"DXT1"))),
+        // BC1_UNORM_SRGB
+        72 => (Layout::Block(8), None),
+        // BC2_UNORM
+        74 => (Layout::Block(16), Some(PixelFormat::four_cc(b"DXT3"))),
+        // BC2_UNORM_SRGB
+        75 => (Layout::Block(16), None),
+        // BC3_UNORM
+        77 => (Layout::Block(16), Some(PixelFormat::four_cc(b"DXT5"))),
+        // BC3_UNORM_SRGB
+        78 => (Layout::Block(16), None),
+        // BC4_UNORM
+        80 => (Layout::Block(8), Some(PixelFormat::four_cc(b"ATI1"))),
+        // BC4_SNORM
+        81 => (Layout::Block(8), Some(PixelFormat::four_cc(b"BC4S"))),
+        // BC5_UNORM
+        83 => (Layout::Block(16), Some(PixelFormat::four_cc(b"ATI2"))),
+        // BC5_SNORM
+        84 => (Layout::Block(16), Some(PixelFormat::four_cc(b"BC5S"))),
+        // B5G6R5_UNORM
+        85 => (
+            Layout::Bits(16),
+            Some(PixelFormat::masks(
+                DDPF_RGB,
+                16,
+                [0xF800, 0x07E0, 0x001F, 0],
+            )),
+        ),
+        // B5G5R5A1_UNORM
+        86 => (
+            Layout::Bits(16),
+            Some(PixelFormat::masks(
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                16,
+                [0x7C00, 0x03E0, 0x001F, 0x8000],
+            )),
+        ),
+        // B8G8R8A8_UNORM
+        87 => (
+            Layout::Bits(32),
+            Some(PixelFormat::masks(
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                32,
+                [0x00FF0000, 0x0000FF00, 0x000000FF, 0xFF000000],
+            )),
+        ),
+        // B8G8R8X8_UNORM
+        88 => (
+            Layout::Bits(32),
+            Some(PixelFormat::masks(
+                DDPF_RGB,
+                32,
+                [0x00FF0000, 0x0000FF00, 0x000000FF, 0],
+            )),
+        ),
+        // B8G8R8A8_UNORM_SRGB, B8G8R8X8_UNORM_SRGB
+        91 | 93 => (Layout::Bits(32), None),
+        // BC6H_UF16, BC6H_SF16, BC7_UNORM, BC7_UNORM_SRGB
+        95 | 96 | 98 | 99 => (Layout::Block(16), None),
+        _ => return None,
+    };
+
+    Some(info)
+}
+
+/// Builds DDS headers for a 2D texture (or a cube map) described by its dimensions, mip count and DXGI format.
+pub fn make_headers(
+    width: u32,
+    height: u32,
+    mip_count: u32,
+    dxgi_format: u32,
+    cubemap: bool,
+) -> Option<(Header, Option<Dx10Header>)> {
+    let (layout, pixel_format) = format_info(dxgi_format)?;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_MIPMAPCOUNT;
+    let pitch_or_linear_size = match layout {
+        Layout::Block(block_size) => {
+            flags |= DDSD_LINEARSIZE;
+            width.div_ceil(4).max(1) * height.div_ceil(4).max(1) * block_size
+        }
+        Layout::Bits(bits_per_pixel) => {
+            flags |= DDSD_PITCH;
+            (width * bits_per_pixel).div_ceil(8)
+        }
+    };
+
+    let mut caps = DDSCAPS_TEXTURE;
+    let mut caps2 = 0;
+
+    if mip_count > 1 {
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+
+    if cubemap {
+        caps |= DDSCAPS_COMPLEX;
+        caps2 |= DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALL_FACES;
+    }
+
+    let dx10_header = match pixel_format {
+        Some(_) => None,
+        None => Some(Dx10Header {
+            dxgi_format,
+            resource_dimension: DDS_DIMENSION_TEXTURE2D,
+            misc_flag: if cubemap {
+                DDS_RESOURCE_MISC_TEXTURECUBE
+            } else {
+                0
+            },
+            array_size: 1,
+            misc_flags2: 0,
+        }),
+    };
+
+    let hdr = Header {
+        size: DDS_HEADER_SIZE,
+        flags,
+        height,
+        width,
+        pitch_or_linear_size,
+        depth: 0,
+        mip_map_count: mip_count,
+        reserved_1: [0; 11],
+        pixel_format: pixel_format.unwrap_or_else(|| PixelFormat::four_cc(b"DX10")),
+        caps,
+        caps2,
+        caps3: 0,
+        caps4: 0,
+        reserved_2: 0,
+    };
+
+    Some((hdr, dx10_header))
+}
+
+/// Texture description parsed from DDS headers.
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub mip_count: u32,
+    pub dxgi_format: u32,
+    pub cubemap: bool,
+}
+
+impl Texture {
+    /// Reads DDS signature and headers. The reader is left at the beginning of texture data.
+    pub fn read(r: &mut impl io::BufRead) -> io::Result<Self> {
+        let signature = r.read_u8_vec(4)?;
+
+        if signature != DDS_SIGNATURE.as_bytes() {
+            return Err(io_error!(InvalidData, "not a DDS file: invalid signature"));
+        }
+
+        let hdr = Header::read(r)?;
+
+        if hdr.size != DDS_HEADER_SIZE || hdr.pixel_format.size != DDS_PIXEL_FORMAT_SIZE {
+            return Err(io_error!(
+                InvalidData,
+                "not a DDS file: invalid header size"
+            ));
+        }
+
+        if hdr.caps2 & DDSCAPS2_VOLUME != 0 {
+            return Err(io_error!(Unsupported, "volume textures are not supported"));
+        }
+
+        let is_dx10 = hdr.pixel_format.flags & DDPF_FOURCC != 0 && { hdr.pixel_format.four_cc }
+            == u32::from_le_bytes(*b"DX10");
+
+        let (dxgi_format, cubemap) = if is_dx10 {
+            let dx10_hdr = Dx10Header::read(r)?;
+
+            if dx10_hdr.array_size > 1 {
+                return Err(io_error!(Unsupported, "texture arrays are not supported"));
+            }
+
+            (
+                dx10_hdr.dxgi_format,
+                dx10_hdr.misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0,
+            )
+        } else {
+            let dxgi_format = LEGACY_FORMATS
+                .into_iter()
+                .find(|&format| {
+                    matches!(format_info(format), Some((_, Some(pf))) if pf.matches(&hdr.pixel_format))
+                })
+                .ok_or_else(|| io_error!(Unsupported, "unsupported pixel format"))?;
+
+            (dxgi_format, hdr.caps2 & DDSCAPS2_CUBEMAP != 0)
+        };
+
+        if format_info(dxgi_format).is_none() {
+            return Err(io_error!(
+                Unsupported,
+                "DXGI format {dxgi_format} is not supported"
+            ));
+        }
+
+        if cubemap && hdr.caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+            return Err(io_error!(
+                Unsupported,
+                "cube maps without all six faces are not supported"
+            ));
+        }
+
+        let mip_count = if hdr.flags & DDSD_MIPMAPCOUNT != 0 {
+            hdr.mip_map_count.max(1)
+        } else {
+            1
+        };
+
+        // every level halves the larger dimension until it is 1
+        let max_mip_count = u32::BITS - hdr.width.max(hdr.height).max(1).leading_zeros();
+
+        if mip_count > max_mip_count {
+            return Err(io_error!(
+                InvalidData,
+                "{mip_count} mip levels for {}x{} texture, expected at most {max_mip_count}",
+                { hdr.width },
+                { hdr.height }
+            ));
+        }
+
+        Ok(Self {
+            width: hdr.width,
+            height: hdr.height,
+            mip_count,
+            dxgi_format,
+            cubemap,
+        })
+    }
+
+    /// Returns data size of every mip level of a single face.
+    pub fn mip_sizes(&self) -> Vec<u64> {
+        let (layout, _) = format_info(self.dxgi_format).expect("format should be supported");
+
+        (0..self.mip_count)
+            .map(|level| {
+                let width = u64::from((self.width >> level).max(1));
+                let height = u64::from((self.height >> level).max(1));
+
+                match layout {
+                    Layout::Block(block_size) => {
+                        width.div_ceil(4).max(1) * height.div_ceil(4).max(1) * u64::from(block_size)
+                    }
+                    Layout::Bits(bits_per_pixel) => {
+                        (width * u64::from(bits_per_pixel)).div_ceil(8) * height
+                    }
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use crate::PathBufUtils;
+
+/// CRC32 used by Bethesda: standard polynomial, but zero initial value and no final xor.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new_with_initial(u32::MAX);
+    hasher.update(bytes);
+    !hasher.finalize()
+}
+
+/// Calculates file name hash, extension and directory hash of a file path.
+pub fn file_hashes(path: &Path) -> (u32, [u8; 4], u32) {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let dir = path
+        .parent()
+        .map(|dir| dir.to_path_buf().try_to_win().unwrap_or_default())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let (stem, ext) = file_name.rsplit_once('.').unwrap_or((&file_name, ""));
+
+    let mut ext_buf = [0u8; 4];
+    let ext_len = ext.len().min(4);
+    ext_buf[..ext_len].copy_from_slice(&ext.as_bytes()[..ext_len]);
+
+    (crc32(stem.as_bytes()), ext_buf, crc32(dir.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::PathBufUtils;
+
+    use super::{crc32, file_hashes};
+
+    #[test]
+    fn calc_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"a"), 0x3AB551CE);
+    }
+
+    #[test]
+    fn calc_file_hashes() {
+        let (name_hash, ext, dir_hash) =
+            file_hashes(&PathBuf::from_win("Meshes\\Armor\\Helmet.NIF"));
+
+        assert_eq!(name_hash, crc32(b"helmet"));
+        assert_eq!(&ext, b"nif\0");
+        assert_eq!(dir_hash, crc32(b"meshes\\armor"));
+    }
+}
@@ -1,9 +1,14 @@
 // https://wiki.nexusmods.com/index.php/Bethesda_mod_archives
 
-// mod dds;
+mod dds;
+mod hash;
 mod reader;
-//#[cfg(test)]
-//mod reader_tests;
+#[cfg(test)]
+mod reader_tests;
 mod records;
+mod writer;
+#[cfg(test)]
+mod writer_tests;
 
-pub use reader::make_reader;
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -1,6 +1,6 @@
 use std::{
     fs,
-    io::{BufReader, Read, Seek, SeekFrom},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
     str,
 };
@@ -8,17 +8,31 @@ use std::{
 use encoding_rs::WINDOWS_1252;
 use libflate::zlib;
 
-use crate::{FileType, PathBufUtils, ReadEx};
+use crate::{io_error, FileType, PathBufUtils, ReadEx};
 
-use super::records::{
-    GeneralBlock, Header, TextureBlock, TextureChunk, TextureInfo, BA2_SIGNATURE,
+use super::{
+    dds::{self, DDS_SIGNATURE},
+    hash::file_hashes,
+    records::{
+        GeneralBlock, GnmfBlock, Header, HeaderExt, TextureBlock, TextureChunk, TextureInfo,
+        BA2_PADDING, BA2_SIGNATURE, COMPRESSION_LZ4, COMPRESSION_ZLIB, GNF_HEADER_SIZE,
+        GNF_SIGNATURE, SUPPORTED_VERSIONS,
+    },
 };
 
+#[derive(Clone, Copy)]
+enum Compression {
+    Zlib,
+    /// Raw LZ4 blocks, used by some Starfield archives.
+    Lz4Block,
+}
+
 pub struct Reader {
     stm: BufReader<fs::File>,
     general_files: Vec<GeneralBlock>,
     texture_files: Vec<TextureInfo>,
     names: Vec<PathBuf>,
+    compression: Compression,
 }
 
 impl Reader {
@@ -35,24 +49,40 @@ impl Reader {
             });
         }
 
-        if hdr.version != 1 {
+        if !SUPPORTED_VERSIONS.contains(&{ hdr.version }) {
             return Err(crate::reader::Error::UnsupportedVersion {
                 version: hdr.version,
-                supported_versions: &[1u32],
+                supported_versions: SUPPORTED_VERSIONS,
             });
         }
 
+        let hdr_ext =
+            HeaderExt::read(&mut stm, hdr.version).map_err(crate::reader::Error::ReadingHeader)?;
+
+        let compression = match hdr_ext.map_or(COMPRESSION_ZLIB, |ext| ext.compression) {
+            COMPRESSION_ZLIB => Compression::Zlib,
+            COMPRESSION_LZ4 => Compression::Lz4Block,
+            method => {
+                return Err(crate::reader::Error::Unsupported(format!(
+                    "compression method {method} is not supported"
+                )))
+            }
+        };
+
         let archive_type = str::from_utf8(&hdr.archive_type)
             .map_err(|_| crate::reader::Error::Other("invalid block type".into()))?;
 
         let mut general_files = Vec::new();
         let mut texture_files = Vec::new();
+        // name hash, extension and directory hash of every file
+        let mut hashes = Vec::new();
 
         match archive_type {
             "GNRL" => {
                 for _ in 0..hdr.num_files {
                     let rec = GeneralBlock::read(&mut stm)
                         .map_err(crate::reader::Error::ReadingInputFile)?;
+                    hashes.push((rec.name_hash, rec.ext, rec.dir_hash));
                     general_files.push(rec);
                 }
             }
@@ -60,28 +90,35 @@ impl Reader {
                 for _ in 0..hdr.num_files {
                     let texture = TextureBlock::read(&mut stm)
                         .map_err(crate::reader::Error::ReadingInputFile)?;
-                    let mut chunks = Vec::new();
-
-                    for _ in 0..texture.num_chunks {
-                        let texture_chunk = TextureChunk::read(&mut stm)
-                            .map_err(crate::reader::Error::ReadingInputFile)?;
-                        chunks.push(texture_chunk);
-                    }
+                    hashes.push((texture.name_hash, texture.ext, texture.dir_hash));
+                    let chunks = read_texture_chunks(&mut stm, texture.num_chunks)?;
 
                     texture_files.push(TextureInfo {
-                        hdr: texture,
+                        width: texture.width.into(),
+                        height: texture.height.into(),
+                        mip_count: texture.num_mips.into(),
+                        format: texture.format,
+                        header: make_dds_header(&texture),
                         chunks,
                     });
                 }
-
-                return Err(crate::reader::Error::Unsupported(
-                    "texture archives are not supported".into(),
-                ));
             }
             "GNMF" => {
-                return Err(crate::reader::Error::Unsupported(
-                    "GNMF archives are not supported".into(),
-                ));
+                for _ in 0..hdr.num_files {
+                    let texture = GnmfBlock::read(&mut stm)
+                        .map_err(crate::reader::Error::ReadingInputFile)?;
+                    hashes.push((texture.name_hash, texture.ext, texture.dir_hash));
+                    let chunks = read_texture_chunks(&mut stm, texture.num_chunks)?;
+
+                    texture_files.push(TextureInfo {
+                        width: texture.width(),
+                        height: texture.height(),
+                        mip_count: texture.mip_count(),
+                        format: 0,
+                        header: Some(make_gnf_header(&texture, chunks_size(&chunks))),
+                        chunks,
+                    });
+                }
             }
             _ => {
                 return Err(crate::reader::Error::InvalidHeader(format!(
@@ -96,24 +133,33 @@ impl Reader {
 
         let mut names = Vec::new();
 
-        for _ in 0..hdr.num_files {
+        for record_hashes in hashes {
             let name = stm
                 .read_u16le_string(WINDOWS_1252)
                 .map_err(crate::reader::Error::ReadingFileName)?;
-            names.push(PathBuf::from_win(&name));
+            let mut name = PathBuf::from_win(&name);
+
+            if options.strict {
+                verify_file_hashes(&name, record_hashes)?;
+            }
+
+            // textures keep their `.dds` names in GNMF archives, but are extracted as GNF
+            if archive_type == "GNMF"
+                && name
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("dds"))
+            {
+                name.set_extension("gnf");
+            }
+
+            names.push(name);
         }
 
         if options.strict {
-            for (_index, file) in general_files.iter().enumerate() {
-                if file.padding != 0xBAADF00D {
+            for file in &general_files {
+                if file.padding != BA2_PADDING {
                     return Err(crate::reader::Error::Other("invalid padding".into()));
                 }
-
-                // let name = &names[index];
-                // let (dir, fname) = name.rsplit_once('/').unwrap_or_else(|| ("", name.as_str()));
-                // let (fname, _ext) = fname.rsplit_once('.').unwrap_or_else(|| (fname, ""));
-                // let dir_crc = crc32fast::hash(dir.to_lowercase().as_bytes());
-                // let fname_crc = crc32fast::hash(fname.to_lowercase().as_bytes());
             }
         }
 
@@ -122,6 +168,7 @@ impl Reader {
             general_files,
             texture_files,
             names,
+            compression,
         })
     }
 }
@@ -155,10 +202,12 @@ impl crate::reader::Reader for Reader {
                 .get(index)
                 .expect("`index` should be within boundaries");
 
+            let header_size = file.header.as_ref().map_or(0, Vec::len);
+
             crate::reader::File {
                 name: self.names[index].clone(),
                 file_type: FileType::RegularFile,
-                size: Some(file.chunks[0].unpacked_size.into()),
+                size: Some(header_size as u64 + chunks_size(&file.chunks)),
             }
         }
     }
@@ -177,29 +226,262 @@ impl crate::reader::Reader for Reader {
                 .seek(SeekFrom::Start(file.offset))
                 .map_err(crate::reader::Error::ReadingInputFile)?;
 
+            // packed size of 0 means the file is stored uncompressed
+            if file.packed_size == 0 {
+                return Ok(Box::new(self.stm.by_ref().take(file.unpacked_size.into())));
+            }
+
             let stm = self.stm.by_ref().take(file.packed_size.into());
-            let rdr = zlib::Decoder::new(stm).map_err(crate::reader::Error::ReadingInputFile)?;
 
-            Ok(Box::new(rdr))
+            match self.compression {
+                Compression::Zlib => {
+                    let rdr =
+                        zlib::Decoder::new(stm).map_err(crate::reader::Error::ReadingInputFile)?;
+                    Ok(Box::new(rdr))
+                }
+                Compression::Lz4Block => {
+                    let data = decompress_lz4_block(stm, file.unpacked_size)
+                        .map_err(crate::reader::Error::ReadingInputFile)?;
+                    Ok(Box::new(Cursor::new(data)))
+                }
+            }
+        } else {
+            let file = self
+                .texture_files
+                .get(index)
+                .expect("`index` should be within boundaries");
+
+            let Some(header) = &file.header else {
+                return Err(crate::reader::Error::Unsupported(format!(
+                    "DXGI format {} is not supported",
+                    file.format
+                )));
+            };
+
+            Ok(Box::new(TextureReader {
+                stm: &mut self.stm,
+                chunks: &file.chunks,
+                compression: self.compression,
+                next_chunk: 0,
+                buf: Cursor::new(header.clone()),
+            }))
+        }
+    }
+
+    fn file_attrs(&self, index: usize) -> Vec<(&'static str, String)> {
+        if let Some(file) = self.general_files.get(index) {
+            return self.packing_attrs(&[(file.packed_size, file.unpacked_size)]);
+        }
+
+        let file = self
+            .texture_files
+            .get(index)
+            .expect("`index` should be within boundaries");
+
+        let sizes: Vec<_> = file
+            .chunks
+            .iter()
+            .map(|chunk| (chunk.packed_size, chunk.unpacked_size))
+            .collect();
+
+        let mut attrs = vec![
+            ("width", file.width.to_string()),
+            ("height", file.height.to_string()),
+            ("mips", file.mip_count.to_string()),
+        ];
+        attrs.extend(self.packing_attrs(&sizes));
+        attrs
+    }
+}
+
+impl Reader {
+    /// Describes how data is stored given packed and unpacked sizes of its parts.
+    /// Parts with packed size of 0 are stored uncompressed.
+    fn packing_attrs(&self, sizes: &[(u32, u32)]) -> Vec<(&'static str, String)> {
+        let compressed = sizes.iter().any(|&(packed_size, _)| packed_size != 0);
+        let packed_size: u64 = sizes
+            .iter()
+            .map(|&(packed_size, unpacked_size)| {
+                u64::from(if packed_size == 0 {
+                    unpacked_size
+                } else {
+                    packed_size
+                })
+            })
+            .sum();
+
+        let compression = match (compressed, self.compression) {
+            (false, _) => "none",
+            (true, Compression::Zlib) => "zlib",
+            (true, Compression::Lz4Block) => "lz4",
+        };
+
+        vec![
+            ("compression", compression.to_string()),
+            ("packed", packed_size.to_string()),
+        ]
+    }
+}
+
+/// Compares hashes and extension stored in a file record with the ones calculated from the file name.
+fn verify_file_hashes(
+    name: &Path,
+    (name_hash, ext, dir_hash): (u32, [u8; 4], u32),
+) -> crate::reader::Result<()> {
+    let (expected_name_hash, expected_ext, expected_dir_hash) = file_hashes(name);
+
+    if name_hash != expected_name_hash {
+        return Err(crate::reader::Error::InvalidFileNameHash {
+            filename: name.to_path_buf(),
+            hash: format!("{name_hash:08x}"),
+            expected_hash: format!("{expected_name_hash:08x}"),
+        });
+    }
+
+    if dir_hash != expected_dir_hash {
+        return Err(crate::reader::Error::InvalidDirectoryNameHash {
+            filename: name.to_path_buf(),
+            hash: format!("{dir_hash:08x}"),
+            expected_hash: format!("{expected_dir_hash:08x}"),
+        });
+    }
+
+    if ext != expected_ext {
+        return Err(crate::reader::Error::InvalidFileExtension {
+            filename: name.to_path_buf(),
+            ext: String::from_utf8_lossy(&ext)
+                .trim_end_matches('\0')
+                .to_string(),
+            expected_ext: String::from_utf8_lossy(&expected_ext)
+                .trim_end_matches('\0')
+                .to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn read_texture_chunks(
+    stm: &mut impl io::BufRead,
+    count: u8,
+) -> crate::reader::Result<Vec<TextureChunk>> {
+    (0..count)
+        .map(|_| TextureChunk::read(stm).map_err(crate::reader::Error::ReadingInputFile))
+        .collect()
+}
+
+fn chunks_size(chunks: &[TextureChunk]) -> u64 {
+    chunks
+        .iter()
+        .map(|chunk| u64::from(chunk.unpacked_size))
+        .sum()
+}
+
+/// Builds DDS file header for a texture.
+fn make_dds_header(texture: &TextureBlock) -> Option<Vec<u8>> {
+    let (hdr, dx10_hdr) = dds::make_headers(
+        texture.width.into(),
+        texture.height.into(),
+        texture.num_mips.into(),
+        texture.format.into(),
+        texture.is_cubemap(),
+    )?;
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(DDS_SIGNATURE.as_bytes());
+    hdr.write(&mut buf).expect("writing to memory buffer");
+
+    if let Some(dx10_hdr) = dx10_hdr {
+        dx10_hdr.write(&mut buf).expect("writing to memory buffer");
+    }
+
+    Some(buf)
+}
+
+/// Builds GNF file header for a texture. The header is padded to 256 bytes, texture data follows it.
+fn make_gnf_header(texture: &GnmfBlock, data_size: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(GNF_HEADER_SIZE as usize);
+    buf.extend_from_slice(GNF_SIGNATURE.as_bytes());
+    // size of the header after this field
+    buf.extend_from_slice(&(GNF_HEADER_SIZE - 8).to_le_bytes());
+    // version, texture count, alignment (log2), unused
+    buf.extend_from_slice(&[2, 1, 8, 0]);
+    // size of the whole file
+    let stream_size = u32::try_from(u64::from(GNF_HEADER_SIZE) + data_size).unwrap_or(u32::MAX);
+    buf.extend_from_slice(&stream_size.to_le_bytes());
+    buf.extend_from_slice(&texture.descriptor);
+    buf.resize(GNF_HEADER_SIZE as usize, 0);
+    buf
+}
+
+fn decompress_lz4_block(mut stm: impl Read, unpacked_size: u32) -> io::Result<Vec<u8>> {
+    let mut packed = Vec::new();
+    stm.read_to_end(&mut packed)?;
+
+    let unpacked_size = usize::try_from(unpacked_size).expect("should fit into `usize`");
+    lz4_flex::block::decompress(&packed, unpacked_size)
+        .map_err(|err| io_error!(InvalidData, "failed to decompress LZ4 block: {err}"))
+}
+
+/// Reads DDS or GNF header followed by texture chunks, decompressing one chunk at a time.
+struct TextureReader<'a> {
+    stm: &'a mut BufReader<fs::File>,
+    chunks: &'a [TextureChunk],
+    compression: Compression,
+    next_chunk: usize,
+    buf: Cursor<Vec<u8>>,
+}
+
+impl TextureReader<'_> {
+    fn read_next_chunk(&mut self) -> io::Result<bool> {
+        let Some(chunk) = self.chunks.get(self.next_chunk) else {
+            return Ok(false);
+        };
+
+        self.next_chunk += 1;
+        self.stm.seek(SeekFrom::Start(chunk.offset))?;
+
+        let unpacked_size = usize::try_from(chunk.unpacked_size).expect("should fit into `usize`");
+        let mut data = Vec::with_capacity(unpacked_size);
+
+        if chunk.packed_size == 0 {
+            self.stm
+                .by_ref()
+                .take(chunk.unpacked_size.into())
+                .read_to_end(&mut data)?;
         } else {
-            Err(crate::reader::Error::Unsupported(
-                "texture archives are not supported".into(),
-            ))
-            /*
-                        let file = self
-                            .texture_files
-                            .get(index)
-                            .expect("`index` should be within boundaries");
-                        let chuck = &file.chunks[0];
-
-                        self.stm.seek(SeekFrom::Start(chuck.offset))
-                            .map_err(crate::reader::Error::ReadingInputFile)?;
-
-                        let stm = self.stm.by_ref().take(chuck.packed_size.into());
-                        let rdr = zlib::Decoder::new(stm)
-                            .map_err(crate::reader::Error::ReadingInputFile)?;
-                        return Ok(Box::new(rdr))
-            */
+            let stm = self.stm.by_ref().take(chunk.packed_size.into());
+
+            match self.compression {
+                Compression::Zlib => {
+                    zlib::Decoder::new(stm)?.read_to_end(&mut data)?;
+                }
+                Compression::Lz4Block => data = decompress_lz4_block(stm, chunk.unpacked_size)?,
+            }
+        }
+
+        if data.len() != unpacked_size {
+            return Err(io_error!(
+                InvalidData,
+                "texture chunk at offset {} has {} bytes, expected {unpacked_size}",
+                { chunk.offset },
+                data.len(),
+            ));
+        }
+
+        self.buf = Cursor::new(data);
+        Ok(true)
+    }
+}
+
+impl Read for TextureReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.buf.read(buf)?;
+
+            if len > 0 || buf.is_empty() || !self.read_next_chunk()? {
+                return Ok(len);
+            }
         }
     }
 }
@@ -210,3 +492,39 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let hdr = Header::read(&mut BufReader::new(file))?;
+
+    let (version, names_offset) = (hdr.version, hdr.names_offset);
+
+    if hdr.signature != BA2_SIGNATURE.as_bytes() {
+        return Ok(crate::Probe::Invalid(String::from("invalid signature")));
+    }
+
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        return Ok(crate::Probe::Invalid(format!(
+            "unsupported version {version}"
+        )));
+    }
+
+    let archive_type = String::from_utf8_lossy(&hdr.archive_type);
+
+    if !["GNRL", "DX10", "GNMF"].contains(&archive_type.as_ref()) {
+        return Ok(crate::Probe::Invalid(format!(
+            "unknown archive type '{archive_type}'"
+        )));
+    }
+
+    if names_offset > file_size {
+        return Ok(crate::Probe::Invalid(format!(
+            "names offset {names_offset} is out of bounds"
+        )));
+    }
+
+    Ok(crate::Probe::Valid(format!(
+        "version {version}, {archive_type}"
+    )))
+}
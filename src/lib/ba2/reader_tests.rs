@@ -1,11 +1,185 @@
-use std::path::Path;
+use std::{collections::HashMap, fs, io::Read, path::Path};
+
+use rstest::*;
+use tempdir::TempDir;
+
+use crate::{reader::Error, InputFileListBuilder};
 
 #[test]
 fn correct_general_archive() {
     let registry = super::make_reader(
-        Path::new("./samples/bs2/correct_general.ba2"),
-        crate::reader::Options { strict: true },
+        Path::new("./samples/ba2/correct_general.ba2"),
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
+    );
+
+    assert!(registry.is_ok());
+}
+
+#[test]
+fn correct_texture_archive() {
+    let mut rdr = super::make_reader(
+        Path::new("./samples/ba2/correct_dx10.ba2"),
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(rdr.file_count(), 3);
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(Path::new("./samples/ba2/dx10").join(&file.name)).unwrap();
+
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(file.size, Some(expected.len() as u64));
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
+
+#[test]
+fn correct_gnmf_archive() {
+    let mut rdr = super::make_reader(
+        Path::new("./samples/ba2/correct_gnmf.ba2"),
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(rdr.file_count(), 2);
+
+    let file = rdr.get_file(0);
+    assert_eq!(file.name, Path::new("textures/ps4.gnf"));
+    assert_eq!(
+        rdr.file_attrs(0),
+        [
+            ("width", String::from("64")),
+            ("height", String::from("32")),
+            ("mips", String::from("2")),
+            ("compression", String::from("zlib")),
+            ("packed", String::from("542"))
+        ]
     );
 
-    assert_eq!(registry.is_ok(), true);
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(Path::new("./samples/ba2/gnmf").join(&file.name)).unwrap();
+
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(file.size, Some(expected.len() as u64));
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
+
+#[rstest]
+#[case("general_v2.ba2", "general")]
+#[case("general_v3_lz4.ba2", "general")]
+#[case("general_v7.ba2", "general")]
+#[case("general_v8.ba2", "general")]
+#[case("dx10_v3_lz4.ba2", "dx10")]
+fn versions(#[case] name: &str, #[case] src_dir: &str) {
+    let src_dir = Path::new("./samples/ba2").join(src_dir);
+    let mut rdr = super::make_reader(
+        &Path::new("./samples/ba2").join(name),
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert!(rdr.file_count() > 0);
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(src_dir.join(&file.name)).unwrap();
+
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
+
+#[rstest]
+#[case(0, "name hash")]
+#[case(4, "extension")]
+#[case(8, "directory hash")]
+fn invalid_hashes(#[case] offset: usize, #[case] field: &str) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/ba2/general"))
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let written_path = dir.path().join("archive.ba2");
+    super::create_archive(input_files, &written_path, &HashMap::new()).unwrap();
+
+    // archives with the header sizes of every version, `meshes/notes.txt` is the first file
+    let archives = [
+        (written_path, 24),
+        (Path::new("./samples/ba2/general_v2.ba2").to_path_buf(), 32),
+        (
+            Path::new("./samples/ba2/general_v3_lz4.ba2").to_path_buf(),
+            36,
+        ),
+        (Path::new("./samples/ba2/general_v7.ba2").to_path_buf(), 24),
+        (Path::new("./samples/ba2/general_v8.ba2").to_path_buf(), 24),
+    ];
+
+    for (src_path, header_size) in archives {
+        // corrupt a field of the first file record
+        let path = dir.path().join("corrupted.ba2");
+        let mut data = fs::read(&src_path).unwrap();
+        data[header_size + offset] ^= 0x01;
+        fs::write(&path, data).unwrap();
+
+        assert!(super::make_reader(
+            &path,
+            crate::reader::Options {
+                strict: false,
+                ..Default::default()
+            }
+        )
+        .is_ok());
+
+        let res = super::make_reader(
+            &path,
+            crate::reader::Options {
+                strict: true,
+                ..Default::default()
+            },
+        );
+
+        let filename = match (field, res) {
+            ("name hash", Err(Error::InvalidFileNameHash { filename, .. }))
+            | ("extension", Err(Error::InvalidFileExtension { filename, .. }))
+            | ("directory hash", Err(Error::InvalidDirectoryNameHash { filename, .. })) => filename,
+            (_, res) => panic!(
+                "expected {field} mismatch in {}, got {:?}",
+                src_path.display(),
+                res.err()
+            ),
+        };
+        assert_eq!(filename, Path::new("meshes/notes.txt"), "{field}");
+    }
 }
@@ -1,8 +1,21 @@
 use std::io;
 
-use crate::ReadEx;
+use crate::{ReadEx, WriteEx};
 
 pub const BA2_SIGNATURE: &str = "BTDX";
+pub const BA2_PADDING: u32 = 0xBAADF00D;
+pub const HEADER_SIZE: u64 = 24;
+/// Fallout 4 (1), Starfield (2, 3), Fallout 4 next-gen update (7, 8).
+pub const SUPPORTED_VERSIONS: &[u32] = &[1, 2, 3, 7, 8];
+pub const COMPRESSION_ZLIB: u32 = 0;
+pub const COMPRESSION_LZ4: u32 = 3;
+pub const GENERAL_BLOCK_SIZE: u64 = 36;
+pub const GENERAL_BLOCK_FLAGS: u32 = 0x00100100;
+pub const TEXTURE_BLOCK_SIZE: u64 = 24;
+pub const TEXTURE_CHUNK_SIZE: u16 = 24;
+pub const TEXTURE_FLAGS: u16 = 0x800;
+pub const GNF_SIGNATURE: &str = "GNF ";
+pub const GNF_HEADER_SIZE: u32 = 0x100;
 
 #[repr(C, packed)]
 pub struct Header {
@@ -25,6 +38,61 @@ impl Header {
 
         Ok(hdr)
     }
+
+    pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_all(&self.signature)?;
+        w.write_u32_le(self.version)?;
+        w.write_all(&self.archive_type)?;
+        w.write_u32_le(self.num_files)?;
+        w.write_u64_le(self.names_offset)?;
+        Ok(())
+    }
+}
+
+/// Header fields that follow [`Header`] in Starfield archives.
+pub struct HeaderExt {
+    pub unknown_1: u64, // always 1
+    /// Present in version 3 only, zlib otherwise.
+    pub compression: u32,
+}
+
+impl HeaderExt {
+    /// Size of the whole header, including [`Header`].
+    pub const fn header_size(version: u32) -> u64 {
+        match version {
+            2 => HEADER_SIZE + 8,
+            3 => HEADER_SIZE + 12,
+            _ => HEADER_SIZE,
+        }
+    }
+
+    pub fn read(r: &mut impl io::BufRead, version: u32) -> io::Result<Option<Self>> {
+        if !matches!(version, 2 | 3) {
+            return Ok(None);
+        }
+
+        let unknown_1 = r.read_u64_le()?;
+        let compression = if version == 3 {
+            r.read_u32_le()?
+        } else {
+            COMPRESSION_ZLIB
+        };
+
+        Ok(Some(Self {
+            unknown_1,
+            compression,
+        }))
+    }
+
+    pub fn write(&self, w: &mut impl io::Write, version: u32) -> io::Result<()> {
+        w.write_u64_le(self.unknown_1)?;
+
+        if version == 3 {
+            w.write_u32_le(self.compression)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[repr(C, packed)]
@@ -36,7 +104,7 @@ pub struct GeneralBlock {
     pub offset: u64,
     pub packed_size: u32,
     pub unpacked_size: u32,
-    pub padding: u32, // always 0xBAADFOOD
+    pub padding: u32, // always `BA2_PADDING`
 }
 
 impl GeneralBlock {
@@ -55,6 +123,18 @@ impl GeneralBlock {
 
         Ok(hdr)
     }
+
+    pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_u32_le(self.name_hash)?;
+        w.write_all(&self.ext)?;
+        w.write_u32_le(self.dir_hash)?;
+        w.write_u32_le(self.unknown_1)?;
+        w.write_u64_le(self.offset)?;
+        w.write_u32_le(self.packed_size)?;
+        w.write_u32_le(self.unpacked_size)?;
+        w.write_u32_le(self.padding)?;
+        Ok(())
+    }
 }
 
 #[repr(C, packed)]
@@ -69,7 +149,7 @@ pub struct TextureBlock {
     pub width: u16,
     pub num_mips: u8,
     pub format: u8,
-    pub unknown_2: u16,
+    pub flags: u16, // 0x800, the lowest bit is set for cube maps
 }
 
 impl TextureBlock {
@@ -82,11 +162,30 @@ impl TextureBlock {
             rec.chunk_hdr_size = rec.chunk_hdr_size.swap_bytes();
             rec.height = rec.height.swap_bytes();
             rec.width = rec.width.swap_bytes();
-            rec.unknown_2 = rec.unknown_2.swap_bytes();
+            rec.flags = rec.flags.swap_bytes();
         }
 
         Ok(rec)
     }
+
+    pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_u32_le(self.name_hash)?;
+        w.write_all(&self.ext)?;
+        w.write_u32_le(self.dir_hash)?;
+        w.write_u8(self.unknown_1)?;
+        w.write_u8(self.num_chunks)?;
+        w.write_u16_le(self.chunk_hdr_size)?;
+        w.write_u16_le(self.height)?;
+        w.write_u16_le(self.width)?;
+        w.write_u8(self.num_mips)?;
+        w.write_u8(self.format)?;
+        w.write_u16_le(self.flags)?;
+        Ok(())
+    }
+
+    pub fn is_cubemap(&self) -> bool {
+        self.flags & 1 != 0
+    }
 }
 
 #[repr(C, packed)]
@@ -96,7 +195,7 @@ pub struct TextureChunk {
     pub unpacked_size: u32,
     pub start_mip: u16,
     pub end_mip: u16,
-    pub unknown_1: u32,
+    pub padding: u32, // always `BA2_PADDING`
 }
 
 impl TextureChunk {
@@ -109,14 +208,76 @@ impl TextureChunk {
             rec.unpacked_size = rec.unpacked_size.swap_bytes();
             rec.start_mip = rec.start_mip.swap_bytes();
             rec.end_mip = rec.end_mip.swap_bytes();
-            rec.unknown_1 = rec.unknown_1.swap_bytes();
+            rec.padding = rec.padding.swap_bytes();
         }
 
         Ok(rec)
     }
+
+    pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_u64_le(self.offset)?;
+        w.write_u32_le(self.packed_size)?;
+        w.write_u32_le(self.unpacked_size)?;
+        w.write_u16_le(self.start_mip)?;
+        w.write_u16_le(self.end_mip)?;
+        w.write_u32_le(self.padding)?;
+        Ok(())
+    }
+}
+
+/// GNMF (PS4) texture record. Texture chunks follow it the same way they follow [`TextureBlock`].
+#[repr(C, packed)]
+pub struct GnmfBlock {
+    pub name_hash: u32,
+    pub ext: [u8; 4],
+    pub dir_hash: u32,
+    pub unknown_1: u8,
+    pub num_chunks: u8,
+    pub chunk_hdr_size: u16,
+    /// `sce::Gnm::Texture` resource descriptor, 8 little endian dwords.
+    pub descriptor: [u8; 32],
+}
+
+impl GnmfBlock {
+    pub fn read(r: &mut impl io::BufRead) -> io::Result<Self> {
+        let mut rec: Self = r.read_c_struct()?;
+
+        if cfg!(target_endian = "big") {
+            rec.name_hash = rec.name_hash.swap_bytes();
+            rec.dir_hash = rec.dir_hash.swap_bytes();
+            rec.chunk_hdr_size = rec.chunk_hdr_size.swap_bytes();
+        }
+
+        Ok(rec)
+    }
+
+    fn descriptor_dword(&self, index: usize) -> u32 {
+        let bytes = &self.descriptor[index * 4..index * 4 + 4];
+        u32::from_le_bytes(bytes.try_into().expect("should be 4 bytes long"))
+    }
+
+    pub fn width(&self) -> u32 {
+        (self.descriptor_dword(2) & 0x3FFF) + 1
+    }
+
+    pub fn height(&self) -> u32 {
+        ((self.descriptor_dword(2) >> 14) & 0x3FFF) + 1
+    }
+
+    pub fn mip_count(&self) -> u32 {
+        let base_level = (self.descriptor_dword(3) >> 12) & 0xF;
+        let last_level = (self.descriptor_dword(3) >> 16) & 0xF;
+        last_level.saturating_sub(base_level) + 1
+    }
 }
 
 pub struct TextureInfo {
-    pub hdr: TextureBlock,
+    pub width: u32,
+    pub height: u32,
+    pub mip_count: u32,
+    /// DXGI format for DX10 textures, used in error messages.
+    pub format: u8,
     pub chunks: Vec<TextureChunk>,
+    /// DDS or GNF signature and headers. `None` if DXGI format is not supported.
+    pub header: Option<Vec<u8>>,
 }
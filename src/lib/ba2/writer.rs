@@ -0,0 +1,517 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    io_error, writer, writer::CompressionLevel, FileType, InputFileList, Overhead, PathBufUtils,
+    WriteEx,
+};
+
+use super::{
+    dds::Texture,
+    hash::file_hashes,
+    records::{
+        GeneralBlock, Header, HeaderExt, TextureBlock, TextureChunk, BA2_PADDING, BA2_SIGNATURE,
+        COMPRESSION_LZ4, COMPRESSION_ZLIB, GENERAL_BLOCK_FLAGS, GENERAL_BLOCK_SIZE,
+        SUPPORTED_VERSIONS, TEXTURE_BLOCK_SIZE, TEXTURE_CHUNK_SIZE, TEXTURE_FLAGS,
+    },
+};
+
+/// Estimate for `max-size`: the largest header, general file record and name length in the name
+/// table. Texture records are smaller than DDS headers dropped from input files.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: HeaderExt::header_size(3),
+    entry_size: GENERAL_BLOCK_SIZE + 2,
+    name_copies: 1,
+};
+
+/// Sound files are never compressed, the game cannot play them otherwise.
+const STORED_EXTENSIONS: [&str; 3] = ["wav", "xwm", "fuz"];
+
+/// Archive2 puts every mip level of at least this size into its own chunk.
+const SEPARATE_CHUNK_MIN_DIMENSION: u32 = 512;
+const MAX_CHUNKS: usize = 4;
+
+#[derive(PartialEq)]
+enum ArchiveType {
+    General,
+    Textures,
+}
+
+struct Options {
+    archive_type: ArchiveType,
+    version: u32,
+    compress: bool,
+    /// [`COMPRESSION_ZLIB`] or [`COMPRESSION_LZ4`].
+    compression: u32,
+    level: CompressionLevel,
+}
+
+struct Entry {
+    src_path: PathBuf,
+    name: String,
+    name_hash: u32,
+    ext: [u8; 4],
+    dir_hash: u32,
+}
+
+struct ChunkPlan {
+    start_mip: u16,
+    end_mip: u16,
+    size: u64,
+}
+
+pub fn create_archive(
+    input_files: InputFileList,
+    path: &Path,
+    params: &HashMap<String, String>,
+) -> writer::Result<()> {
+    let options = parse_options(params)?;
+    let entries = collect_entries(input_files)?;
+    let file_count =
+        u32::try_from(entries.len()).map_err(|_| writer::Error::TotalInputLarger4GiB)?;
+
+    let out = fs::File::create(path).map_err(writer::Error::CreatingOutputFile)?;
+    let mut out = BufWriter::new(out);
+
+    let (archive_type, names_offset, records) = match options.archive_type {
+        ArchiveType::General => {
+            let records = write_general_data(&mut out, &entries, &options)?;
+            (*b"GNRL", out_position(&mut out)?, records)
+        }
+        ArchiveType::Textures => {
+            let records = write_texture_data(&mut out, &entries, &options)?;
+            (*b"DX10", out_position(&mut out)?, records)
+        }
+    };
+
+    // name table
+    for entry in &entries {
+        let len = u16::try_from(entry.name.len()).expect("name length is checked");
+        out.write_u16_le(len)
+            .map_err(writer::Error::WritingFileIndex)?;
+        out.write_all(entry.name.as_bytes())
+            .map_err(writer::Error::WritingFileIndex)?;
+    }
+
+    // header and file records
+    out.rewind().map_err(writer::Error::WritingHeader)?;
+
+    let hdr = Header {
+        signature: BA2_SIGNATURE
+            .as_bytes()
+            .try_into()
+            .expect("signature should be 4 bytes long"),
+        version: options.version,
+        archive_type,
+        num_files: file_count,
+        names_offset,
+    };
+    hdr.write(&mut out).map_err(writer::Error::WritingHeader)?;
+
+    if matches!(options.version, 2 | 3) {
+        HeaderExt {
+            unknown_1: 1,
+            compression: options.compression,
+        }
+        .write(&mut out, options.version)
+        .map_err(writer::Error::WritingHeader)?;
+    }
+    out.write_all(&records)
+        .map_err(writer::Error::WritingFileIndex)?;
+    out.flush().map_err(writer::Error::WritingFileIndex)?;
+
+    Ok(())
+}
+
+fn out_position(out: &mut BufWriter<fs::File>) -> writer::Result<u64> {
+    out.stream_position()
+        .map_err(writer::Error::WritingFileData)
+}
+
+/// Writes file data of a general archive. Returns encoded file records.
+fn write_general_data(
+    out: &mut BufWriter<fs::File>,
+    entries: &[Entry],
+    options: &Options,
+) -> writer::Result<Vec<u8>> {
+    let data_offset =
+        HeaderExt::header_size(options.version) + GENERAL_BLOCK_SIZE * entries.len() as u64;
+    out.seek(SeekFrom::Start(data_offset))
+        .map_err(writer::Error::WritingHeader)?;
+
+    let mut records = Vec::new();
+
+    for entry in entries {
+        let mut file = fs::File::open(&entry.src_path)
+            .map_err(|err| writer::Error::OpeningInputFile(entry.src_path.clone(), err))?;
+        let file_size = file
+            .metadata()
+            .map_err(|err| writer::Error::ArchivingInputFile(entry.src_path.clone(), err))?
+            .len();
+        let unpacked_size = u32::try_from(file_size)
+            .map_err(|_| writer::Error::InputFileLarger4GiB(entry.src_path.clone()))?;
+
+        let ext = String::from_utf8_lossy(&entry.ext);
+        let store = STORED_EXTENSIONS.contains(&ext.trim_end_matches('\0'));
+
+        let offset = out_position(out)?;
+        let packed_size = write_file_data(
+            out,
+            &mut file,
+            unpacked_size,
+            options.compress && !store,
+            options,
+        )
+        .map_err(|err| writer::Error::ArchivingInputFile(entry.src_path.clone(), err))?;
+
+        GeneralBlock {
+            name_hash: entry.name_hash,
+            ext: entry.ext,
+            dir_hash: entry.dir_hash,
+            unknown_1: GENERAL_BLOCK_FLAGS,
+            offset,
+            packed_size,
+            unpacked_size,
+            padding: BA2_PADDING,
+        }
+        .write(&mut records)
+        .expect("writing to memory buffer");
+    }
+
+    Ok(records)
+}
+
+/// Writes texture chunks of a DX10 archive. Returns encoded texture records.
+fn write_texture_data(
+    out: &mut BufWriter<fs::File>,
+    entries: &[Entry],
+    options: &Options,
+) -> writer::Result<Vec<u8>> {
+    let mut textures = Vec::with_capacity(entries.len());
+
+    // chunk count is needed to know where texture data begins
+    for entry in entries {
+        let invalid_texture = |err: io::Error| {
+            writer::Error::ArchivingInputFile(
+                entry.src_path.clone(),
+                io_error!(InvalidData, "invalid DDS texture: {err}"),
+            )
+        };
+
+        if &entry.ext != b"dds\0" {
+            return Err(invalid_texture(io_error!(
+                InvalidInput,
+                "texture archives can only contain .dds files"
+            )));
+        }
+
+        let file = fs::File::open(&entry.src_path)
+            .map_err(|err| writer::Error::OpeningInputFile(entry.src_path.clone(), err))?;
+        let file_size = file
+            .metadata()
+            .map_err(|err| writer::Error::ReadingInputFileMetadata(entry.src_path.clone(), err))?
+            .len();
+        let mut rdr = BufReader::new(file);
+        let texture = Texture::read(&mut rdr).map_err(invalid_texture)?;
+        let data_offset = rdr.stream_position().map_err(invalid_texture)?;
+
+        let (Ok(width), Ok(height), Ok(mip_count), Ok(format)) = (
+            u16::try_from(texture.width),
+            u16::try_from(texture.height),
+            u8::try_from(texture.mip_count),
+            u8::try_from(texture.dxgi_format),
+        ) else {
+            return Err(invalid_texture(io_error!(
+                Unsupported,
+                "texture dimensions or format do not fit into texture record"
+            )));
+        };
+
+        let chunks = plan_chunks(&texture);
+        let data_size: u64 = chunks.iter().map(|chunk| chunk.size).sum();
+
+        if file_size < data_offset + data_size {
+            return Err(invalid_texture(io_error!(
+                UnexpectedEof,
+                "expected {data_size} bytes of texture data, got {}",
+                file_size - data_offset
+            )));
+        }
+
+        let hdr = TextureBlock {
+            name_hash: entry.name_hash,
+            ext: entry.ext,
+            dir_hash: entry.dir_hash,
+            unknown_1: 0,
+            num_chunks: u8::try_from(chunks.len()).expect("should fit into `u8`"),
+            chunk_hdr_size: TEXTURE_CHUNK_SIZE,
+            height,
+            width,
+            num_mips: mip_count,
+            format,
+            flags: TEXTURE_FLAGS | u16::from(texture.cubemap),
+        };
+
+        textures.push((hdr, data_offset, chunks));
+    }
+
+    let records_size: u64 = textures
+        .iter()
+        .map(|(_, _, chunks)| {
+            TEXTURE_BLOCK_SIZE + u64::from(TEXTURE_CHUNK_SIZE) * chunks.len() as u64
+        })
+        .sum();
+    out.seek(SeekFrom::Start(
+        HeaderExt::header_size(options.version) + records_size,
+    ))
+    .map_err(writer::Error::WritingHeader)?;
+
+    let mut records = Vec::new();
+
+    for (entry, (hdr, data_offset, chunks)) in entries.iter().zip(textures) {
+        hdr.write(&mut records).expect("writing to memory buffer");
+
+        let mut file = fs::File::open(&entry.src_path)
+            .map_err(|err| writer::Error::OpeningInputFile(entry.src_path.clone(), err))?;
+        file.seek(SeekFrom::Start(data_offset))
+            .map_err(|err| writer::Error::ArchivingInputFile(entry.src_path.clone(), err))?;
+
+        for chunk in chunks {
+            let mut data = vec![0; usize::try_from(chunk.size).expect("should fit into `usize`")];
+            file.read_exact(&mut data)
+                .map_err(|err| writer::Error::ArchivingInputFile(entry.src_path.clone(), err))?;
+
+            let unpacked_size = u32::try_from(data.len())
+                .map_err(|_| writer::Error::InputFileLarger4GiB(entry.src_path.clone()))?;
+
+            let offset = out_position(out)?;
+            let packed_size = write_data(out, &data, options.compress, options)
+                .map_err(|err| writer::Error::ArchivingInputFile(entry.src_path.clone(), err))?;
+
+            TextureChunk {
+                offset,
+                packed_size,
+                unpacked_size,
+                start_mip: chunk.start_mip,
+                end_mip: chunk.end_mip,
+                padding: BA2_PADDING,
+            }
+            .write(&mut records)
+            .expect("writing to memory buffer");
+        }
+    }
+
+    Ok(records)
+}
+
+/// Splits mip levels into chunks the way Archive2 does. Every mip level of at least 512x512 gets its own chunk,
+/// remaining levels share the last chunk. Cube maps keep all faces together, so they are stored as a single chunk.
+fn plan_chunks(texture: &Texture) -> Vec<ChunkPlan> {
+    let mip_sizes = texture.mip_sizes();
+    let last_mip = u16::try_from(mip_sizes.len() - 1).expect("should fit into `u16`");
+
+    if texture.cubemap {
+        return vec![ChunkPlan {
+            start_mip: 0,
+            end_mip: last_mip,
+            size: mip_sizes.iter().sum::<u64>() * 6,
+        }];
+    }
+
+    let mut chunks: Vec<ChunkPlan> = Vec::new();
+    let mut merging = false;
+
+    for (level, size) in (0..=last_mip).zip(mip_sizes) {
+        let width = (texture.width >> level).max(1);
+        let height = (texture.height >> level).max(1);
+
+        if merging {
+            let last = chunks.last_mut().expect("merged chunk should exist");
+            last.end_mip = level;
+            last.size += size;
+            continue;
+        }
+
+        merging = width < SEPARATE_CHUNK_MIN_DIMENSION
+            || height < SEPARATE_CHUNK_MIN_DIMENSION
+            || chunks.len() == MAX_CHUNKS - 1;
+
+        chunks.push(ChunkPlan {
+            start_mip: level,
+            end_mip: level,
+            size,
+        });
+    }
+
+    chunks
+}
+
+/// Streams an input file into the archive, compressing it if enabled. Falls back to storing
+/// the file as is when compression does not make it smaller. Returns packed size, which is 0
+/// for stored data.
+fn write_file_data(
+    out: &mut BufWriter<fs::File>,
+    file: &mut fs::File,
+    unpacked_size: u32,
+    compress: bool,
+    options: &Options,
+) -> io::Result<u32> {
+    if !compress || unpacked_size == 0 {
+        io::copy(file, out)?;
+        return Ok(0);
+    }
+
+    if options.compression == COMPRESSION_LZ4 {
+        // a raw LZ4 block cannot be produced incrementally
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        return write_data(out, &data, compress, options);
+    }
+
+    let start = out.stream_position()?;
+    let mut encoder = flate2::write::ZlibEncoder::new(&mut *out, options.level.to_flate2());
+    io::copy(file, &mut encoder)?;
+    encoder.finish()?;
+    let packed_size = out.stream_position()? - start;
+
+    if packed_size < u64::from(unpacked_size) {
+        return Ok(u32::try_from(packed_size).expect("packed data is smaller than unpacked"));
+    }
+
+    out.seek(SeekFrom::Start(start))?;
+    file.rewind()?;
+    io::copy(file, out)?;
+
+    // drop the rest of the compressed data, nothing has been written past it yet
+    out.flush()?;
+    let end = out.stream_position()?;
+    out.get_ref().set_len(end)?;
+
+    Ok(0)
+}
+
+/// Writes compressed data, or data as is if compression is disabled or does not make data smaller.
+/// Returns packed size, which is 0 for stored data.
+fn write_data(
+    out: &mut impl Write,
+    data: &[u8],
+    compress: bool,
+    options: &Options,
+) -> io::Result<u32> {
+    if compress && !data.is_empty() {
+        let packed = if options.compression == COMPRESSION_LZ4 {
+            lz4_flex::block::compress(data)
+        } else {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), options.level.to_flate2());
+            encoder.write_all(data)?;
+            encoder.finish()?
+        };
+
+        if packed.len() < data.len() {
+            out.write_all(&packed)?;
+            return Ok(u32::try_from(packed.len()).expect("packed data is smaller than unpacked"));
+        }
+    }
+
+    out.write_all(data)?;
+    Ok(0)
+}
+
+fn collect_entries(input_files: InputFileList) -> writer::Result<Vec<Entry>> {
+    input_files
+        .into_iter()
+        .filter(|f| f.file_type == FileType::RegularFile)
+        .map(|input_file| {
+            let name = input_file.dst_path.try_to_win().map_err(|err| {
+                writer::Error::InvalidInputFileName(input_file.dst_path.clone(), err)
+            })?;
+
+            if !name.is_ascii() {
+                return Err(writer::Error::InputFileNotAscii(name));
+            }
+
+            if name.len() > usize::from(u16::MAX) {
+                return Err(writer::Error::InputFileNameTooLong(
+                    name,
+                    usize::from(u16::MAX),
+                ));
+            }
+
+            let (name_hash, ext, dir_hash) = file_hashes(&input_file.dst_path);
+
+            Ok(Entry {
+                src_path: input_file.src_path,
+                name,
+                name_hash,
+                ext,
+                dir_hash,
+            })
+        })
+        .collect()
+}
+
+fn parse_options(params: &HashMap<String, String>) -> writer::Result<Options> {
+    let archive_type = match params.get("type").map(String::as_str) {
+        None | Some("general") => ArchiveType::General,
+        Some("dx10") => ArchiveType::Textures,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "type",
+                format!("expected 'general' or 'dx10', got '{value}'"),
+            ))
+        }
+    };
+    let version = match params.get("version") {
+        None => 1,
+        Some(value) => value
+            .parse()
+            .ok()
+            .filter(|version| SUPPORTED_VERSIONS.contains(version))
+            .ok_or_else(|| {
+                writer::Error::InvalidParameter(
+                    "version",
+                    format!("expected one of {SUPPORTED_VERSIONS:?}, got '{value}'"),
+                )
+            })?,
+    };
+    let compression = match params.get("compression").map(String::as_str) {
+        None | Some("zlib") => COMPRESSION_ZLIB,
+        Some("lz4") if version == 3 => COMPRESSION_LZ4,
+        Some("lz4") => {
+            return Err(writer::Error::InvalidParameter(
+                "compression",
+                String::from("LZ4 compression requires version 3"),
+            ))
+        }
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "compression",
+                format!("expected 'zlib' or 'lz4', got '{value}'"),
+            ))
+        }
+    };
+    let compress = match params.get("compress").map(String::as_str) {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "compress",
+                format!("expected 'true' or 'false', got '{value}'"),
+            ))
+        }
+    };
+    let level = CompressionLevel::from_params(params)?.unwrap_or(CompressionLevel::Default);
+
+    Ok(Options {
+        archive_type,
+        version,
+        compress,
+        compression,
+        level,
+    })
+}
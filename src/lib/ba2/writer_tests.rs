@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use hex_literal::hex;
+use rstest::*;
+use sha1::{Digest, Sha1};
+use tempdir::TempDir;
+
+use crate::{reader::Options, InputFileListBuilder};
+
+#[test]
+fn correct() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &HashMap::new());
+    assert!(res.is_ok());
+
+    let mut file = fs::File::open(&output_path).unwrap();
+    let mut hasher = Sha1::new();
+    io::copy(&mut file, &mut hasher).unwrap();
+    let hash = hasher.finalize();
+
+    assert_eq!(hash[..], hex!("aad061b17a22517e054c754fc56332e934d25da9"));
+}
+
+#[test]
+fn uncompressed() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("compress"), String::from("false"));
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_ok());
+
+    let mut rdr = super::make_reader(
+        &output_path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(rdr.file_count(), 5);
+
+    let file = rdr.get_file(4);
+    assert_eq!(file.name, Path::new("img001.png"));
+    assert_eq!(file.size, Some(22290));
+    assert_eq!(
+        rdr.file_attrs(4),
+        [
+            ("compression", String::from("none")),
+            ("packed", String::from("22290"))
+        ]
+    );
+
+    let mut data = Vec::new();
+    rdr.create_file_reader(4)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, fs::read("./samples/unpacked/img001.png").unwrap());
+}
+
+#[test]
+fn textures() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/ba2/dx10"))
+        .unwrap()
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("type"), String::from("dx10"));
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_ok());
+
+    let mut rdr = super::make_reader(
+        &output_path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(rdr.file_count(), 3);
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(Path::new("./samples/ba2/dx10").join(&file.name)).unwrap();
+
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
+
+#[test]
+fn textures_from_non_dds_files() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("type"), String::from("dx10"));
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_err());
+}
+
+#[rstest]
+#[case("1", "zlib", "general")]
+#[case("2", "zlib", "general")]
+#[case("3", "lz4", "general")]
+#[case("7", "zlib", "general")]
+#[case("8", "zlib", "general")]
+#[case("2", "zlib", "dx10")]
+#[case("3", "lz4", "dx10")]
+#[case("8", "zlib", "dx10")]
+fn versions(#[case] version: &str, #[case] compression: &str, #[case] archive_type: &str) {
+    let src_dir = match archive_type {
+        "dx10" => Path::new("./samples/ba2/dx10"),
+        _ => Path::new("./samples/ba2/general"),
+    };
+    let input_files = InputFileListBuilder::new()
+        .add_dir(src_dir)
+        .unwrap()
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("type"), archive_type.to_string());
+    params.insert(String::from("version"), version.to_string());
+    params.insert(String::from("compression"), compression.to_string());
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_ok());
+
+    let mut rdr = super::make_reader(
+        &output_path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(rdr.file_count() > 0);
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(src_dir.join(&file.name)).unwrap();
+
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
+
+#[test]
+fn lz4_requires_version_3() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/ba2/general"))
+        .unwrap()
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("compression"), String::from("lz4"));
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_err());
+}
+
+#[test]
+fn incompressible_file_is_stored() {
+    let src_dir = TempDir::new("flpak-tests").unwrap();
+    fs::write(src_dir.path().join("a.txt"), "text ".repeat(1000)).unwrap();
+    // xorshift noise, zlib makes it larger
+    let mut state = 0x2545_f491u32;
+    let noise: Vec<u8> = (0..200_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    fs::write(src_dir.path().join("b.bin"), &noise).unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(src_dir.path())
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.ba2");
+    let res = super::create_archive(input_files, &output_path, &HashMap::new());
+    assert!(res.is_ok());
+
+    let mut rdr = super::make_reader(
+        &output_path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(rdr.file_count(), 2);
+    assert_eq!(rdr.file_attrs(0)[0], ("compression", String::from("zlib")));
+    assert_eq!(
+        rdr.file_attrs(1),
+        [
+            ("compression", String::from("none")),
+            ("packed", String::from("200000"))
+        ]
+    );
+
+    let mut data = Vec::new();
+    rdr.create_file_reader(1)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, noise);
+
+    // the name table directly follows the stored data, no leftovers of the compressed attempt
+    let archive = fs::read(&output_path).unwrap();
+    let names_offset = u64::from_le_bytes(archive[16..24].try_into().unwrap());
+    assert_eq!(archive.len() as u64, names_offset + 2 + 5 + 2 + 5);
+}
+
+#[rstest]
+#[case("yes")]
+#[case("1")]
+fn invalid_compress(#[case] value: &str) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([(String::from("compress"), value.to_string())]);
+    let res = super::create_archive(
+        InputFileListBuilder::new().build(),
+        &dir.path().join("archive.ba2"),
+        &params,
+    );
+
+    assert!(matches!(
+        res,
+        Err(crate::writer::Error::InvalidParameter("compress", _))
+    ));
+}
+
+#[rstest]
+#[case(6)]
+#[case(32)]
+#[case(u32::MAX)]
+fn too_many_mip_levels(#[case] mip_count: u32) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let input_dir = dir.path().join("textures");
+    fs::create_dir(&input_dir).unwrap();
+
+    // 16x8 texture has at most 5 mip levels
+    let mut data = fs::read("./samples/ba2/dx10/textures/bc1.dds").unwrap();
+    data[28..32].copy_from_slice(&mip_count.to_le_bytes());
+    fs::write(input_dir.join("bc1.dds"), data).unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&input_dir)
+        .unwrap()
+        .build();
+    let params = HashMap::from([(String::from("type"), String::from("dx10"))]);
+    let res = super::create_archive(input_files, &dir.path().join("archive.ba2"), &params);
+
+    match res {
+        Err(crate::writer::Error::ArchivingInputFile(_, err)) => {
+            assert!(err.to_string().contains("mip levels"), "{err}");
+        }
+        _ => panic!("expected invalid texture"),
+    }
+}
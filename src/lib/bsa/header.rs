@@ -7,6 +7,8 @@ use crate::{io_error, ReadEx, WriteEx};
 use super::Version;
 
 pub const BSA_SIGNATURE: &[u8; 4] = b"BSA\0";
+/// Signature and header.
+pub const HEADER_SIZE: u64 = 36;
 
 bitflags! {
     pub struct Flags: u32 {
@@ -21,5 +21,5 @@ use read_file_index::*;
 use version::*;
 use write_file_index::*;
 
-pub use reader::make_reader;
-pub use writer::create_archive;
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -132,7 +132,7 @@ pub trait ReadFileIndex: BufRead + Seek {
 
                 let full_path = PathBuf::from_win(&full_path);
 
-                if has_folder_names && has_folder_names {
+                if has_folder_names && has_file_names {
                     if full_path != file.name {
                         return Err(io_error!(
                             InvalidData,
@@ -1,6 +1,6 @@
 use std::{
     fs,
-    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
     path::Path,
 };
 
@@ -177,3 +177,28 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let mut rdr = BufReader::new(fs::File::open(path)?);
+
+    if rdr.read_u8_vec(4)? != BSA_SIGNATURE {
+        return Ok(crate::Probe::Invalid(String::from("invalid signature")));
+    }
+
+    let hdr = match rdr.read_header() {
+        Ok(hdr) => hdr,
+        Err(err) => return Ok(crate::Probe::Invalid(err.to_string())),
+    };
+
+    if hdr.folder_records_offset != 36 {
+        return Ok(crate::Probe::Invalid(format!(
+            "folder records offset {}, expected 36",
+            hdr.folder_records_offset
+        )));
+    }
+
+    Ok(crate::Probe::Valid(format!(
+        "version {}",
+        u32::from(&hdr.version)
+    )))
+}
@@ -19,18 +19,26 @@ fn correct(#[case] version: &str, #[case] compress: bool, #[case] embed_names: b
     let embed_names = if embed_names { "_names" } else { "" };
 
     let res = make_reader(
-        Path::new(&format!("./samples/bsa/correct_v{version}{compress}{embed_names}.bsa")),
-        Options { strict: true },
+        Path::new(&format!(
+            "./samples/bsa/correct_v{version}{compress}{embed_names}.bsa"
+        )),
+        Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
-    assert_eq!(res.is_ok(), true);
+    assert!(res.is_ok());
 }
 
 #[test]
 fn invalid_signature() {
     let res = make_reader(
         Path::new("./samples/bsa/invalid_signature.bsa"),
-        Options { strict: true },
+        Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let err = res.err().unwrap();
@@ -44,7 +52,10 @@ fn invalid_signature() {
 fn invalid_header() {
     let res = make_reader(
         Path::new("./samples/bsa/invalid_header.bsa"),
-        Options { strict: true },
+        Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let err = res.err().unwrap();
@@ -58,7 +69,10 @@ fn invalid_header() {
 fn invalid_version() {
     let res = make_reader(
         Path::new("./samples/bsa/invalid_version.bsa"),
-        Options { strict: true },
+        Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let err = res.err().unwrap();
@@ -72,7 +86,10 @@ fn invalid_version() {
 fn invalid_flags() {
     let res = make_reader(
         Path::new("./samples/bsa/invalid_flags.bsa"),
-        Options { strict: true },
+        Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let err = res.err().unwrap();
@@ -83,7 +100,10 @@ fn invalid_flags() {
 fn invalid_file_flags() {
     let res = make_reader(
         Path::new("./samples/bsa/invalid_file_flags.bsa"),
-        Options { strict: true },
+        Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let err = res.err().unwrap();
@@ -92,3 +112,42 @@ fn invalid_file_flags() {
         "failed to read file header: invalid file flags"
     );
 }
+
+/// Archives with folder names and embedded file names, but without the file names block, take file
+/// names from data blocks instead of comparing them with folder names.
+#[test]
+fn embedded_names_without_file_names() {
+    use std::{collections::HashMap, fs, path::PathBuf};
+
+    let input_files = crate::InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .exclude_pattern("empty_file")
+        .exclude_pattern("file001.txt")
+        .exclude_pattern("img001.png")
+        .build();
+
+    let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+    let path = dir.path().join("archive.bsa");
+    let params = HashMap::from([
+        (String::from("version"), String::from("104")),
+        (String::from("embed-names"), String::from("true")),
+    ]);
+    super::create_archive(input_files, &path, &params).unwrap();
+
+    let read_names = |path: &Path| -> Vec<PathBuf> {
+        let rdr = make_reader(path, Default::default()).unwrap();
+        (0..rdr.file_count())
+            .map(|index| rdr.get_file(index).name)
+            .collect()
+    };
+    let names = read_names(&path);
+
+    // clear "file names" archive flag
+    let mut data = fs::read(&path).unwrap();
+    data[12] &= !0x02;
+    fs::write(&path, data).unwrap();
+
+    assert_eq!(read_names(&path), names);
+}
@@ -7,6 +7,10 @@ use crate::WriteEx;
 
 use super::{Flags, Hash, Header, Version, WriteHash};
 
+/// Folder record with v105 padding, the largest of all versions.
+pub const FOLDER_RECORD_SIZE_V105: u64 = 24;
+pub const FILE_RECORD_SIZE: u64 = 16;
+
 pub struct Folder {
     pub name_hash: Hash,
     pub name: String,
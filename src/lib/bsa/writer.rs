@@ -5,11 +5,21 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::{writer, FileType, InputFileList, PathBufUtils, WriteEx};
+use crate::{
+    writer, writer::CompressionLevel, FileType, InputFileList, Overhead, PathBufUtils, WriteEx,
+};
 
 use super::{
-    write_file_index::{File, Folder},
-    Flags, Hash, Header, Version, WriteFileIndex, WriteHeader, BSA_SIGNATURE,
+    write_file_index::{File, Folder, FILE_RECORD_SIZE, FOLDER_RECORD_SIZE_V105},
+    Flags, Hash, Header, Version, WriteFileIndex, WriteHeader, BSA_SIGNATURE, HEADER_SIZE,
+};
+
+/// Estimate of the file index for `max-size`: file record, folder record, name lengths and nulls,
+/// original size of compressed files. Names are stored in the name table and embedded.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: HEADER_SIZE,
+    entry_size: FILE_RECORD_SIZE + FOLDER_RECORD_SIZE_V105 + 4 + 4,
+    name_copies: 2,
 };
 
 pub fn create_archive(
@@ -19,6 +29,7 @@ pub fn create_archive(
 ) -> writer::Result<()> {
     let mut hdr = Header::default();
     parse_options(options, &mut hdr)?;
+    let level = CompressionLevel::from_params(options)?.unwrap_or(CompressionLevel::Best);
 
     let folder_record_size = match hdr.version {
         Version::V103 | Version::V104 => 16,
@@ -111,15 +122,16 @@ pub fn create_archive(
 
                 match hdr.version {
                     Version::V103 | Version::V104 => {
-                        let mut encoder = flate2::write::ZlibEncoder::new(
-                            &mut output_file,
-                            flate2::Compression::best(),
-                        );
+                        let mut encoder =
+                            flate2::write::ZlibEncoder::new(&mut output_file, level.to_flate2());
                         io::copy(&mut input_file, &mut encoder)
                             .map_err(writer::Error::WritingFileData)?;
                     }
                     Version::V105 => {
-                        let mut encoder = lz4_flex::frame::FrameEncoder::new(&mut output_file);
+                        let mut encoder = lz4_flex::frame::FrameEncoder::with_frame_info(
+                            level.to_lz4_frame_info(),
+                            &mut output_file,
+                        );
                         io::copy(&mut input_file, &mut encoder)
                             .map_err(writer::Error::WritingFileData)?;
                         encoder
@@ -161,9 +173,9 @@ fn parse_options(params: &HashMap<String, String>, hdr: &mut Header) -> crate::w
     hdr.version = Version::try_from(params.get("version"))
         .map_err(|err| writer::Error::InvalidParameter("version", err))?;
 
-    let compress = params.get("compress").map_or(false, |v| v == "true");
-    let xbox = params.get("xbox").map_or(false, |v| v == "true");
-    let embed_names = params.get("embed-names").map_or(false, |v| v == "true");
+    let compress = params.get("compress").is_some_and(|v| v == "true");
+    let xbox = params.get("xbox").is_some_and(|v| v == "true");
+    let embed_names = params.get("embed-names").is_some_and(|v| v == "true");
 
     if compress {
         hdr.flags |= Flags::COMPRESSED_BY_DEFAULT;
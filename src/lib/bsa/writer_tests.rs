@@ -1,11 +1,16 @@
-use std::{collections::HashMap, fs, io, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
 
-use rstest::*;
 use hex_literal::hex;
+use rstest::*;
 use sha1::{Digest, Sha1};
 use tempdir::TempDir;
 
-use crate::InputFileListBuilder;
+use crate::{reader::Options, InputFileListBuilder};
 
 #[rstest]
 #[case("103", hex!("1c9ab5419e6494ed374e8911a9c25bbed9d5bcda"))]
@@ -36,3 +41,81 @@ fn correct(#[case] version: &str, #[case] sha1: [u8; 20]) {
 
     assert_eq!(hash[..], sha1);
 }
+
+#[rstest]
+#[case("104", "fast")]
+#[case("104", "best")]
+#[case("105", "fast")]
+#[case("105", "best")]
+fn compressed_with_level(#[case] version: &str, #[case] level: &str) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .exclude_pattern("empty_file")
+        .exclude_pattern("file001.txt")
+        .exclude_pattern("img001.png")
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("version"), version.to_string());
+    params.insert(String::from("compress"), String::from("true"));
+    params.insert(String::from("level"), level.to_string());
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.bsa");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_ok());
+
+    let mut rdr = super::make_reader(
+        &output_path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let index = (0..rdr.file_count())
+        .find(|&index| rdr.get_file(index).name == Path::new("dir1/img002.jpg"))
+        .unwrap();
+    let mut data = Vec::new();
+    rdr.create_file_reader(index)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+
+    assert_eq!(
+        data,
+        fs::read("./samples/unpacked/dir1/img002.jpg").unwrap()
+    );
+}
+
+#[rstest]
+#[case("104")]
+#[case("105")]
+fn level_changes_packed_size(#[case] version: &str) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let input_dir = dir.path().join("input");
+    fs::create_dir_all(input_dir.join("dir")).unwrap();
+    let text: String = (0..20000u32)
+        .map(|i| format!("line {i}: {}\n", i.wrapping_mul(2654435761) % 997))
+        .collect();
+    fs::write(input_dir.join("dir/data.txt"), text).unwrap();
+
+    let archive_size = |level: &str| {
+        let input_files = InputFileListBuilder::new()
+            .add_dir(&input_dir)
+            .unwrap()
+            .build();
+        let params = HashMap::from([
+            (String::from("version"), version.to_string()),
+            (String::from("compress"), String::from("true")),
+            (String::from("level"), level.to_string()),
+        ]);
+        let output_path = dir.path().join(format!("{level}.bsa"));
+        super::create_archive(input_files, &output_path, &params).unwrap();
+        fs::metadata(&output_path).unwrap().len()
+    };
+
+    assert!(archive_size("best") < archive_size("fast"));
+}
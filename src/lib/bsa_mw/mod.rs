@@ -9,5 +9,5 @@ mod writer;
 #[cfg(test)]
 mod writer_tests;
 
-pub use reader::make_reader;
-pub use writer::create_archive;
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -147,3 +147,27 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let hdr = Header::read(&mut io::BufReader::new(file))?;
+
+    if hdr.signature != BSA_SIGNATURE {
+        return Ok(crate::Probe::Invalid(String::from("invalid signature")));
+    }
+
+    let file_count = u64::from(hdr.file_count);
+    let hash_table_offset = hdr.hash_table_offset;
+
+    // file records (8 bytes), name offsets (4 bytes) and hashes (8 bytes)
+    if hdr.absolute_hash_table_offset() + file_count * 8 > file_size
+        || u64::from(hash_table_offset) < file_count * 12
+    {
+        return Ok(crate::Probe::Invalid(format!(
+            "hash table offset {hash_table_offset} is out of bounds"
+        )));
+    }
+
+    Ok(crate::Probe::Valid(format!("{file_count} files")))
+}
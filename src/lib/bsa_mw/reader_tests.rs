@@ -5,7 +5,10 @@ use std::path::Path;
 fn correct() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/correct.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let rdr = res.ok().unwrap();
@@ -32,7 +35,10 @@ fn correct() {
 fn failed_to_open() {
     let res = super::make_reader(
         Path::new("./samples/bsa/none.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let rdr = res.err().unwrap();
@@ -46,7 +52,10 @@ fn failed_to_open() {
 fn invalid_header() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/invalid_header.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let rdr = res.err().unwrap();
@@ -60,7 +69,10 @@ fn invalid_header() {
 fn invalid_signature() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/invalid_signature.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let rdr = res.err().unwrap();
@@ -74,7 +86,10 @@ fn invalid_signature() {
 fn invalid_file_records() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/invalid_file_records.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options {
+            strict: true,
+            ..Default::default()
+        },
     );
 
     let rdr = res.err().unwrap();
@@ -89,7 +104,7 @@ fn invalid_file_records() {
 fn invalid_file_names() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/invalid_file_names.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options { strict: true, ..Default::default() },
     );
 
     let rdr = res.err().unwrap();
@@ -100,7 +115,7 @@ fn invalid_file_names() {
 fn invalid_hash_records() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/invalid_hash_records.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options { strict: true, ..Default::default() },
     );
 
     let rdr = res.err().unwrap();
@@ -111,7 +126,7 @@ fn invalid_hash_records() {
 fn invalid_hashes() {
     let res = super::make_reader(
         Path::new("./samples/bsa-mw/invalid_hashes.bsa"),
-        crate::reader::Options { strict: true },
+        crate::reader::Options { strict: true, ..Default::default() },
     );
 
     let rdr = res.err().unwrap();
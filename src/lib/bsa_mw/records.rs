@@ -9,6 +9,10 @@ use crate::{io_error, utils::buffer_to_zstring, ReadEx, WriteEx};
 
 pub const BSA_SIGNATURE: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
 pub const BSA_HEADER_SIZE: u64 = 12;
+/// Size and offset of a file.
+pub const FILE_RECORD_SIZE: u64 = 8;
+pub const NAME_OFFSET_SIZE: u64 = 4;
+pub const HASH_SIZE: u64 = 8;
 
 #[repr(C, packed)]
 pub struct Header {
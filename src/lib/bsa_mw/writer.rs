@@ -8,9 +8,16 @@ use std::{
 
 use super::{
     hash::Hash,
-    records::{FileRecord, Header},
+    records::{FileRecord, Header, BSA_HEADER_SIZE, FILE_RECORD_SIZE, HASH_SIZE, NAME_OFFSET_SIZE},
+};
+use crate::{writer, FileType, InputFileList, Overhead, PathBufUtils, WriteEx};
+
+/// File record, name offset, hash and null terminator of every file, used by `max-size`.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: BSA_HEADER_SIZE,
+    entry_size: FILE_RECORD_SIZE + NAME_OFFSET_SIZE + HASH_SIZE + 1,
+    name_copies: 1,
 };
-use crate::{writer, FileType, InputFileList, PathBufUtils, WriteEx};
 
 struct File {
     local_path: PathBuf,
@@ -44,8 +51,10 @@ pub fn create_archive(
 
     let mut out = fs::File::create(path).map_err(writer::Error::CreatingOutputFile)?;
 
-    let hash_table_offset = u32::try_from((file_count * 8) + (file_count * 4) + names.len())
-        .map_err(|_| writer::Error::Other("total size of file records exceeds 4GiB".into()))?;
+    let hash_table_offset = u32::try_from(
+        (file_count as u64 * (FILE_RECORD_SIZE + NAME_OFFSET_SIZE)) + names.len() as u64,
+    )
+    .map_err(|_| writer::Error::Other("total size of file records exceeds 4GiB".into()))?;
     Header::write(&mut out, hash_table_offset, file_count).map_err(writer::Error::WritingHeader)?;
 
     for &input_file in input_files_by_hash.values() {
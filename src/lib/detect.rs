@@ -0,0 +1,77 @@
+use std::{io, path::Path};
+
+/// Bytes expected at a fixed offset from the beginning of an archive.
+pub struct Signature {
+    pub offset: u64,
+    pub bytes: &'static [u8],
+}
+
+impl Signature {
+    #[must_use]
+    pub const fn new(bytes: &'static [u8]) -> Self {
+        Self { offset: 0, bytes }
+    }
+
+    #[must_use]
+    pub const fn at(offset: u64, bytes: &'static [u8]) -> Self {
+        Self { offset, bytes }
+    }
+
+    /// Offset of the first byte after the signature.
+    #[must_use]
+    pub fn end(&self) -> u64 {
+        self.offset + self.bytes.len() as u64
+    }
+
+    /// Checks the signature against the beginning of a file.
+    #[must_use]
+    pub fn matches(&self, head: &[u8]) -> bool {
+        usize::try_from(self.offset)
+            .ok()
+            .and_then(|offset| head.get(offset..offset + self.bytes.len()))
+            == Some(self.bytes)
+    }
+
+    /// Printable form of the signature, non-ASCII bytes are escaped.
+    #[must_use]
+    pub fn display(&self) -> String {
+        let bytes = self.bytes.escape_ascii().to_string();
+
+        match self.offset {
+            0 => format!("'{bytes}'"),
+            offset => format!("'{bytes}' at {offset}"),
+        }
+    }
+}
+
+/// Result of checking that a file header is valid for a format.
+pub enum Probe {
+    /// The header is valid, with a short description of what was found.
+    Valid(String),
+    /// The header is invalid, with a reason.
+    Invalid(String),
+}
+
+pub type ProbeFn = fn(path: &Path) -> io::Result<Probe>;
+
+/// A format an archive may be in, see [`crate::Registry::detect`].
+pub struct Candidate {
+    pub format: &'static str,
+    pub score: i32,
+    pub reasons: Vec<String>,
+}
+
+impl Candidate {
+    pub const SIGNATURE_SCORE: i32 = 20;
+    pub const VALID_HEADER_SCORE: i32 = 40;
+    /// Outweighs an extension, but not a signature: a damaged archive is still opened by its
+    /// signature, so that the reader reports what is wrong with it.
+    pub const INVALID_HEADER_SCORE: i32 = -20;
+    pub const EXTENSION_SCORE: i32 = 10;
+
+    /// Whether the archive can be opened as this format.
+    #[must_use]
+    pub fn is_plausible(&self) -> bool {
+        self.score > 0
+    }
+}
@@ -1,9 +1,11 @@
+mod detect;
 mod file_type;
 mod input_file;
 mod path_utils;
 mod read_ex;
 pub mod reader;
 mod registry;
+mod split;
 pub mod utils;
 mod write_ex;
 pub mod writer;
@@ -14,12 +16,16 @@ mod bsa;
 mod bsa_mw;
 mod pak;
 mod rpa;
+mod ue_pak;
 mod vpk;
+mod wad;
 mod zip;
 
+pub use detect::*;
 pub use file_type::*;
 pub use input_file::*;
 pub use path_utils::*;
 pub use read_ex::*;
 pub use registry::*;
+pub use split::*;
 pub use write_ex::*;
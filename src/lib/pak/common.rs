@@ -1,2 +1,57 @@
 pub const PAK_SIGNATURE: &[u8; 4] = b"PACK";
-pub const PAK_FILE_ENTRY_SIZE: usize = 64;
+pub const SIN_SIGNATURE: &[u8; 4] = b"SPAK";
+pub const HEADER_SIZE: u32 = 12;
+
+/// Daikatana entry flag: file data is compressed.
+pub const DAIKATANA_COMPRESSED: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// Quake, Quake 2, Half-Life and others: 56-byte names.
+    Quake,
+    /// SiN: `SPAK` signature, 120-byte names.
+    Sin,
+    /// Daikatana: 56-byte names, entries also have compressed size and compression flag.
+    Daikatana,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 3] = [Variant::Quake, Variant::Sin, Variant::Daikatana];
+
+    /// Name used in `variant=` option.
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Quake => "quake",
+            Variant::Sin => "sin",
+            Variant::Daikatana => "daikatana",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|variant| variant.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn signature(self) -> &'static [u8; 4] {
+        match self {
+            Variant::Quake | Variant::Daikatana => PAK_SIGNATURE,
+            Variant::Sin => SIN_SIGNATURE,
+        }
+    }
+
+    /// Size of the name field, including the null terminator.
+    pub const fn name_size(self) -> usize {
+        match self {
+            Variant::Quake | Variant::Daikatana => 56,
+            Variant::Sin => 120,
+        }
+    }
+
+    pub const fn entry_size(self) -> usize {
+        match self {
+            Variant::Quake | Variant::Sin => self.name_size() + 8,
+            Variant::Daikatana => self.name_size() + 16,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Daikatana PAK compression: a byte oriented mix of run-length encoding and back references.
+//!
+//! Every block starts with a code byte `x`:
+//! - `0..=63`: `x + 1` literal bytes follow;
+//! - `64..=127`: `x - 62` zero bytes;
+//! - `128..=191`: the next byte repeated `x - 126` times;
+//! - `192..=253`: `x - 190` bytes copied from the output, starting `next byte + 2` bytes back;
+//! - `255`: end of data.
+
+use std::io::{Error, ErrorKind, Result};
+
+const MAX_LITERALS: usize = 64;
+const MAX_RUN: usize = 65;
+const END: u8 = 255;
+
+pub fn decompress(input: &[u8], size: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(size);
+    let mut input = input.iter().copied();
+    let mut next = || {
+        input
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "compressed data is truncated"))
+    };
+
+    loop {
+        let x = next()?;
+
+        match x {
+            0..=63 => {
+                for _ in 0..=x {
+                    out.push(next()?);
+                }
+            }
+            64..=127 => out.resize(out.len() + usize::from(x - 62), 0),
+            128..=191 => {
+                let byte = next()?;
+                out.resize(out.len() + usize::from(x - 126), byte);
+            }
+            192..=253 => {
+                let distance = usize::from(next()?) + 2;
+                let Some(start) = out.len().checked_sub(distance) else {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "back reference before the beginning of data",
+                    ));
+                };
+
+                // the referenced bytes may overlap with the copied ones
+                for index in start..start + usize::from(x - 190) {
+                    out.push(out[index]);
+                }
+            }
+            END => break,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid code {x:#04x}"),
+                ))
+            }
+        }
+    }
+
+    if out.len() != size {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("decompressed {} bytes, expected {size}", out.len()),
+        ));
+    }
+
+    Ok(out)
+}
+
+/// Compresses data with literals and runs only.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2);
+    let mut literals_start = 0;
+    let mut pos = 0;
+
+    let flush_literals = |out: &mut Vec<u8>, literals: &[u8]| {
+        for chunk in literals.chunks(MAX_LITERALS) {
+            out.push(u8::try_from(chunk.len() - 1).expect("chunk should be small"));
+            out.extend_from_slice(chunk);
+        }
+    };
+
+    while pos < data.len() {
+        let byte = data[pos];
+        let run = data[pos..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == byte)
+            .count();
+
+        if run < 2 {
+            pos += 1;
+            continue;
+        }
+
+        flush_literals(&mut out, &data[literals_start..pos]);
+        let run = u8::try_from(run).expect("run should be small");
+
+        if byte == 0 {
+            out.push(run + 62);
+        } else {
+            out.push(run + 126);
+            out.push(byte);
+        }
+
+        pos += usize::from(run);
+        literals_start = pos;
+    }
+
+    flush_literals(&mut out, &data[literals_start..]);
+    out.push(END);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+
+    #[test]
+    fn codes() {
+        let compressed = [
+            2, b'a', b'b', b'c', // literals
+            65,   // 3 zeros
+            130, b'x', // 4 times 'x'
+            194, 5, // 4 bytes from 7 bytes back
+            255,
+        ];
+
+        assert_eq!(
+            decompress(&compressed, 14).unwrap(),
+            b"abc\0\0\0xxxx\0\0\0x"
+        );
+        assert!(decompress(&compressed, 13).is_err());
+        assert!(decompress(&compressed[..4], 3).is_err());
+        assert!(decompress(&[192, 0, 255], 2).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut data = b"header".to_vec();
+        data.extend([0; 200]);
+        data.extend((0..=255u8).cycle().take(1000));
+        data.extend([7; 70]);
+        data.push(0);
+
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+
+        assert_eq!(decompress(&compress(b""), 0).unwrap(), b"");
+    }
+}
@@ -1,6 +1,7 @@
 // https://quakewiki.org/wiki/.pak
 
 mod common;
+mod daikatana;
 mod reader;
 mod reader_bits;
 #[cfg(test)]
@@ -9,5 +10,5 @@ mod writer;
 #[cfg(test)]
 mod writer_tests;
 
-pub use reader::make_reader;
-pub use writer::create_archive;
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -1,10 +1,12 @@
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::collections::HashMap;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::{fs, io};
 
 use super::reader_bits::read_file_index;
 use super::{
-    common::PAK_SIGNATURE,
+    common::{Variant, HEADER_SIZE, PAK_SIGNATURE, SIN_SIGNATURE},
+    daikatana,
     reader_bits::{File, Header},
 };
 
@@ -13,44 +15,116 @@ use crate::{FileType, ReadEx};
 pub struct Reader {
     stm: BufReader<fs::File>,
     files: Vec<File>,
+    variant: Variant,
 }
 
 impl Reader {
-    fn open(path: &Path, _options: crate::reader::Options) -> crate::reader::Result<Self> {
+    fn open(path: &Path, options: crate::reader::Options) -> crate::reader::Result<Self> {
+        let variant = match options.params.get("variant") {
+            Some(name) => Some(Variant::from_name(name).ok_or_else(|| {
+                let names: Vec<_> = Variant::ALL.iter().map(|v| v.name()).collect();
+                crate::reader::Error::InvalidParameter(
+                    "variant",
+                    format!("unknown variant '{name}', expected {}", names.join("/")),
+                )
+            })?),
+            None => None,
+        };
+
         let file = fs::File::open(path).map_err(crate::reader::Error::OpeningInputFile)?;
+        let file_size = file
+            .metadata()
+            .map_err(crate::reader::Error::ReadingInputFileMetadata)?
+            .len();
         let mut stm = BufReader::new(file);
 
         let signature = stm
             .read_u8_vec(4)
             .map_err(crate::reader::Error::ReadingSignature)?;
 
-        if !signature.eq(PAK_SIGNATURE) {
+        let expected_signature = match variant {
+            Some(variant) => variant.signature(),
+            None if signature == SIN_SIGNATURE => SIN_SIGNATURE,
+            None => PAK_SIGNATURE,
+        };
+
+        if !signature.eq(expected_signature) {
             return Err(crate::reader::Error::InvalidSignature {
                 signature,
-                expected_signature: PAK_SIGNATURE.to_vec(),
+                expected_signature: expected_signature.to_vec(),
             });
         }
 
         // header
         let hdr = Header::read(&mut stm).map_err(crate::reader::Error::ReadingHeader)?;
 
-        if hdr.index_size % 64 > 0 {
-            return Err(crate::reader::Error::InvalidHeader(
-                "file index size should be a multiple of 64".to_string(),
-            ));
+        let variant = match variant {
+            Some(variant) => variant,
+            None if signature == SIN_SIGNATURE => Variant::Sin,
+            None => detect_pack_variant(&mut stm, &hdr, file_size)?,
+        };
+
+        let entry_size = variant.entry_size() as u32;
+
+        if hdr.index_size % entry_size > 0 {
+            return Err(crate::reader::Error::InvalidHeader(format!(
+                "file index size should be a multiple of {entry_size}"
+            )));
         }
 
-        let file_count = usize::try_from(hdr.index_size / 64)
+        let file_count = usize::try_from(hdr.index_size / entry_size)
             .expect("usize should be large enough to hold file count");
 
         // files
-        let mut files = read_file_index(&mut stm, hdr.index_offset, file_count)
+        let mut files = read_file_index(&mut stm, variant, hdr.index_offset, file_count)
             .map_err(crate::reader::Error::ReadingFileIndex)?;
 
         // sort by offset
         files.sort_by_key(|f| f.offset);
 
-        Ok(Reader { stm, files })
+        Ok(Reader {
+            stm,
+            files,
+            variant,
+        })
+    }
+}
+
+/// Quake and Daikatana archives have the same signature, but different sizes of index entries.
+/// If the index size fits both, the index is checked to be a valid Quake one: non-empty names
+/// and file data between the header and the end of the archive.
+fn detect_pack_variant(
+    stm: &mut BufReader<fs::File>,
+    hdr: &Header,
+    file_size: u64,
+) -> crate::reader::Result<Variant> {
+    let fits = |variant: Variant| hdr.index_size % variant.entry_size() as u32 == 0;
+
+    match (fits(Variant::Quake), fits(Variant::Daikatana)) {
+        (true, false) => Ok(Variant::Quake),
+        (false, true) => Ok(Variant::Daikatana),
+        (false, false) => Err(crate::reader::Error::InvalidHeader(format!(
+            "file index size should be a multiple of {} or {}",
+            Variant::Quake.entry_size(),
+            Variant::Daikatana.entry_size()
+        ))),
+        (true, true) => {
+            let file_count = (hdr.index_size as usize) / Variant::Quake.entry_size();
+            let valid = read_file_index(stm, Variant::Quake, hdr.index_offset, file_count)
+                .is_ok_and(|files| {
+                    files.iter().all(|file| {
+                        !file.name.as_os_str().is_empty()
+                            && file.offset >= HEADER_SIZE
+                            && u64::from(file.offset) + u64::from(file.size) <= file_size
+                    })
+                });
+
+            Ok(if valid {
+                Variant::Quake
+            } else {
+                Variant::Daikatana
+            })
+        }
     }
 }
 
@@ -85,9 +159,41 @@ impl crate::reader::Reader for Reader {
             .seek(SeekFrom::Start(u64::from(file.offset)))
             .map_err(crate::reader::Error::ReadingInputFile)?;
 
-        let stm = self.stm.by_ref().take(u64::from(file.size));
+        let mut stm = self.stm.by_ref().take(u64::from(file.packed_size()));
+
+        if file.compressed_size.is_some() {
+            let mut compressed = Vec::new();
+            stm.read_to_end(&mut compressed)
+                .map_err(crate::reader::Error::ReadingInputFile)?;
+            let data = daikatana::decompress(&compressed, file.size as usize)
+                .map_err(crate::reader::Error::ReadingInputFile)?;
+
+            return Ok(Box::new(Cursor::new(data)));
+        }
+
         Ok(Box::new(stm))
     }
+
+    fn file_attrs(&self, index: usize) -> Vec<(&'static str, String)> {
+        let file = self
+            .files
+            .get(index)
+            .expect("`index` should be within boundaries");
+
+        match file.compressed_size {
+            Some(compressed_size) => vec![
+                ("compression", String::from("rle")),
+                ("packed", compressed_size.to_string()),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("variant"), self.variant.name().to_string());
+        attrs
+    }
 }
 
 pub fn make_reader(
@@ -96,3 +202,46 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let mut stm = BufReader::new(file);
+
+    let signature = stm.read_u8_vec(4)?;
+
+    if signature != PAK_SIGNATURE && signature != SIN_SIGNATURE {
+        return Ok(crate::Probe::Invalid(String::from("invalid signature")));
+    }
+
+    let hdr = Header::read(&mut stm)?;
+
+    if hdr.index_offset < HEADER_SIZE
+        || u64::from(hdr.index_offset) + u64::from(hdr.index_size) > file_size
+    {
+        return Ok(crate::Probe::Invalid(format!(
+            "file index at {} of {} bytes is out of bounds",
+            hdr.index_offset, hdr.index_size
+        )));
+    }
+
+    let variant = if signature == SIN_SIGNATURE {
+        Some(Variant::Sin)
+    } else {
+        [Variant::Quake, Variant::Daikatana]
+            .into_iter()
+            .find(|variant| hdr.index_size % variant.entry_size() as u32 == 0)
+    };
+
+    match variant {
+        Some(Variant::Sin) => Ok(crate::Probe::Valid(String::from("SiN"))),
+        Some(_) => Ok(crate::Probe::Valid(format!(
+            "file index of {} bytes",
+            hdr.index_size
+        ))),
+        None => Ok(crate::Probe::Invalid(format!(
+            "file index size {} does not fit any variant",
+            hdr.index_size
+        ))),
+    }
+}
@@ -6,6 +6,7 @@ use std::{
 
 use encoding_rs::WINDOWS_1252;
 
+use super::common::{Variant, DAIKATANA_COMPRESSED};
 use crate::{utils::buffer_to_zstring, PathBufUtils, ReadEx};
 
 pub struct Header {
@@ -27,26 +28,45 @@ pub struct File {
     pub name: PathBuf,
     pub offset: u32,
     pub size: u32,
+    /// Size of compressed data, Daikatana only.
+    pub compressed_size: Option<u32>,
 }
 
 impl File {
     #[inline]
-    pub fn read(r: &mut impl BufRead) -> Result<Self> {
-        let mut name_buf = vec![0u8; 56];
+    pub fn read(r: &mut impl BufRead, variant: Variant) -> Result<Self> {
+        let mut name_buf = vec![0u8; variant.name_size()];
         r.read_exact(&mut name_buf)?;
 
         let name = buffer_to_zstring(&name_buf, WINDOWS_1252)?.to_string();
+        let offset = r.read_u32_le()?;
+        let size = r.read_u32_le()?;
+
+        let compressed_size = if variant == Variant::Daikatana {
+            let compressed_size = r.read_u32_le()?;
+            let flags = r.read_u32_le()?;
+            (flags & DAIKATANA_COMPRESSED != 0).then_some(compressed_size)
+        } else {
+            None
+        };
 
         Ok(Self {
             name: PathBuf::from_unix(&name),
-            offset: r.read_u32_le()?,
-            size: r.read_u32_le()?,
+            offset,
+            size,
+            compressed_size,
         })
     }
+
+    /// Size of the data in the archive.
+    pub fn packed_size(&self) -> u32 {
+        self.compressed_size.unwrap_or(self.size)
+    }
 }
 
 pub fn read_file_index(
     r: &mut BufReader<fs::File>,
+    variant: Variant,
     index_offset: u32,
     file_count: usize,
 ) -> Result<Vec<File>> {
@@ -55,7 +75,7 @@ pub fn read_file_index(
     r.seek(SeekFrom::Start(u64::from(index_offset)))?;
 
     for _ in 0..file_count {
-        let file = File::read(r)?;
+        let file = File::read(r, variant)?;
         files.push(file);
     }
 
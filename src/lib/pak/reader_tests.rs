@@ -5,9 +5,83 @@ use std::path::Path;
 fn new_reader() {
     let res = super::make_reader(
         Path::new("./samples/pak/correct.pak"),
-        crate::reader::Options { strict: false },
+        crate::reader::Options {
+            strict: false,
+            ..Default::default()
+        },
     );
 
     let rdr = res.ok().unwrap();
     assert_eq!(rdr.file_count(), 5);
 }
+
+#[cfg(test)]
+fn read_files(path: &Path, params: &[(&str, &str)]) -> (String, Vec<(String, Vec<u8>)>) {
+    use std::io::Read;
+
+    let options = crate::reader::Options {
+        strict: true,
+        params: params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    };
+    let mut rdr = super::make_reader(path, options).unwrap();
+    let mut files = Vec::new();
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(file.size, Some(data.len() as u64));
+        files.push((file.name.to_str().unwrap().replace('\\', "/"), data));
+    }
+
+    (rdr.attrs()["variant"].clone(), files)
+}
+
+#[rstest::rstest]
+#[case("sin.pak", &[], "sin")]
+#[case("daikatana.pak", &[], "daikatana")]
+#[case("daikatana.pak", &[("variant", "daikatana")], "daikatana")]
+fn variants(#[case] name: &str, #[case] params: &[(&str, &str)], #[case] variant: &str) {
+    let (detected, files) = read_files(&Path::new("./samples/pak").join(name), params);
+
+    assert_eq!(detected, variant);
+
+    let mut e1m1 = vec![0u8; 40];
+    e1m1.extend(b"map!!!!!!!!!!");
+
+    assert_eq!(
+        files,
+        vec![
+            (
+                String::from("readme.txt"),
+                b"SiN and Daikatana archives\n".to_vec()
+            ),
+            (String::from("maps/e1m1.txt"), e1m1),
+            (String::from("empty"), Vec::new()),
+        ]
+    );
+}
+
+#[rstest::rstest]
+#[case("sin.pak", "quake")]
+#[case("correct.pak", "sin")]
+#[case("correct.pak", "unreal")]
+fn wrong_variant(#[case] name: &str, #[case] variant: &str) {
+    let options = crate::reader::Options {
+        strict: true,
+        params: [(String::from("variant"), variant.to_string())].into(),
+    };
+    let res = super::make_reader(&Path::new("./samples/pak").join(name), options);
+
+    assert!(matches!(
+        res,
+        Err(crate::reader::Error::InvalidSignature { .. }
+            | crate::reader::Error::InvalidParameter(..))
+    ));
+}
@@ -1,26 +1,44 @@
 use std::{
     collections::HashMap,
     fs,
-    io::{self, Cursor, Seek, SeekFrom, Write},
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
-use crate::{writer, FileType, InputFile, InputFileList, PathBufUtils, WriteEx};
+use crate::{writer, FileType, InputFile, InputFileList, Overhead, PathBufUtils, WriteEx};
 
-use super::common::{PAK_FILE_ENTRY_SIZE, PAK_SIGNATURE};
+use super::{
+    common::{Variant, DAIKATANA_COMPRESSED, HEADER_SIZE},
+    daikatana,
+};
+
+/// Estimate for `max-size`: SiN directory entries, the largest ones, with fixed-size names.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: HEADER_SIZE as u64,
+    entry_size: Variant::Sin.entry_size() as u64,
+    name_copies: 0,
+};
+
+struct Options {
+    variant: Variant,
+    /// Daikatana only.
+    compress: bool,
+}
 
 pub fn create_archive(
     input_files: InputFileList,
     path: &Path,
-    _params: &HashMap<String, String>,
+    params: &HashMap<String, String>,
 ) -> writer::Result<()> {
+    let options = parse_options(params)?;
+    let variant = options.variant;
     let mut out = fs::File::create(path).map_err(writer::Error::CreatingOutputFile)?;
 
-    out.write_all(PAK_SIGNATURE)
+    out.write_all(variant.signature())
         .map_err(writer::Error::WritingHeader)?;
 
     // header placeholder
-    out.seek(SeekFrom::Start(12))
+    out.seek(SeekFrom::Start(u64::from(HEADER_SIZE)))
         .map_err(writer::Error::WritingHeader)?;
 
     // files and making file index
@@ -28,8 +46,9 @@ pub fn create_archive(
         .iter()
         .filter(|f| f.file_type == FileType::RegularFile)
         .collect();
-    let mut index_buffer = vec![0u8; input_files.len() * PAK_FILE_ENTRY_SIZE];
+    let mut index_buffer = vec![0u8; input_files.len() * variant.entry_size()];
     let mut index_cursor = Cursor::new(&mut index_buffer);
+    let max_name_len = variant.name_size() - 1;
 
     for input_file in input_files {
         let metadata = input_file.src_path.metadata().map_err(|err| {
@@ -42,8 +61,8 @@ pub fn create_archive(
             .try_to_unix()
             .map_err(|err| writer::Error::InvalidInputFileName(input_file.dst_path.clone(), err))?;
 
-        if path.len() > 55 {
-            return Err(writer::Error::InputFileNameTooLong(path, 55));
+        if path.len() > max_name_len {
+            return Err(writer::Error::InputFileNameTooLong(path, max_name_len));
         }
 
         let offset = out
@@ -53,14 +72,18 @@ pub fn create_archive(
         let mut file = fs::File::open(&input_file.src_path)
             .map_err(|err| writer::Error::OpeningInputFile(input_file.src_path.clone(), err))?;
 
-        io::copy(&mut file, &mut out)
-            .map_err(|err| writer::Error::ArchivingInputFile(input_file.src_path.clone(), err))?;
+        let compressed_size = if options.compress {
+            write_compressed(&mut file, &mut out)
+        } else {
+            io::copy(&mut file, &mut out).map(|_| None)
+        }
+        .map_err(|err| writer::Error::ArchivingInputFile(input_file.src_path.clone(), err))?;
 
         let offset = u32::try_from(offset)
             .map_err(|_| writer::Error::InputFileLarger4GiB(input_file.src_path.clone()))?;
 
         index_cursor
-            .write_all(format!("{:\0<56}", path).as_bytes())
+            .write_all(format!("{:\0<width$}", path, width = variant.name_size()).as_bytes())
             .expect("writing to memory buffer");
         index_cursor
             .write_u32_le(offset)
@@ -68,6 +91,20 @@ pub fn create_archive(
         index_cursor
             .write_u32_le(size)
             .expect("writing to memory buffer");
+
+        if variant == Variant::Daikatana {
+            let (packed_size, flags) = match compressed_size {
+                Some(compressed_size) => (compressed_size, DAIKATANA_COMPRESSED),
+                None => (size, 0),
+            };
+
+            index_cursor
+                .write_u32_le(packed_size)
+                .expect("writing to memory buffer");
+            index_cursor
+                .write_u32_le(flags)
+                .expect("writing to memory buffer");
+        }
     }
 
     // encode, compress file index
@@ -92,3 +129,43 @@ pub fn create_archive(
 
     Ok(())
 }
+
+fn parse_options(params: &HashMap<String, String>) -> writer::Result<Options> {
+    let variant = match params.get("variant") {
+        None => Variant::Quake,
+        Some(value) => Variant::from_name(value).ok_or_else(|| {
+            writer::Error::InvalidParameter(
+                "variant",
+                format!("expected 'quake', 'sin' or 'daikatana', got '{value}'"),
+            )
+        })?,
+    };
+    let compress = params.get("compress").is_some_and(|v| v == "true");
+
+    if compress && variant != Variant::Daikatana {
+        return Err(writer::Error::InvalidParameter(
+            "compress",
+            String::from("only Daikatana archives are compressed"),
+        ));
+    }
+
+    Ok(Options { variant, compress })
+}
+
+/// Writes compressed data if it is smaller, returns compressed size in that case.
+fn write_compressed(file: &mut fs::File, out: &mut fs::File) -> io::Result<Option<u32>> {
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    let compressed = daikatana::compress(&data);
+
+    if compressed.len() < data.len() {
+        out.write_all(&compressed)?;
+        Ok(Some(u32::try_from(compressed.len()).expect(
+            "compressed data should be smaller than the input file",
+        )))
+    } else {
+        out.write_all(&data)?;
+        Ok(None)
+    }
+}
@@ -1,6 +1,12 @@
 use hex_literal::hex;
+use rstest::rstest;
 use sha1::{Digest, Sha1};
-use std::{collections::HashMap, fs, io, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 use tempdir::TempDir;
 
 use crate::InputFileListBuilder;
@@ -25,3 +31,103 @@ fn correct() {
 
     assert_eq!(hash[..], hex!("994bc521e3d39137a9fab494ee854778f815f905"));
 }
+
+fn read_back(path: &Path) -> (String, Vec<(PathBuf, Vec<u8>)>) {
+    let mut rdr = crate::pak::make_reader(path, Default::default()).unwrap();
+    let mut files = Vec::new();
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        files.push((file.name, data));
+    }
+
+    (rdr.attrs()["variant"].clone(), files)
+}
+
+#[rstest]
+#[case("sin", false, hex!("a1214196d6a2ecae9bc8d5b2f9e8bad3c1d3bea4"))]
+#[case("daikatana", false, hex!("5f324a8724d0df10b65dd56c5fb6e1c731e3842c"))]
+#[case("daikatana", true, hex!("de6fd92cdfc0daa1ad2cd9f379c6af31531d4b9e"))]
+fn variants(#[case] variant: &str, #[case] compress: bool, #[case] expected_hash: [u8; 20]) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.pak");
+    let params = HashMap::from([
+        (String::from("variant"), variant.to_string()),
+        (String::from("compress"), compress.to_string()),
+    ]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let mut file = fs::File::open(&output_path).unwrap();
+    let mut hasher = Sha1::new();
+    io::copy(&mut file, &mut hasher).unwrap();
+    let hash = hasher.finalize();
+
+    assert_eq!(hash[..], expected_hash);
+
+    let (detected, files) = read_back(&output_path);
+    assert_eq!(detected, variant);
+    assert_eq!(files.len(), 5);
+
+    for (name, data) in files {
+        assert_eq!(
+            data,
+            fs::read(Path::new("./samples/unpacked").join(name)).unwrap()
+        );
+    }
+}
+
+/// 8 Daikatana entries take as much space as 9 Quake ones.
+#[test]
+fn ambiguous_index_size() {
+    let input_dir = TempDir::new("flpak-tests").unwrap();
+
+    for index in 0..8 {
+        let mut data = vec![0u8; 100 * index];
+        data.extend(format!("file {index}").bytes());
+        fs::write(input_dir.path().join(format!("{index}.bin")), data).unwrap();
+    }
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(input_dir.path())
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.pak");
+    let params = HashMap::from([
+        (String::from("variant"), String::from("daikatana")),
+        (String::from("compress"), String::from("true")),
+    ]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let (detected, files) = read_back(&output_path);
+    assert_eq!(detected, "daikatana");
+    assert_eq!(files.len(), 8);
+    assert_eq!(files[7].1.len(), 706);
+}
+
+#[rstest]
+#[case("variant", "hexen")]
+#[case("compress", "true")]
+fn invalid_options(#[case] name: &str, #[case] value: &str) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([(name.to_string(), value.to_string())]);
+    let res = super::create_archive(
+        InputFileListBuilder::new().build(),
+        &dir.path().join("archive.pak"),
+        &params,
+    );
+
+    assert!(matches!(res, Err(crate::writer::Error::InvalidParameter(n, _)) if n == name));
+}
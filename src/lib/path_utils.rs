@@ -20,12 +20,8 @@ pub trait PathBufUtils {
 
 impl PathBufUtils for PathBuf {
     fn is_safe(&self) -> bool {
-        self.components().all(|cmp| {
-            match cmp {
-                Component::Normal(_) => true,
-                _ => false,
-            }
-        })
+        self.components()
+            .all(|cmp| matches!(cmp, Component::Normal(_)))
     }
 
     fn from_win(path: &str) -> PathBuf {
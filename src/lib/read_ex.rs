@@ -92,7 +92,7 @@ pub trait ReadEx: BufRead {
     /// Reads a sized null-terminated string.
     /// First byte signifies integer length of following string including null.
     fn read_u8_zstring(&mut self, encoding: &'static Encoding) -> Result<String> {
-        let len = self.read_u8()?.try_into().expect("should fit into `usize`");
+        let len = usize::from(self.read_u8()?);
 
         if len == 0 {
             return Err(io_error!(
@@ -115,7 +115,7 @@ pub trait ReadEx: BufRead {
     /// Reads a sized string.
     /// First byte signifies integer length of following string.
     fn read_u8_string(&mut self, encoding: &'static Encoding) -> Result<String> {
-        let len = self.read_u8()?.try_into().expect("should fit into `usize`");
+        let len = usize::from(self.read_u8()?);
 
         let mut buf = vec![0u8; len];
         self.read_exact(&mut buf)?;
@@ -127,10 +127,7 @@ pub trait ReadEx: BufRead {
     /// Reads a sized string.
     /// First two bytes signify little endian 16 bit integer length of following string.
     fn read_u16le_string(&mut self, encoding: &'static Encoding) -> Result<String> {
-        let len: usize = self
-            .read_u16_le()?
-            .try_into()
-            .expect("should fit into `usize`");
+        let len = usize::from(self.read_u16_le()?);
 
         let mut buf = vec![0u8; len];
         self.read_exact(&mut buf)?;
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, io, path::PathBuf, result};
+use std::{collections::HashMap, io, path::PathBuf, result, time::SystemTime};
 
 use crate::FileType;
 
@@ -7,6 +7,9 @@ pub enum Error {
     #[error("failed to open file: {0}")]
     OpeningInputFile(#[source] io::Error),
 
+    #[error("failed to open chunk file '{0}': {1}")]
+    OpeningChunkFile(PathBuf, #[source] io::Error),
+
     #[error("failed to read file metadata: {0}")]
     ReadingInputFileMetadata(#[source] io::Error),
 
@@ -47,6 +50,27 @@ pub enum Error {
         expected_hash: String,
     },
 
+    #[error("invalid directory name hash '{hash}' for '{filename}', expected '{expected_hash}'")]
+    InvalidDirectoryNameHash {
+        filename: PathBuf,
+        hash: String,
+        expected_hash: String,
+    },
+
+    #[error("invalid file extension '{ext}' for '{filename}', expected '{expected_ext}'")]
+    InvalidFileExtension {
+        filename: PathBuf,
+        ext: String,
+        expected_ext: String,
+    },
+
+    #[error("invalid {what} checksum {checksum}, expected {expected_checksum}")]
+    InvalidChecksum {
+        what: String,
+        checksum: String,
+        expected_checksum: String,
+    },
+
     #[error("failed to read file index: {0}")]
     ReadingFileIndex(#[source] io::Error),
 
@@ -56,6 +80,9 @@ pub enum Error {
     #[error("not a regular file")]
     NotFile,
 
+    #[error("invalid option `{0}`: {1}")]
+    InvalidParameter(&'static str, String),
+
     #[error("{0}")]
     Unsupported(String),
 
@@ -71,9 +98,19 @@ pub struct File {
     pub size: Option<u64>,
 }
 
+/// Metadata of a file stored in the archive, restored on extraction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits, without setuid, setgid and sticky bits.
+    pub mode: Option<u32>,
+}
+
 #[derive(Default)]
 pub struct Options {
     pub strict: bool,
+    /// Format specific options, like `key=value` options of writers.
+    pub params: HashMap<String, String>,
 }
 
 pub trait Reader {
@@ -84,4 +121,18 @@ pub trait Reader {
     fn attrs(&self) -> HashMap<String, String> {
         HashMap::new()
     }
+
+    /// Verifies archive-wide checksums. Checksums of individual files are verified while reading them in strict mode.
+    fn verify(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Format specific details of a file, e.g. texture dimensions.
+    fn file_attrs(&self, _index: usize) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    fn file_metadata(&self, _index: usize) -> FileMetadata {
+        FileMetadata::default()
+    }
 }
@@ -2,17 +2,20 @@ use std::{
     collections::HashMap,
     fs::File,
     io::{self, Read},
-    path::Path,
+    path::{Path, PathBuf},
     result,
 };
 
-use crate::{ba2, bsa, bsa_mw, pak, reader, rpa, vpk, writer, zip, InputFileList};
+use crate::{
+    ba2, bsa, bsa_mw, pak, parse_max_size, reader, rpa, split_input_files, ue_pak, vpk, wad,
+    writer, zip, Candidate, InputFileList, PartNaming, Probe, ProbeFn, Signature, SplitScheme,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("unknown format '{0}'")]
     UnknownFormat(String),
-    #[error("unable to detect format by signature")]
+    #[error("unable to detect format")]
     UnableToDetect,
     #[error("reading '{0}' not supported")]
     ReadingUnsupported(String),
@@ -21,6 +24,8 @@ pub enum Error {
     #[error("{0}")]
     ReaderError(reader::Error),
     #[error("{0}")]
+    WriterError(writer::Error),
+    #[error("{0}")]
     IoError(io::Error),
 }
 
@@ -34,9 +39,12 @@ pub struct FormatDesc {
     pub name: &'static str,
     pub description: &'static str,
     pub extensions: Vec<&'static str>,
-    pub signatures: Vec<Vec<u8>>,
+    pub signatures: Vec<Signature>,
+    /// Validates the header of an archive, used in format detection.
+    pub probe_fn: Option<ProbeFn>,
     pub make_reader_fn: Option<MakeReaderFn>,
     pub writer_fn: Option<WriterFn>,
+    pub split_scheme: SplitScheme,
 }
 
 pub struct Registry {
@@ -51,61 +59,128 @@ impl Registry {
                 name: "bsa-mw",
                 description: "Bethesda Archive (v100)",
                 extensions: vec!["bsa"],
-                signatures: vec![vec![0x00, 0x01, 0x00, 0x00]],
+                signatures: vec![Signature::new(&[0x00, 0x01, 0x00, 0x00])],
+                probe_fn: Some(bsa_mw::probe),
                 make_reader_fn: Some(bsa_mw::make_reader),
                 writer_fn: Some(bsa_mw::create_archive),
+                split_scheme: SplitScheme {
+                    overhead: bsa_mw::SPLIT_OVERHEAD,
+                    ..SplitScheme::default()
+                },
             },
             FormatDesc {
                 name: "bsa",
                 description: "Bethesda Archive (v103, v104, v105)",
                 extensions: vec!["bsa"],
-                signatures: vec![b"BSA\0".to_vec()],
+                signatures: vec![Signature::new(b"BSA\0")],
+                probe_fn: Some(bsa::probe),
                 make_reader_fn: Some(bsa::make_reader),
                 writer_fn: Some(bsa::create_archive),
+                split_scheme: SplitScheme {
+                    naming: PartNaming::Suffix,
+                    keep_folders_together: true,
+                    overhead: bsa::SPLIT_OVERHEAD,
+                },
             },
             FormatDesc {
                 name: "ba2",
                 description: "Bethesda Archive 2",
                 extensions: vec!["ba2"],
-                signatures: vec![b"BTDX".to_vec()],
+                signatures: vec![Signature::new(b"BTDX")],
+                probe_fn: Some(ba2::probe),
                 make_reader_fn: Some(ba2::make_reader),
-                writer_fn: None,
+                writer_fn: Some(ba2::create_archive),
+                split_scheme: SplitScheme {
+                    overhead: ba2::SPLIT_OVERHEAD,
+                    ..SplitScheme::default()
+                },
             },
             FormatDesc {
                 name: "pak",
-                description: "id Software .pak",
-                extensions: vec!["vpk"],
-                signatures: vec![b"PACK".to_vec()],
+                description: "id Software .pak (Quake, Quake 2, SiN, Daikatana)",
+                extensions: vec!["pak"],
+                signatures: vec![Signature::new(b"PACK"), Signature::new(b"SPAK")],
+                probe_fn: Some(pak::probe),
                 make_reader_fn: Some(pak::make_reader),
                 writer_fn: Some(pak::create_archive),
+                split_scheme: SplitScheme {
+                    naming: PartNaming::Increment,
+                    keep_folders_together: false,
+                    overhead: pak::SPLIT_OVERHEAD,
+                },
             },
             FormatDesc {
                 name: "rpa",
                 description: "Ren'Py Archive",
-                extensions: vec!["rpa"],
-                signatures: vec![b"RPA-".to_vec()],
+                extensions: vec!["rpa", "rpi"],
+                signatures: vec![
+                    Signature::new(b"RPA-3.0 "),
+                    Signature::new(b"RPA-3.2 "),
+                    Signature::new(b"RPA-2.0 "),
+                    Signature::new(b"ALT-1.0 "),
+                    Signature::new(b"ZiX-12A "),
+                ],
+                probe_fn: Some(rpa::probe),
                 make_reader_fn: Some(rpa::make_reader),
                 writer_fn: Some(rpa::create_archive),
+                split_scheme: SplitScheme {
+                    overhead: rpa::SPLIT_OVERHEAD,
+                    ..SplitScheme::default()
+                },
+            },
+            FormatDesc {
+                name: "ue-pak",
+                description: "Unreal Engine .pak (UE4, UE5)",
+                extensions: vec!["pak"],
+                signatures: Vec::new(),
+                probe_fn: Some(ue_pak::probe),
+                make_reader_fn: Some(ue_pak::make_reader),
+                writer_fn: None,
+                split_scheme: SplitScheme::default(),
             },
             FormatDesc {
                 name: "vpk",
                 description: "Valve Pack",
                 extensions: vec!["vpk"],
-                signatures: vec![vec![0x34, 0x12, 0xAA, 0x55]],
+                signatures: vec![Signature::new(&[0x34, 0x12, 0xAA, 0x55])],
+                probe_fn: Some(vpk::probe),
                 make_reader_fn: Some(vpk::make_reader),
-                writer_fn: None,
+                writer_fn: Some(vpk::create_archive),
+                split_scheme: SplitScheme {
+                    naming: PartNaming::DirSuffix,
+                    keep_folders_together: false,
+                    overhead: vpk::SPLIT_OVERHEAD,
+                },
+            },
+            FormatDesc {
+                name: "wad",
+                description: "Doom WAD (IWAD, PWAD)",
+                extensions: vec!["wad"],
+                signatures: vec![Signature::new(b"IWAD"), Signature::new(b"PWAD")],
+                probe_fn: Some(wad::probe),
+                make_reader_fn: Some(wad::make_reader),
+                writer_fn: Some(wad::create_archive),
+                split_scheme: SplitScheme {
+                    overhead: wad::SPLIT_OVERHEAD,
+                    ..SplitScheme::default()
+                },
             },
             FormatDesc {
                 name: "zip",
                 description: "ZIP",
                 extensions: vec!["zip"],
                 signatures: vec![
-                    b"PK\x03\x04".to_vec(),
-                    b"PK\x05\x06".to_vec(),
-                    b"PK\x07\x08".to_vec(),
+                    Signature::new(b"PK\x03\x04"),
+                    Signature::new(b"PK\x05\x06"),
+                    Signature::new(b"PK\x07\x08"),
                 ],
+                probe_fn: Some(zip::probe),
                 make_reader_fn: Some(zip::make_reader),
                 writer_fn: Some(zip::create_archive),
+                split_scheme: SplitScheme {
+                    overhead: zip::SPLIT_OVERHEAD,
+                    ..SplitScheme::default()
+                },
             },
         ];
 
@@ -130,15 +205,14 @@ impl Registry {
 
             format_desc
         } else {
-            let mut sig = vec![0u8; 4];
-            let mut file = File::open(path).map_err(Error::IoError)?;
-            file.read_exact(&mut sig).map_err(Error::IoError)?;
+            let candidates = self.detect(path)?;
 
-            let Some(format_desc) = self.find_format_by_signature(&sig) else {
+            let Some(candidate) = candidates.first().filter(|c| c.is_plausible()) else {
                 return Err(Error::UnableToDetect);
             };
 
-            format_desc
+            self.find_format_by_name(candidate.format)
+                .expect("format should exist")
         };
 
         let Some(make_reader_fn) = format_desc.make_reader_fn else {
@@ -160,16 +234,124 @@ impl Registry {
         Ok(writer_fn)
     }
 
+    /// Creates an archive. If `max-size` option is set, input files are split across several archives.
+    /// Returns paths of created archives.
+    pub fn create_archive(
+        &self,
+        format: &str,
+        input_files: InputFileList,
+        path: &Path,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<PathBuf>> {
+        let writer_fn = self.create_writer(format)?;
+
+        let Some(max_size) = parse_max_size(params).map_err(Error::WriterError)? else {
+            writer_fn(input_files, path, params).map_err(Error::WriterError)?;
+            return Ok(vec![path.to_path_buf()]);
+        };
+
+        let split_scheme = self
+            .find_format_by_name(format)
+            .expect("format should exist")
+            .split_scheme;
+        let parts =
+            split_input_files(input_files, max_size, &split_scheme).map_err(Error::WriterError)?;
+        let mut paths = Vec::with_capacity(parts.len());
+
+        for (index, part) in parts.into_iter().enumerate() {
+            let part_path = split_scheme.naming.part_path(path, index);
+            writer_fn(part, &part_path, params).map_err(Error::WriterError)?;
+            paths.push(part_path);
+        }
+
+        Ok(paths)
+    }
+
     #[must_use]
     pub fn find_format_by_name(&self, name: &str) -> Option<&FormatDesc> {
         self.formats.iter().find(|f| f.name == name)
     }
 
+    /// Finds the first format with a signature matching the beginning of a file.
     #[must_use]
-    pub fn find_format_by_signature(&self, buf: &[u8]) -> Option<&FormatDesc> {
+    pub fn find_format_by_signature(&self, head: &[u8]) -> Option<&FormatDesc> {
         self.formats
             .iter()
-            .find(|f| f.signatures.iter().any(|sig| sig == buf))
+            .find(|f| f.signatures.iter().any(|sig| sig.matches(head)))
+    }
+
+    /// Returns formats the archive may be in, the most likely first. Every format is scored by
+    /// its signatures, header validation and file extension, formats with no evidence are omitted.
+    pub fn detect(&self, path: &Path) -> Result<Vec<Candidate>> {
+        let head_size = self
+            .formats
+            .iter()
+            .flat_map(|f| f.signatures.iter().map(Signature::end))
+            .max()
+            .unwrap_or_default();
+
+        let mut head = Vec::new();
+        File::open(path)
+            .and_then(|file| file.take(head_size).read_to_end(&mut head))
+            .map_err(Error::IoError)?;
+
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        let mut candidates = Vec::new();
+
+        for format_desc in &self.formats {
+            let mut candidate = Candidate {
+                format: format_desc.name,
+                score: 0,
+                reasons: Vec::new(),
+            };
+            let mut evidence = false;
+
+            if let Some(sig) = format_desc.signatures.iter().find(|sig| sig.matches(&head)) {
+                // longer signatures are less likely to match by accident
+                candidate.score += Candidate::SIGNATURE_SCORE + sig.bytes.len() as i32;
+                candidate
+                    .reasons
+                    .push(format!("signature {}", sig.display()));
+                evidence = true;
+            }
+
+            if let Some(ext) = extension.as_deref() {
+                if format_desc.extensions.contains(&ext) {
+                    candidate.score += Candidate::EXTENSION_SCORE;
+                    candidate.reasons.push(format!("extension '.{ext}'"));
+                    evidence = true;
+                }
+            }
+
+            if let Some(probe_fn) = format_desc.probe_fn {
+                match probe_fn(path) {
+                    Ok(Probe::Valid(what)) => {
+                        candidate.score += Candidate::VALID_HEADER_SCORE;
+                        candidate.reasons.push(format!("valid header: {what}"));
+                        evidence = true;
+                    }
+                    Ok(Probe::Invalid(why)) => {
+                        candidate.score += Candidate::INVALID_HEADER_SCORE;
+                        candidate.reasons.push(format!("invalid header: {why}"));
+                    }
+                    Err(err) => {
+                        candidate.score += Candidate::INVALID_HEADER_SCORE;
+                        candidate.reasons.push(format!("invalid header: {err}"));
+                    }
+                }
+            }
+
+            if evidence {
+                candidates.push(candidate);
+            }
+        }
+
+        candidates.sort_by_key(|c| -c.score);
+
+        Ok(candidates)
     }
 }
 
@@ -188,7 +370,7 @@ mod tests {
         let registry = super::Registry::new();
         let formats = registry.list();
 
-        assert_eq!(formats.len(), 7);
+        assert_eq!(formats.len(), 9);
     }
 
     #[test]
@@ -197,10 +379,13 @@ mod tests {
         let res = registry.create_reader(
             Some(String::from("bsa-mw")),
             Path::new("./samples/bsa-mw/correct.bsa"),
-            crate::reader::Options { strict: false },
+            crate::reader::Options {
+                strict: false,
+                ..Default::default()
+            },
         );
 
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
     }
 
     #[test]
@@ -209,10 +394,13 @@ mod tests {
         let res = registry.create_reader(
             Some(String::from("nonexistent-format")),
             Path::new("./samples/bsa-mw/correct.bsa"),
-            crate::reader::Options { strict: false },
+            crate::reader::Options {
+                strict: false,
+                ..Default::default()
+            },
         );
 
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_err());
     }
 
     #[test]
@@ -221,16 +409,242 @@ mod tests {
         let res = registry.create_reader(
             None,
             Path::new("./samples/bsa-mw/correct.bsa"),
-            crate::reader::Options { strict: false },
+            crate::reader::Options {
+                strict: false,
+                ..Default::default()
+            },
+        );
+
+        assert!(res.is_ok());
+    }
+
+    #[rstest::rstest]
+    #[case("./samples/bsa-mw/correct.bsa", "bsa-mw")]
+    #[case("./samples/bsa/correct_v104.bsa", "bsa")]
+    #[case("./samples/ba2/correct_general.ba2", "ba2")]
+    #[case("./samples/pak/sin.pak", "pak")]
+    #[case("./samples/rpa/alt1.rpa", "rpa")]
+    #[case("./samples/ue_pak/v11.pak", "ue-pak")]
+    #[case("./samples/pak/correct.pak", "pak")]
+    #[case("./samples/rpa/v1.rpa", "rpa")]
+    #[case("./samples/rpa/v1.rpi", "rpa")]
+    #[case("./samples/wad/doom.wad", "wad")]
+    #[case("./samples/zip/correct.zip", "zip")]
+    // damaged archives are still opened by signature
+    #[case("./samples/bsa/invalid_version.bsa", "bsa")]
+    fn detect(#[case] path: &str, #[case] format: &str) {
+        let registry = super::Registry::new();
+        let candidates = registry.detect(Path::new(path)).unwrap();
+
+        assert_eq!(candidates[0].format, format);
+        assert!(candidates[0].is_plausible());
+        assert!(candidates[1..]
+            .iter()
+            .all(|c| c.score < candidates[0].score));
+    }
+
+    #[test]
+    fn detect_zip_with_prepended_data() {
+        let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+        let path = dir.path().join("archive.exe");
+        let mut data = vec![b'M', b'Z'];
+        data.resize(1000, 0);
+        data.extend(std::fs::read("./samples/zip/correct.zip").unwrap());
+        std::fs::write(&path, data).unwrap();
+
+        let registry = super::Registry::new();
+        let candidates = registry.detect(&path).unwrap();
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].format, "zip");
+        assert!(candidates[0].reasons[0].ends_with("1000 bytes prepended"));
+
+        let rdr = registry
+            .create_reader(None, &path, Default::default())
+            .unwrap();
+        assert_eq!(rdr.file_count(), 8);
+    }
+
+    #[test]
+    fn unable_to_detect() {
+        let registry = super::Registry::new();
+        let path = Path::new("./samples/unpacked/file001.txt");
+
+        assert!(registry.detect(path).unwrap().is_empty());
+        assert!(matches!(
+            registry.create_reader(None, path, Default::default()),
+            Err(super::Error::UnableToDetect)
+        ));
+    }
+
+    #[test]
+    fn extensions() {
+        let registry = super::Registry::new();
+
+        for format_desc in registry.list() {
+            let name = format_desc
+                .name
+                .trim_end_matches("-mw")
+                .trim_start_matches("ue-");
+            assert!(format_desc.extensions.contains(&name), "{name}");
+        }
+    }
+
+    #[test]
+    fn create_split_archive() {
+        let input_files = crate::InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .build();
+
+        let mut params = std::collections::HashMap::new();
+        params.insert(String::from("max-size"), String::from("32K"));
+
+        let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+        let registry = super::Registry::new();
+        let paths = registry
+            .create_archive("pak", input_files, &dir.path().join("pak0.pak"), &params)
+            .unwrap();
+
+        assert_eq!(
+            paths,
+            [dir.path().join("pak0.pak"), dir.path().join("pak1.pak")]
+        );
+        assert!(paths.iter().all(|path| path.is_file()));
+    }
+
+    /// Archives of small files are mostly headers and records, they have to fit into the estimate.
+    #[rstest::rstest]
+    #[case("bsa-mw", "")]
+    #[case("bsa", "embed-names=true")]
+    #[case("ba2", "compress=false")]
+    #[case("pak", "variant=sin")]
+    #[case("rpa", "version=3.2")]
+    #[case("vpk", "")]
+    #[case("zip", "method=stored")]
+    fn overhead_estimate(#[case] format: &str, #[case] params: &str) {
+        let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+        let input_dir = dir.path().join("input");
+
+        for name in ["meshes/a.nif", "meshes/b.nif", "textures/c.dds"] {
+            let path = input_dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, name).unwrap();
+        }
+
+        let input_files = crate::InputFileListBuilder::new()
+            .add_dir(&input_dir)
+            .unwrap()
+            .build();
+        let params = params
+            .split(',')
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let registry = super::Registry::new();
+        let overhead = registry
+            .find_format_by_name(format)
+            .unwrap()
+            .split_scheme
+            .overhead;
+        let estimate = overhead.header_size
+            + input_files
+                .iter()
+                .filter(|file| file.file_type == crate::FileType::RegularFile)
+                .map(|file| {
+                    overhead.entry(&file.dst_path) + file.src_path.metadata().unwrap().len()
+                })
+                .sum::<u64>();
+
+        let path = dir.path().join("archive");
+        registry
+            .create_archive(format, input_files, &path, &params)
+            .unwrap();
+
+        let size = path.metadata().unwrap().len();
+        assert!(size <= estimate, "{size} > {estimate}");
+    }
+
+    #[test]
+    fn create_split_vpk_archive() {
+        let input_files = crate::InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .build();
+
+        let mut params = std::collections::HashMap::new();
+        params.insert(String::from("max-size"), String::from("32K"));
+
+        let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+        let registry = super::Registry::new();
+        let paths = registry
+            .create_archive(
+                "vpk",
+                input_files,
+                &dir.path().join("pak01_dir.vpk"),
+                &params,
+            )
+            .unwrap();
+
+        assert_eq!(
+            paths,
+            [
+                dir.path().join("pak01_dir.vpk"),
+                dir.path().join("pak010_dir.vpk")
+            ]
         );
+        // every part has its own chunks
+        assert!(dir.path().join("pak01_000.vpk").is_file());
+        assert!(dir.path().join("pak010_000.vpk").is_file());
+
+        let mut names = Vec::new();
+
+        for path in &paths {
+            let mut rdr = registry
+                .create_reader(
+                    Some(String::from("vpk")),
+                    path,
+                    crate::reader::Options {
+                        strict: true,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            rdr.verify().unwrap();
 
-        assert_eq!(res.is_ok(), true);
+            for index in 0..rdr.file_count() {
+                let file = rdr.get_file(index);
+                let mut data = Vec::new();
+                std::io::Read::read_to_end(&mut rdr.create_file_reader(index).unwrap(), &mut data)
+                    .unwrap();
+                assert_eq!(
+                    data,
+                    std::fs::read(Path::new("./samples/unpacked").join(&file.name)).unwrap()
+                );
+                names.push(file.name);
+            }
+        }
+
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "dir1/file002.txt",
+                "dir1/img002.jpg",
+                "empty_dir/.gitkeep",
+                "empty_file",
+                "file001.txt",
+                "img001.png"
+            ]
+            .map(std::path::PathBuf::from)
+        );
     }
 
     #[test]
     fn create_writer() {
         let registry = super::Registry::new();
         let res = registry.create_writer("bsa-mw");
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
     }
 }
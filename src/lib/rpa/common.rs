@@ -1,5 +1,85 @@
-use std::collections::BTreeMap;
-
 pub const DEFAULT_KEY: u32 = 0x42424242;
 pub const RENPY_PADDING: &[u8; 17] = b"Made with Ren'Py.";
-pub type FileIndex = BTreeMap<String, Vec<(u64, u64, String)>>;
+/// `RPA-3.2 <offset> <unused> <key>\n`, the longest header.
+pub const HEADER_V3_2_SIZE: u64 = 43;
+/// Key stored in ALT-1.0 headers is XORed with this value.
+pub const ALT_KEY_MASK: u64 = 0xDABE8DF0;
+
+/// Part of file data: `prefix` bytes stored in the file index followed by `size` bytes at `offset`.
+/// Files usually consist of one segment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Segment {
+    pub offset: u64,
+    pub size: u64,
+    pub prefix: Vec<u8>,
+}
+
+impl Segment {
+    /// Size of the segment data, including the prefix.
+    pub fn total_size(&self) -> u64 {
+        self.prefix.len() as u64 + self.size
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Version {
+    /// No header, file index is in a separate `.rpi` file.
+    Rpa1,
+    /// `RPA-2.0 offset`, no key.
+    Rpa2,
+    /// `RPA-3.0 offset key`
+    Rpa3,
+    /// `RPA-3.2 offset unused key`
+    Rpa3_2,
+    /// `ALT-1.0 key offset`, the key is masked.
+    Alt1,
+    /// `ZiX-12A offset ...`, the key is computed by the game's own loader.
+    Zix12A,
+}
+
+impl Version {
+    pub const ALL: [Version; 6] = [
+        Version::Rpa1,
+        Version::Rpa2,
+        Version::Rpa3,
+        Version::Rpa3_2,
+        Version::Alt1,
+        Version::Zix12A,
+    ];
+
+    /// Name used in `version=` option.
+    pub fn name(self) -> &'static str {
+        match self {
+            Version::Rpa1 => "1.0",
+            Version::Rpa2 => "2.0",
+            Version::Rpa3 => "3.0",
+            Version::Rpa3_2 => "3.2",
+            Version::Alt1 => "alt-1.0",
+            Version::Zix12A => "zix-12a",
+        }
+    }
+
+    /// First word of the header, RPA-1.0 archives have no header.
+    pub fn signature(self) -> Option<&'static str> {
+        match self {
+            Version::Rpa1 => None,
+            Version::Rpa2 => Some("RPA-2.0"),
+            Version::Rpa3 => Some("RPA-3.0"),
+            Version::Rpa3_2 => Some("RPA-3.2"),
+            Version::Alt1 => Some("ALT-1.0"),
+            Version::Zix12A => Some("ZiX-12A"),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|version| version.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn from_signature(signature: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|version| version.signature() == Some(signature))
+    }
+}
@@ -4,8 +4,11 @@
 mod common;
 mod reader;
 mod reader_bits;
+#[cfg(test)]
 mod reader_tests;
 mod writer;
+#[cfg(test)]
+mod writer_tests;
 
-pub use reader::make_reader;
-pub use writer::create_archive;
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -1,41 +1,126 @@
 use std::{
+    collections::HashMap,
     fs,
-    io::{BufReader, Read, Seek, SeekFrom},
+    io::{self, BufReader, Read, Seek, SeekFrom},
     path::Path,
 };
 
-use super::reader_bits::{read_file_index, File, Header};
-use crate::FileType;
+use super::{
+    common::{Segment, Version},
+    reader_bits::{read_file_index, File, Header},
+};
+use crate::{io_error, FileType};
 
 pub struct Reader {
     stm: BufReader<fs::File>,
     files: Vec<File>,
+    version: Version,
 }
 
 impl Reader {
-    fn open(path: &Path, _options: crate::reader::Options) -> crate::reader::Result<Self> {
-        let file = fs::File::open(path).map_err(crate::reader::Error::OpeningInputFile)?;
+    fn open(path: &Path, options: crate::reader::Options) -> crate::reader::Result<Self> {
+        let version = match options.params.get("version") {
+            Some(name) => Some(Version::from_name(name).ok_or_else(|| {
+                let names: Vec<_> = Version::ALL.iter().map(|v| v.name()).collect();
+                crate::reader::Error::InvalidParameter(
+                    "version",
+                    format!("unknown version '{name}', expected {}", names.join("/")),
+                )
+            })?),
+            None => None,
+        };
+
+        let key = match options.params.get("key") {
+            Some(key) => Some(
+                u64::from_str_radix(key.trim_start_matches("0x"), 16).map_err(|_| {
+                    crate::reader::Error::InvalidParameter(
+                        "key",
+                        format!("'{key}' is not a hexadecimal number"),
+                    )
+                })?,
+            ),
+            None => None,
+        };
+
+        // RPA-1.0 index file can be opened in place of the archive
+        let is_index = path.extension().is_some_and(|ext| ext == "rpi");
+        let data_path = if is_index {
+            path.with_extension("rpa")
+        } else {
+            path.to_path_buf()
+        };
+        let index_path = data_path.with_extension("rpi");
+
+        let file = fs::File::open(&data_path).map_err(crate::reader::Error::OpeningInputFile)?;
         let mut stm = BufReader::new(file);
 
-        let hdr = Header::read(&mut stm).map_err(crate::reader::Error::ReadingHeader)?;
+        let line = if is_index {
+            None
+        } else {
+            Header::read_line(&mut stm).map_err(crate::reader::Error::ReadingHeader)?
+        };
 
-        if hdr.signature != "RPA-3.0" {
-            return Err(crate::reader::Error::InvalidStringSignature {
-                signature: hdr.signature,
-                expected_signature: "RPA-3.0",
-            });
-        }
+        let version = match version {
+            Some(version) => version,
+            None if is_index => Version::Rpa1,
+            None => detect_version(line.as_deref(), &index_path)?,
+        };
 
-        stm.seek(SeekFrom::Start(hdr.index_offset))
-            .map_err(crate::reader::Error::ReadingInputFile)?;
+        let files = if version == Version::Rpa1 {
+            let index =
+                fs::File::open(&index_path).map_err(crate::reader::Error::OpeningInputFile)?;
 
-        let files =
-            read_file_index(&mut stm, hdr.key).map_err(crate::reader::Error::ReadingFileIndex)?;
+            read_file_index(&mut BufReader::new(index), key.unwrap_or(0))
+                .map_err(crate::reader::Error::ReadingFileIndex)?
+        } else {
+            let hdr = Header::parse(line.as_deref().unwrap_or_default(), version)
+                .map_err(crate::reader::Error::ReadingHeader)?;
 
-        Ok(Reader { stm, files })
+            let key = match key.or(hdr.key) {
+                Some(key) => key,
+                None if version == Version::Zix12A => {
+                    return Err(crate::reader::Error::Unsupported(String::from(
+                        "ZiX-12A key is computed by the game's loader, pass it with `key=` option",
+                    )));
+                }
+                None => 0,
+            };
+
+            stm.seek(SeekFrom::Start(hdr.index_offset))
+                .map_err(crate::reader::Error::ReadingInputFile)?;
+
+            read_file_index(&mut stm, key).map_err(crate::reader::Error::ReadingFileIndex)?
+        };
+
+        Ok(Reader {
+            stm,
+            files,
+            version,
+        })
     }
 }
 
+/// Detects version by the first word of the header. Archives without a known header are
+/// RPA-1.0 if there is an index file next to them.
+fn detect_version(line: Option<&str>, index_path: &Path) -> crate::reader::Result<Version> {
+    let signature = line
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap_or_default();
+
+    if let Some(version) = Version::from_signature(signature) {
+        return Ok(version);
+    }
+
+    if index_path.exists() {
+        return Ok(Version::Rpa1);
+    }
+
+    Err(crate::reader::Error::InvalidStringSignature {
+        signature: signature.to_string(),
+        expected_signature: "RPA-3.0",
+    })
+}
+
 impl crate::reader::Reader for Reader {
     fn file_count(&self) -> usize {
         self.files.len()
@@ -50,7 +135,7 @@ impl crate::reader::Reader for Reader {
         crate::reader::File {
             name: file.name.clone(),
             file_type: FileType::RegularFile,
-            size: Some(file.size),
+            size: Some(file.size()),
         }
     }
 
@@ -63,12 +148,71 @@ impl crate::reader::Reader for Reader {
             .get(index)
             .expect("`index` should be within boundaries");
 
-        self.stm
-            .seek(SeekFrom::Start(file.offset))
-            .map_err(crate::reader::Error::ReadingInputFile)?;
+        Ok(Box::new(SegmentsReader {
+            stm: &mut self.stm,
+            segments: &file.segments,
+            index: 0,
+            pos: 0,
+        }))
+    }
 
-        let stm = self.stm.by_ref().take(file.size);
-        Ok(Box::new(stm))
+    fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("version"), self.version.name().to_string());
+        attrs
+    }
+}
+
+/// Reads file segments one after another: prefix bytes from the index, then data from the archive.
+struct SegmentsReader<'a> {
+    stm: &'a mut BufReader<fs::File>,
+    segments: &'a [Segment],
+    /// Current segment.
+    index: usize,
+    /// Position within the current segment, including the prefix.
+    pos: u64,
+}
+
+impl<'a> Read for SegmentsReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(segment) = self.segments.get(self.index) {
+            let prefix_len = segment.prefix.len() as u64;
+
+            if self.pos < prefix_len {
+                let mut prefix = &segment.prefix[self.pos as usize..];
+                let len = prefix.read(buf)?;
+                self.pos += len as u64;
+                return Ok(len);
+            }
+
+            let data_pos = self.pos - prefix_len;
+
+            if data_pos < segment.size {
+                if data_pos == 0 {
+                    self.stm.seek(SeekFrom::Start(segment.offset))?;
+                }
+
+                let max_len = usize::try_from(segment.size - data_pos).unwrap_or(usize::MAX);
+                let len = buf.len().min(max_len);
+                let len = self.stm.read(&mut buf[..len])?;
+
+                if len == 0 && !buf.is_empty() {
+                    return Err(io_error!(
+                        UnexpectedEof,
+                        "file data at offset {} is truncated",
+                        segment.offset
+                    ));
+                }
+
+                self.pos += len as u64;
+                return Ok(len);
+            }
+
+            self.index += 1;
+            self.pos = 0;
+        }
+
+        Ok(0)
     }
 }
 
@@ -78,3 +222,41 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let is_index = path.extension().is_some_and(|ext| ext == "rpi");
+
+    if is_index {
+        return Ok(if path.with_extension("rpa").exists() {
+            crate::Probe::Valid(String::from("version 1.0 index"))
+        } else {
+            crate::Probe::Invalid(String::from("no archive next to the index file"))
+        });
+    }
+
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let line = Header::read_line(&mut BufReader::new(file))?;
+    let signature = line
+        .as_deref()
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap_or_default();
+
+    match Version::from_signature(signature) {
+        Some(Version::Zix12A) => Ok(crate::Probe::Valid(String::from("version zix-12a"))),
+        Some(version) => match Header::parse(line.as_deref().unwrap_or_default(), version) {
+            Ok(hdr) if hdr.index_offset < file_size => {
+                Ok(crate::Probe::Valid(format!("version {}", version.name())))
+            }
+            Ok(hdr) => Ok(crate::Probe::Invalid(format!(
+                "index offset {} is out of bounds",
+                hdr.index_offset
+            ))),
+            Err(err) => Ok(crate::Probe::Invalid(err.to_string())),
+        },
+        None if path.with_extension("rpi").exists() => Ok(crate::Probe::Valid(String::from(
+            "version 1.0, index in '.rpi' file",
+        ))),
+        None => Ok(crate::Probe::Invalid(String::from("invalid signature"))),
+    }
+}
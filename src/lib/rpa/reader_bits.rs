@@ -1,80 +1,163 @@
 use std::{
-    collections::BTreeMap,
     io::{BufRead, Read, Result},
     path::PathBuf,
     str,
 };
 
 use libflate::zlib;
+use serde_pickle::{HashableValue, Value};
 
+use super::common::{Segment, Version, ALT_KEY_MASK};
 use crate::{io_error, PathBufUtils};
 
+/// Longest header line accepted, RPA-1.0 archives start with file data instead.
+const MAX_HEADER_LEN: u64 = 256;
+
 pub struct Header {
-    pub signature: String,
     pub index_offset: u64,
-    pub key: u64,
+    /// `None` for versions without a key or with a key not stored in the header.
+    pub key: Option<u64>,
 }
 
 impl Header {
-    #[inline]
-    pub fn read(r: &mut impl BufRead) -> Result<Self> {
-        let mut header = String::new();
-        r.read_line(&mut header)?;
-
-        let parts: Vec<&str> = header[..header.len() - 1].split(' ').collect();
+    /// Reads the first line of the archive. Returns `None` if it is not a text line.
+    pub fn read_line(r: &mut impl BufRead) -> Result<Option<String>> {
+        let mut line = Vec::new();
+        r.take(MAX_HEADER_LEN).read_until(b'\n', &mut line)?;
 
-        if parts.len() != 3 {
-            return Err(io_error!(InvalidData, "invalid header"));
+        if line.pop() != Some(b'\n') {
+            return Ok(None);
         }
 
-        let signature = parts[0].to_string();
+        Ok(String::from_utf8(line).ok())
+    }
+
+    /// Parses the header line according to the layout of `version`.
+    pub fn parse(line: &str, version: Version) -> Result<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
 
-        let Ok(index_offset) = u64::from_str_radix(parts[1], 16) else {
-            return Err(io_error!(InvalidData, "invalid index offset"))
+        let field = |index: usize, name: &str| -> Result<u64> {
+            let Some(part) = parts.get(index) else {
+                return Err(io_error!(InvalidData, "invalid header, missing {name}"));
+            };
+
+            u64::from_str_radix(part, 16)
+                .map_err(|_| io_error!(InvalidData, "invalid {name} '{part}'"))
         };
 
-        let Ok(key) = u64::from_str_radix(parts[2], 16) else {
-            return Err(io_error!(InvalidData, "invalid key"))
+        // RPA-3.x keys may be split into several parts, which are XORed together
+        let xor_fields = |first: usize| -> Result<u64> {
+            if parts.len() <= first {
+                return Err(io_error!(InvalidData, "invalid header, missing key"));
+            }
+
+            (first..parts.len()).try_fold(0, |key, index| Ok(key ^ field(index, "key")?))
         };
 
-        Ok(Header {
-            signature,
-            index_offset,
-            key,
-        })
+        match version {
+            Version::Rpa1 => Err(io_error!(InvalidData, "RPA-1.0 archives have no header")),
+            Version::Rpa2 | Version::Zix12A => Ok(Self {
+                index_offset: field(1, "index offset")?,
+                key: None,
+            }),
+            Version::Rpa3 => Ok(Self {
+                index_offset: field(1, "index offset")?,
+                key: Some(xor_fields(2)?),
+            }),
+            Version::Rpa3_2 => Ok(Self {
+                index_offset: field(1, "index offset")?,
+                key: Some(xor_fields(3)?),
+            }),
+            Version::Alt1 => Ok(Self {
+                index_offset: field(2, "index offset")?,
+                key: Some(field(1, "key")? ^ ALT_KEY_MASK),
+            }),
+        }
     }
 }
 
 pub struct File {
     pub name: PathBuf,
-    pub size: u64,
-    pub offset: u64,
+    pub segments: Vec<Segment>,
 }
 
-type FileIndex = BTreeMap<String, Vec<(u64, u64, String)>>;
-
-pub fn read_file_index(r: &mut impl BufRead, key: u64) -> Result<Vec<File>> {
-    let mut compressed_index = Vec::new();
-    r.read_to_end(&mut compressed_index)?;
+impl File {
+    pub fn size(&self) -> u64 {
+        self.segments.iter().map(Segment::total_size).sum()
+    }
+}
 
+/// Reads zlib compressed pickled index: `{name: [(offset ^ key, size ^ key[, prefix]), ...]}`.
+/// Older archives, written by Python 2, have byte string names and two element tuples.
+pub fn read_file_index(r: &mut impl Read, key: u64) -> Result<Vec<File>> {
     let mut decompressed_index = Vec::new();
-    let mut decoder = zlib::Decoder::new(&compressed_index[..])?;
+    let mut decoder = zlib::Decoder::new(r)?;
     decoder.read_to_end(&mut decompressed_index)?;
-    let deserialized: FileIndex =
-        serde_pickle::from_slice(&decompressed_index, serde_pickle::DeOptions::default()).unwrap();
-
-    let files = deserialized
-        .iter()
-        .map(|(name, entries)| {
-            let (offset, size, _) = entries[0];
-
-            File {
-                name: PathBuf::from_unix(name),
-                offset: offset ^ key,
-                size: size ^ key,
-            }
-        })
-        .collect();
+
+    // Python 3 pickles empty byte strings as `bytes()` call with protocol 2, it's replaced with `None`
+    let options = serde_pickle::DeOptions::new().replace_unresolved_globals();
+    let index = serde_pickle::value_from_slice(&decompressed_index, options)
+        .map_err(|err| io_error!(InvalidData, "failed to unpickle file index: {err}"))?;
+
+    let Value::Dict(index) = index else {
+        return Err(io_error!(InvalidData, "file index is not a dictionary"));
+    };
+
+    let mut files = Vec::with_capacity(index.len());
+
+    for (name, entries) in index {
+        let name = match name {
+            HashableValue::String(name) => name,
+            HashableValue::Bytes(name) => String::from_utf8(name)
+                .map_err(|err| io_error!(InvalidData, "invalid file name: {err}"))?,
+            _ => return Err(io_error!(InvalidData, "file name is not a string")),
+        };
+
+        let (Value::List(entries) | Value::Tuple(entries)) = entries else {
+            return Err(io_error!(InvalidData, "invalid index entry for '{name}'"));
+        };
+
+        let segments = entries
+            .into_iter()
+            .map(|entry| read_segment(entry, key))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| io_error!(InvalidData, "invalid index entry for '{name}'"))?;
+
+        files.push(File {
+            name: PathBuf::from_unix(&name),
+            segments,
+        });
+    }
 
     Ok(files)
 }
+
+/// Reads `(offset ^ key, size ^ key)` or `(offset ^ key, size ^ key, prefix)` tuple.
+fn read_segment(entry: Value, key: u64) -> Option<Segment> {
+    let (Value::List(fields) | Value::Tuple(fields)) = entry else {
+        return None;
+    };
+
+    let (offset, size, prefix) = match &fields[..] {
+        [Value::I64(offset), Value::I64(size)]
+        | [Value::I64(offset), Value::I64(size), Value::None] => (offset, size, Vec::new()),
+        [Value::I64(offset), Value::I64(size), Value::Bytes(prefix)] => {
+            (offset, size, prefix.clone())
+        }
+        // unpickled Python 2 strings, every character is a byte
+        [Value::I64(offset), Value::I64(size), Value::String(prefix)] => {
+            let prefix = prefix
+                .chars()
+                .map(|ch| u8::try_from(u32::from(ch)).ok())
+                .collect::<Option<Vec<_>>>()?;
+            (offset, size, prefix)
+        }
+        _ => return None,
+    };
+
+    Some(Segment {
+        offset: *offset as u64 ^ key,
+        size: *size as u64 ^ key,
+        prefix,
+    })
+}
@@ -1,14 +1,112 @@
-#[cfg(test)]
-use std::path::Path;
+use std::{collections::HashMap, io::Read, path::Path};
+
+use rstest::rstest;
+
+use crate::reader::Options;
 
 #[test]
 fn new_reader() {
     let res = super::make_reader(
         Path::new("./samples/rpa/correct.rpa"),
-        crate::reader::Options { strict: false },
+        crate::reader::Options {
+            strict: false,
+            ..Default::default()
+        },
     );
 
     let rdr = res.ok().unwrap();
 
     assert_eq!(rdr.file_count(), 6);
 }
+
+fn options(params: &[(&str, &str)]) -> Options {
+    Options {
+        strict: true,
+        params: params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+fn read_files(rdr: &mut dyn crate::reader::Reader) -> HashMap<String, Vec<u8>> {
+    let mut files = HashMap::new();
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(file.size, Some(data.len() as u64));
+        files.insert(file.name.to_str().unwrap().replace('\\', "/"), data);
+    }
+
+    files
+}
+
+#[rstest]
+#[case("v1.rpa", &[], "1.0")]
+#[case("v1.rpi", &[], "1.0")]
+#[case("v2.rpa", &[], "2.0")]
+#[case("v3_2.rpa", &[], "3.2")]
+#[case("alt1.rpa", &[], "alt-1.0")]
+#[case("zix.rpa", &[("key", "12345678")], "zix-12a")]
+#[case("v3_2.rpa", &[("version", "zix-12a"), ("key", "0x12345678")], "zix-12a")]
+#[case("v3_2.rpa", &[("version", "3.0"), ("key", "12345678")], "3.0")]
+fn variants(#[case] name: &str, #[case] params: &[(&str, &str)], #[case] version: &str) {
+    let path = Path::new("./samples/rpa").join(name);
+    let mut rdr = super::make_reader(&path, options(params)).unwrap();
+
+    assert_eq!(rdr.attrs().get("version").unwrap(), version);
+
+    let files = read_files(rdr.as_mut());
+    assert_eq!(files.len(), 3);
+    assert_eq!(files["file001.txt"], b"Hello, Ren'Py!\n");
+    assert_eq!(files["dir1/file002.txt"], b"second file\n".repeat(3));
+    assert_eq!(files["empty_file"], b"");
+}
+
+#[test]
+fn zix_without_key() {
+    let res = super::make_reader(Path::new("./samples/rpa/zix.rpa"), options(&[]));
+
+    assert!(matches!(res, Err(crate::reader::Error::Unsupported(_))));
+}
+
+#[rstest]
+#[case(&[("version", "4.0")])]
+#[case(&[("key", "xyz")])]
+fn invalid_options(#[case] params: &[(&str, &str)]) {
+    let res = super::make_reader(Path::new("./samples/rpa/v2.rpa"), options(params));
+
+    assert!(matches!(
+        res,
+        Err(crate::reader::Error::InvalidParameter(_, _))
+    ));
+}
+
+#[test]
+fn unknown_signature() {
+    let res = super::make_reader(Path::new("./samples/pak/correct.pak"), options(&[]));
+
+    assert!(matches!(
+        res,
+        Err(crate::reader::Error::InvalidStringSignature { .. })
+    ));
+}
+
+#[test]
+fn prefixes_and_segments() {
+    let path = Path::new("./samples/rpa/prefixes.rpa");
+    let mut rdr = super::make_reader(path, options(&[])).unwrap();
+
+    let files = read_files(rdr.as_mut());
+    assert_eq!(files.len(), 5);
+    assert_eq!(files["file001.txt"], b"Hello, Ren'Py!\n");
+    assert_eq!(files["dir1/file002.txt"], b"second file\n".repeat(3));
+    assert_eq!(files["multi.txt"], b">> multi segment file\n");
+    assert_eq!(files["empty_file"], b"");
+    assert_eq!(files["prefix_only"], b"\xe9\x00\xff");
+}
@@ -1,60 +1,83 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
-    io::{self, Seek, Write},
+    io::{self, Read, Seek, Write},
     path::Path,
 };
 
-use super::common::{FileIndex, DEFAULT_KEY, RENPY_PADDING};
-use crate::{writer, FileType, InputFileList, PathBufUtils};
-use libflate::zlib;
+use rand::Rng;
+use serde_pickle::{HashableValue, Value};
+
+use super::common::{Segment, Version, DEFAULT_KEY, HEADER_V3_2_SIZE, RENPY_PADDING};
+use crate::{
+    parse_size, writer, writer::CompressionLevel, FileType, InputFileList, Overhead, PathBufUtils,
+};
+use flate2::write::ZlibEncoder;
+
+/// Largest number of random bytes written before a file.
+const MAX_RANDOM_PADDING: u8 = 31;
+/// Pickled index entry without its name, after the index is compressed.
+const INDEX_ENTRY_SIZE: u64 = 33;
+
+/// Estimate for `max-size`: index entry and the largest padding of every file.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: HEADER_V3_2_SIZE,
+    entry_size: INDEX_ENTRY_SIZE + MAX_RANDOM_PADDING as u64,
+    name_copies: 1,
+};
+
+/// Data written before every file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Padding {
+    None,
+    /// `Made with Ren'Py.`, as Ren'Py's archiver does.
+    Fixed,
+    /// 1 to [`MAX_RANDOM_PADDING`] random bytes.
+    Random,
+}
+
+struct Options {
+    version: Version,
+    key: u64,
+    padding: Padding,
+    level: CompressionLevel,
+    /// Number of bytes from the beginning of every file stored in the index instead of the archive.
+    prefix_bytes: u64,
+    /// Maximum size of a file segment, files are stored as one segment if not set.
+    segment_size: Option<u64>,
+}
 
 pub fn create_archive(
     input_files: InputFileList,
     path: &Path,
-    _params: &HashMap<String, String>,
+    params: &HashMap<String, String>,
 ) -> writer::Result<()> {
+    let options = parse_options(params)?;
+    let key = options.key;
+
     let mut out = fs::File::create(path).map_err(writer::Error::CreatingOutputFile)?;
-    let mut file_index = FileIndex::new();
+    let mut file_index = BTreeMap::new();
 
     // header placeholder
-    let header = format!("RPA-3.0 {:016x} {DEFAULT_KEY:08x}\n", 0);
+    let header = make_header(options.version, 0, key);
     out.write_all(header.as_bytes())
         .map_err(writer::Error::WritingHeader)?;
 
     // files and making file index
     for input_file in input_files {
         if input_file.file_type == FileType::RegularFile {
-            let metadata = input_file.src_path.metadata().map_err(|err| {
-                writer::Error::ReadingInputFileMetadata(input_file.src_path.clone(), err)
-            })?;
-            let size = metadata.len();
             let path = input_file
                 .dst_path
                 .try_to_unix()
                 .map_err(|err| writer::Error::InvalidInputFileName(input_file.dst_path, err))?;
 
-            out.write_all(RENPY_PADDING).map_err(|err| {
-                writer::Error::ArchivingInputFile(input_file.src_path.clone(), err)
-            })?;
-            let offset = out.stream_position().map_err(|err| {
-                writer::Error::ArchivingInputFile(input_file.src_path.clone(), err)
-            })?;
-
             let mut file = fs::File::open(&input_file.src_path)
                 .map_err(|err| writer::Error::OpeningInputFile(input_file.src_path.clone(), err))?;
-            io::copy(&mut file, &mut out).map_err(|err| {
+            let segments = write_file(&mut out, &mut file, &options).map_err(|err| {
                 writer::Error::ArchivingInputFile(input_file.src_path.clone(), err)
             })?;
 
-            file_index.insert(
-                path,
-                vec![(
-                    offset ^ u64::from(DEFAULT_KEY),
-                    size ^ u64::from(DEFAULT_KEY),
-                    String::new(),
-                )],
-            );
+            file_index.insert(path, segments);
         }
     }
 
@@ -63,24 +86,182 @@ pub fn create_archive(
         .stream_position()
         .map_err(writer::Error::WritingFileIndex)?;
 
-    let mut zlib_encoder = zlib::Encoder::new(&mut out).expect("should create zlib encoder");
-    serde_pickle::to_writer(
+    let mut zlib_encoder = ZlibEncoder::new(&mut out, options.level.to_flate2());
+    serde_pickle::value_to_writer(
         &mut zlib_encoder,
-        &file_index,
-        serde_pickle::SerOptions::new(),
+        &index_to_value(file_index, key),
+        serde_pickle::SerOptions::new().proto_v2(),
     )
     .map_err(|err| writer::Error::WritingFileIndexCustom(err.to_string()))?;
 
     zlib_encoder
         .finish()
-        .into_result()
         .map_err(writer::Error::WritingFileIndex)?;
 
     // write real header
-    let header = format!("RPA-3.0 {file_index_offset:016x} {DEFAULT_KEY:08x}\n");
+    let header = make_header(options.version, file_index_offset, key);
     out.rewind().map_err(writer::Error::WritingHeader)?;
     out.write_all(header.as_bytes())
         .map_err(writer::Error::WritingHeader)?;
 
     Ok(())
 }
+
+fn parse_options(params: &HashMap<String, String>) -> writer::Result<Options> {
+    let version = match params.get("version").map(String::as_str) {
+        None | Some("3.0") => Version::Rpa3,
+        Some("3.2") => Version::Rpa3_2,
+        Some("2.0") => Version::Rpa2,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "version",
+                format!("expected '2.0', '3.0' or '3.2', got '{value}'"),
+            ))
+        }
+    };
+    let key = match (version, params.get("key").map(String::as_str)) {
+        (Version::Rpa2, None) => 0,
+        (Version::Rpa2, Some(_)) => {
+            return Err(writer::Error::InvalidParameter(
+                "key",
+                String::from("version 2.0 has no key"),
+            ))
+        }
+        (_, None) => u64::from(DEFAULT_KEY),
+        (_, Some("random")) => u64::from(rand::thread_rng().gen::<u32>()),
+        (_, Some(value)) => u32::from_str_radix(value.trim_start_matches("0x"), 16)
+            .map(u64::from)
+            .map_err(|_| {
+                writer::Error::InvalidParameter(
+                    "key",
+                    format!("expected 'random' or a 32-bit hexadecimal number, got '{value}'"),
+                )
+            })?,
+    };
+    let padding = match params.get("padding").map(String::as_str) {
+        None | Some("fixed") => Padding::Fixed,
+        Some("none") => Padding::None,
+        Some("random") => Padding::Random,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "padding",
+                format!("expected 'none', 'fixed' or 'random', got '{value}'"),
+            ))
+        }
+    };
+    let level = CompressionLevel::from_params(params)?.unwrap_or(CompressionLevel::Default);
+    let prefix_bytes = match params.get("prefix-bytes") {
+        None => 0,
+        Some(value) => value.parse().map_err(|_| {
+            writer::Error::InvalidParameter(
+                "prefix-bytes",
+                format!("expected a number, got '{value}'"),
+            )
+        })?,
+    };
+    let segment_size = parse_size(params, "segment-size")?;
+
+    if segment_size == Some(0) {
+        return Err(writer::Error::InvalidParameter(
+            "segment-size",
+            String::from("should be greater than 0"),
+        ));
+    }
+
+    Ok(Options {
+        version,
+        key,
+        padding,
+        level,
+        prefix_bytes,
+        segment_size,
+    })
+}
+
+/// Writes file data, returns its segments.
+fn write_file(
+    out: &mut fs::File,
+    file: &mut fs::File,
+    options: &Options,
+) -> io::Result<Vec<Segment>> {
+    let mut prefix = Vec::new();
+    file.take(options.prefix_bytes).read_to_end(&mut prefix)?;
+
+    match options.padding {
+        Padding::None => {}
+        Padding::Fixed => out.write_all(RENPY_PADDING)?,
+        Padding::Random => {
+            let mut rng = rand::thread_rng();
+            let padding: Vec<u8> = (0..rng.gen_range(1..=MAX_RANDOM_PADDING))
+                .map(|_| rng.gen())
+                .collect();
+            out.write_all(&padding)?;
+        }
+    }
+
+    let mut segments = Vec::new();
+
+    loop {
+        let offset = out.stream_position()?;
+        let segment_size = options.segment_size.unwrap_or(u64::MAX);
+        let size = io::copy(&mut file.take(segment_size), out)?;
+
+        // the rest of the data is in the following segments
+        if size == 0 && !segments.is_empty() {
+            break;
+        }
+
+        segments.push(Segment {
+            offset,
+            size,
+            prefix: if segments.is_empty() {
+                std::mem::take(&mut prefix)
+            } else {
+                Vec::new()
+            },
+        });
+
+        if size < segment_size {
+            break;
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Makes `{name: [(offset ^ key, size ^ key, prefix), (offset ^ key, size ^ key), ...]}` index.
+/// Only the first segment has a prefix.
+fn index_to_value(file_index: BTreeMap<String, Vec<Segment>>, key: u64) -> Value {
+    let index = file_index
+        .into_iter()
+        .map(|(name, segments)| {
+            let segments = segments
+                .into_iter()
+                .enumerate()
+                .map(|(index, segment)| {
+                    let mut fields = vec![
+                        Value::I64((segment.offset ^ key) as i64),
+                        Value::I64((segment.size ^ key) as i64),
+                    ];
+                    if index == 0 {
+                        fields.push(Value::Bytes(segment.prefix));
+                    }
+                    Value::Tuple(fields)
+                })
+                .collect();
+
+            (HashableValue::String(name), Value::List(segments))
+        })
+        .collect();
+
+    Value::Dict(index)
+}
+
+fn make_header(version: Version, index_offset: u64, key: u64) -> String {
+    match version {
+        Version::Rpa2 => format!("RPA-2.0 {index_offset:016x}\n"),
+        // the field between the offset and the key is not used
+        Version::Rpa3_2 => format!("RPA-3.2 {index_offset:016x} {:08x} {key:08x}\n", 0),
+        _ => format!("RPA-3.0 {index_offset:016x} {key:08x}\n"),
+    }
+}
@@ -0,0 +1,213 @@
+use hex_literal::hex;
+use rstest::rstest;
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+use tempdir::TempDir;
+
+use crate::InputFileListBuilder;
+
+#[rstest]
+#[case(None, "3.0", hex!("31da7c59025edcf65505f09279289a60cba31af8"))]
+#[case(Some("3.0"), "3.0", hex!("31da7c59025edcf65505f09279289a60cba31af8"))]
+#[case(Some("3.2"), "3.2", hex!("931855ba0d2e9d9379496544abb7c03e4a5d2fdc"))]
+#[case(Some("2.0"), "2.0", hex!("c24e513d060d090d826114badcb1cd1f9e10d9ce"))]
+fn versions(#[case] param: Option<&str>, #[case] version: &str, #[case] expected_hash: [u8; 20]) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.rpa");
+    let mut params = HashMap::new();
+
+    if let Some(param) = param {
+        params.insert(String::from("version"), param.to_string());
+    }
+
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let mut file = fs::File::open(&output_path).unwrap();
+    let mut hasher = Sha1::new();
+    io::copy(&mut file, &mut hasher).unwrap();
+    let hash = hasher.finalize();
+
+    assert_eq!(hash[..], expected_hash);
+
+    let rdr = super::make_reader(&output_path, Default::default()).unwrap();
+    assert_eq!(rdr.attrs().get("version").unwrap(), version);
+    assert_eq!(rdr.file_count(), 6);
+}
+
+#[rstest]
+#[case("version", "3.1")]
+#[case("key", "xyz")]
+#[case("key", "123456789")]
+#[case("padding", "some")]
+#[case("prefix-bytes", "many")]
+#[case("segment-size", "0")]
+fn invalid_options(#[case] name: &str, #[case] value: &str) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([(name.to_string(), value.to_string())]);
+    let res = super::create_archive(
+        InputFileListBuilder::new().build(),
+        &dir.path().join("archive.rpa"),
+        &params,
+    );
+
+    assert!(matches!(res, Err(crate::writer::Error::InvalidParameter(n, _)) if n == name));
+}
+
+#[test]
+fn key_with_version_2() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([
+        (String::from("version"), String::from("2.0")),
+        (String::from("key"), String::from("12345678")),
+    ]);
+    let res = super::create_archive(
+        InputFileListBuilder::new().build(),
+        &dir.path().join("archive.rpa"),
+        &params,
+    );
+
+    assert!(matches!(
+        res,
+        Err(crate::writer::Error::InvalidParameter("key", _))
+    ));
+}
+
+#[rstest]
+#[case(&[("prefix-bytes", "5")])]
+#[case(&[("key", "deadbeef"), ("padding", "none")])]
+#[case(&[("key", "random"), ("padding", "random")])]
+#[case(&[("version", "3.2"), ("key", "random"), ("padding", "random")])]
+#[case(&[("segment-size", "100")])]
+#[case(&[("prefix-bytes", "1000"), ("segment-size", "1K"), ("version", "2.0")])]
+fn options(#[case] params: &[(&str, &str)]) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.rpa");
+    let params = params
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let mut rdr = super::make_reader(&output_path, Default::default()).unwrap();
+    assert_eq!(rdr.file_count(), 6);
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(Path::new("./samples/unpacked").join(&file.name)).unwrap();
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(file.size, Some(expected.len() as u64));
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
+
+/// Returns the decompressed, pickled file index of an archive.
+fn read_index(path: &Path) -> Vec<u8> {
+    let data = fs::read(path).unwrap();
+    let offset = std::str::from_utf8(&data[8..24]).unwrap();
+    let offset = usize::from_str_radix(offset, 16).unwrap();
+
+    let mut index = Vec::new();
+    flate2::read::ZlibDecoder::new(&data[offset..])
+        .read_to_end(&mut index)
+        .unwrap();
+    index
+}
+
+#[rstest]
+#[case("2.0")]
+#[case("3.0")]
+#[case("3.2")]
+fn index_pickle_protocol(#[case] version: &str) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.rpa");
+    let params = HashMap::from([(String::from("version"), version.to_string())]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    // Ren'Py writes protocol 2, older Python 2 based versions cannot read protocol 3
+    assert_eq!(read_index(&output_path)[..2], *b"\x80\x02");
+}
+
+#[test]
+fn segment_tuples() {
+    use serde_pickle::{HashableValue, Value};
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.rpa");
+    let params = HashMap::from([
+        (String::from("prefix-bytes"), String::from("5")),
+        (String::from("segment-size"), String::from("1K")),
+    ]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let index =
+        serde_pickle::value_from_slice(&read_index(&output_path), Default::default()).unwrap();
+    let Value::Dict(index) = index else {
+        panic!("expected dict, got {index:?}");
+    };
+
+    let Value::List(segments) = &index[&HashableValue::String(String::from("img001.png"))] else {
+        panic!("expected list of segments");
+    };
+    assert!(segments.len() > 1);
+
+    for (i, segment) in segments.iter().enumerate() {
+        match (i, segment) {
+            (0, Value::Tuple(fields)) => {
+                assert!(matches!(
+                    &fields[..],
+                    [Value::I64(_), Value::I64(_), Value::Bytes(prefix)] if prefix.len() == 5
+                ));
+            }
+            (_, Value::Tuple(fields)) => {
+                assert!(
+                    matches!(&fields[..], [Value::I64(_), Value::I64(_)]),
+                    "segment {i}: {fields:?}"
+                );
+            }
+            _ => panic!("segment {i}: expected tuple, got {segment:?}"),
+        }
+    }
+}
+
+#[test]
+fn longest_header() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.rpa");
+    let params = HashMap::from([(String::from("version"), String::from("3.2"))]);
+    super::create_archive(InputFileListBuilder::new().build(), &output_path, &params).unwrap();
+
+    let data = fs::read(&output_path).unwrap();
+    let header_size = data.iter().position(|&byte| byte == b'\n').unwrap() + 1;
+    assert_eq!(header_size as u64, super::common::HEADER_V3_2_SIZE);
+}
@@ -0,0 +1,405 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::{Path, PathBuf},
+};
+
+use crate::{writer, FileType, InputFile, InputFileList};
+
+/// How parts of a split archive are named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartNaming {
+    /// `name.ext`, `name0.ext`, `name1.ext`...
+    Suffix,
+    /// `pak0.pak`, `pak1.pak`, `pak2.pak`... Falls back to [`PartNaming::Suffix`] if the name does not end with a number.
+    Increment,
+    /// `name_dir.ext`, `name0_dir.ext`, `name1_dir.ext`... so that every part gets its own chunk
+    /// files. Falls back to [`PartNaming::Suffix`] if the name does not end with `_dir`.
+    DirSuffix,
+}
+
+impl PartNaming {
+    #[must_use]
+    pub fn part_path(self, path: &Path, index: usize) -> PathBuf {
+        if index == 0 {
+            return path.to_path_buf();
+        }
+
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let name = match self {
+            Self::Increment => match increment_number(&stem, index) {
+                Some(name) => name,
+                None => format!("{stem}{}", index - 1),
+            },
+            Self::DirSuffix => match stem.strip_suffix("_dir") {
+                Some(prefix) => format!("{prefix}{}_dir", index - 1),
+                None => format!("{stem}{}", index - 1),
+            },
+            Self::Suffix => format!("{stem}{}", index - 1),
+        };
+
+        let mut part_path = path.with_file_name(name);
+
+        if let Some(ext) = path.extension() {
+            part_path.set_extension(ext);
+        }
+
+        part_path
+    }
+}
+
+fn increment_number(stem: &str, index: usize) -> Option<String> {
+    let prefix = stem.trim_end_matches(|ch: char| ch.is_ascii_digit());
+    let number: usize = stem[prefix.len()..].parse().ok()?;
+    Some(format!("{prefix}{}", number + index))
+}
+
+/// Estimate of bytes an archive takes besides file data, so parts stay under `max-size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Overhead {
+    /// Header and other structures written once per archive.
+    pub header_size: u64,
+    /// Records written for every file or directory, the largest of the format's variants.
+    pub entry_size: u64,
+    /// How many times a file name is stored, 0 for fixed-size name fields.
+    pub name_copies: u64,
+}
+
+impl Overhead {
+    #[must_use]
+    pub fn entry(&self, path: &Path) -> u64 {
+        self.entry_size + self.name_copies * path.as_os_str().len() as u64
+    }
+}
+
+/// Describes how a format is split into several archives with the `max-size` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitScheme {
+    pub naming: PartNaming,
+    /// Files of the same folder are put into the same part whenever possible.
+    pub keep_folders_together: bool,
+    pub overhead: Overhead,
+}
+
+impl Default for SplitScheme {
+    fn default() -> Self {
+        Self {
+            naming: PartNaming::Suffix,
+            keep_folders_together: false,
+            overhead: Overhead::default(),
+        }
+    }
+}
+
+/// Reads the `max-size` option. The value is a number of bytes with an optional `K`, `M` or `G` suffix.
+pub fn parse_max_size(params: &HashMap<String, String>) -> writer::Result<Option<u64>> {
+    parse_size(params, "max-size")
+}
+
+/// Reads a size option. The value is a number of bytes with an optional `K`, `M` or `G` suffix.
+pub fn parse_size(
+    params: &HashMap<String, String>,
+    name: &'static str,
+) -> writer::Result<Option<u64>> {
+    let Some(value) = params.get(name) else {
+        return Ok(None);
+    };
+
+    let (number, multiplier) = match value.to_ascii_uppercase().chars().last() {
+        Some('K') => (&value[..value.len() - 1], 1 << 10),
+        Some('M') => (&value[..value.len() - 1], 1 << 20),
+        Some('G') => (&value[..value.len() - 1], 1 << 30),
+        _ => (&value[..], 1),
+    };
+
+    match number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+    {
+        Some(size) if size > 0 => Ok(Some(size)),
+        _ => Err(writer::Error::InvalidParameter(
+            name,
+            format!(
+                "expected a positive number of bytes with optional K, M or G suffix, got '{value}'"
+            ),
+        )),
+    }
+}
+
+/// Splits input files into lists, so that each archive made of a list, with file data and the
+/// estimated overhead of the format, takes no more than `max_size` bytes. Compressed data is
+/// assumed not to be larger than input files. Input order is kept, directories are put into the
+/// first list.
+pub fn split_input_files(
+    input_files: InputFileList,
+    max_size: u64,
+    split_scheme: &SplitScheme,
+) -> writer::Result<Vec<InputFileList>> {
+    let overhead = split_scheme.overhead;
+    let mut directories = Vec::new();
+    let mut directories_size = 0;
+    // files of a folder make a single group if folders are kept together
+    let mut groups: Vec<Vec<(u64, InputFile)>> = Vec::new();
+    let mut folder_groups: HashMap<PathBuf, usize> = HashMap::new();
+
+    for input_file in input_files {
+        match input_file.file_type {
+            FileType::Directory => {
+                directories_size += overhead.entry(&input_file.dst_path);
+                directories.push(input_file);
+            }
+            FileType::RegularFile => {
+                let size = input_file
+                    .src_path
+                    .metadata()
+                    .map_err(|err| {
+                        writer::Error::ReadingInputFileMetadata(input_file.src_path.clone(), err)
+                    })?
+                    .len()
+                    + overhead.entry(&input_file.dst_path);
+
+                if overhead.header_size + size > max_size {
+                    return Err(writer::Error::InputFileLargerMaxSize(
+                        input_file.src_path,
+                        max_size,
+                    ));
+                }
+
+                if !split_scheme.keep_folders_together {
+                    groups.push(vec![(size, input_file)]);
+                    continue;
+                }
+
+                let folder = input_file
+                    .dst_path
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+
+                match folder_groups.entry(folder) {
+                    Entry::Occupied(entry) => groups[*entry.get()].push((size, input_file)),
+                    Entry::Vacant(entry) => {
+                        entry.insert(groups.len());
+                        groups.push(vec![(size, input_file)]);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut parts = vec![directories];
+    let mut part_size = overhead.header_size + directories_size;
+    let mut part_files = 0;
+
+    for group in groups {
+        let group_size: u64 = group.iter().map(|(size, _)| size).sum();
+
+        // a folder that does not fit into the current part starts a new one
+        if part_files > 0 && part_size + group_size > max_size {
+            parts.push(Vec::new());
+            part_size = overhead.header_size;
+            part_files = 0;
+        }
+
+        for (size, input_file) in group {
+            // a folder larger than `max_size` is split anyway
+            if part_files > 0 && part_size + size > max_size {
+                parts.push(Vec::new());
+                part_size = overhead.header_size;
+                part_files = 0;
+            }
+
+            part_size += size;
+            part_files += 1;
+            parts
+                .last_mut()
+                .expect("there should be at least one part")
+                .push(input_file);
+        }
+    }
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use super::{parse_max_size, split_input_files, Overhead, PartNaming, SplitScheme};
+    use crate::InputFileListBuilder;
+
+    #[test]
+    fn part_path() {
+        let path = Path::new("data/archive.bsa");
+        assert_eq!(PartNaming::Suffix.part_path(path, 0), path);
+        assert_eq!(
+            PartNaming::Suffix.part_path(path, 1),
+            Path::new("data/archive0.bsa")
+        );
+        assert_eq!(
+            PartNaming::Suffix.part_path(path, 2),
+            Path::new("data/archive1.bsa")
+        );
+
+        let path = Path::new("id1/pak0.pak");
+        assert_eq!(PartNaming::Increment.part_path(path, 0), path);
+        assert_eq!(
+            PartNaming::Increment.part_path(path, 2),
+            Path::new("id1/pak2.pak")
+        );
+
+        let path = Path::new("hl2/pak01_dir.vpk");
+        assert_eq!(PartNaming::DirSuffix.part_path(path, 0), path);
+        assert_eq!(
+            PartNaming::DirSuffix.part_path(path, 1),
+            Path::new("hl2/pak010_dir.vpk")
+        );
+        assert_eq!(
+            PartNaming::DirSuffix.part_path(Path::new("archive.vpk"), 1),
+            Path::new("archive0.vpk")
+        );
+    }
+
+    #[test]
+    fn max_size() {
+        let mut params = HashMap::new();
+        assert_eq!(parse_max_size(&params).unwrap(), None);
+
+        params.insert(String::from("max-size"), String::from("2G"));
+        assert_eq!(parse_max_size(&params).unwrap(), Some(2 << 30));
+
+        params.insert(String::from("max-size"), String::from("100"));
+        assert_eq!(parse_max_size(&params).unwrap(), Some(100));
+
+        params.insert(String::from("max-size"), String::from("big"));
+        assert!(parse_max_size(&params).is_err());
+    }
+
+    #[test]
+    fn split_keeping_folders_together() {
+        let input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .exclude_pattern("empty_dir/.gitkeep")
+            .build();
+
+        // dir1 holds 11590 bytes and comes first, root folder holds 22337 bytes
+        let split_scheme = SplitScheme {
+            keep_folders_together: true,
+            ..Default::default()
+        };
+        let parts = split_input_files(input_files, 32 * 1024, &split_scheme).unwrap();
+
+        let names: Vec<Vec<_>> = parts
+            .iter()
+            .map(|part| part.iter().map(|f| f.dst_path.clone()).collect())
+            .collect();
+
+        assert_eq!(names.len(), 2);
+        assert_eq!(
+            names[0],
+            [
+                Path::new("dir1"),
+                Path::new("empty_dir"),
+                Path::new("dir1/file002.txt"),
+                Path::new("dir1/img002.jpg"),
+            ]
+        );
+        assert_eq!(
+            names[1],
+            [
+                Path::new("empty_file"),
+                Path::new("file001.txt"),
+                Path::new("img001.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn file_larger_than_max_size() {
+        let input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .build();
+
+        assert!(split_input_files(input_files, 1024, &SplitScheme::default()).is_err());
+    }
+
+    #[test]
+    fn split_with_overhead() {
+        let input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .exclude_pattern("empty_dir/.gitkeep")
+            .build();
+
+        // 33927 bytes of data fit into a single part, but not with 4 KiB of overhead
+        let parts = split_input_files(input_files, 34000, &SplitScheme::default()).unwrap();
+        assert_eq!(parts.len(), 1);
+
+        let input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .exclude_pattern("empty_dir/.gitkeep")
+            .build();
+        let split_scheme = SplitScheme {
+            overhead: Overhead {
+                header_size: 4096,
+                entry_size: 0,
+                name_copies: 0,
+            },
+            ..Default::default()
+        };
+        let parts = split_input_files(input_files, 34000, &split_scheme).unwrap();
+
+        assert_eq!(parts.len(), 2);
+    }
+
+    #[test]
+    fn file_with_overhead_larger_than_max_size() {
+        let input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked/dir1"))
+            .unwrap()
+            .build();
+
+        // img002.jpg is 11590 bytes, its name is 10 bytes
+        let split_scheme = SplitScheme {
+            overhead: Overhead {
+                header_size: 0,
+                entry_size: 0,
+                name_copies: 1,
+            },
+            ..Default::default()
+        };
+
+        assert!(split_input_files(input_files, 11600, &split_scheme).is_ok());
+
+        let input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked/dir1"))
+            .unwrap()
+            .build();
+
+        assert!(split_input_files(input_files, 11599, &split_scheme).is_err());
+    }
+
+    #[test]
+    fn split_keeps_input_order() {
+        let mut input_files = InputFileListBuilder::new()
+            .add_dir(Path::new("./samples/unpacked"))
+            .unwrap()
+            .exclude_pattern("empty_dir/.gitkeep")
+            .build();
+        input_files.retain(|f| f.file_type == crate::FileType::RegularFile);
+        input_files.reverse();
+
+        let expected: Vec<_> = input_files.iter().map(|f| f.dst_path.clone()).collect();
+        let parts = split_input_files(input_files, 32 * 1024, &SplitScheme::default()).unwrap();
+        let names: Vec<_> = parts.iter().flatten().map(|f| f.dst_path.clone()).collect();
+
+        assert_eq!(names, expected);
+    }
+}
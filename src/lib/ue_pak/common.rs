@@ -0,0 +1,76 @@
+pub const MAGIC: u32 = 0x5A6F12E1;
+pub const SUPPORTED_VERSIONS: &[u32] = &[3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+/// Compression blocks and file data
+pub const VERSION_COMPRESSION_ENCRYPTION: u32 = 3;
+pub const VERSION_INDEX_ENCRYPTION: u32 = 4;
+/// Compression block offsets are relative to the entry.
+pub const VERSION_RELATIVE_CHUNK_OFFSETS: u32 = 5;
+/// Compression methods are named in the footer, entries refer to them by index.
+pub const VERSION_FNAME_BASED_COMPRESSION_METHOD: u32 = 8;
+pub const VERSION_FROZEN_INDEX: u32 = 9;
+/// Index is split into the primary index with encoded entries, path hash and full directory indices.
+pub const VERSION_PATH_HASH_INDEX: u32 = 10;
+
+/// Encrypted data is padded to AES block size.
+pub const AES_BLOCK_SIZE: u64 = 16;
+pub const COMPRESSION_METHOD_NAME_SIZE: usize = 32;
+
+/// Entry flags
+pub const FLAG_ENCRYPTED: u8 = 0x01;
+pub const FLAG_DELETED: u8 = 0x02;
+
+/// Compression flags of versions before 8
+pub const COMPRESS_ZLIB: u32 = 0x01;
+pub const COMPRESS_GZIP: u32 = 0x02;
+/// Game specific, Oodle most of the time.
+pub const COMPRESS_CUSTOM: u32 = 0x04;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Zlib,
+    Gzip,
+    /// LZ4 block format.
+    Lz4,
+    /// Oodle and game specific methods.
+    Other(String),
+}
+
+impl Compression {
+    pub fn from_flags(flags: u32) -> Self {
+        if flags & COMPRESS_ZLIB != 0 {
+            Self::Zlib
+        } else if flags & COMPRESS_GZIP != 0 {
+            Self::Gzip
+        } else if flags & COMPRESS_CUSTOM != 0 {
+            Self::Other(String::from("custom"))
+        } else {
+            Self::None
+        }
+    }
+
+    /// Compression method named in the footer.
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "zlib" => Self::Zlib,
+            "gzip" => Self::Gzip,
+            "lz4" => Self::Lz4,
+            _ => Self::Other(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::None => "none",
+            Self::Zlib => "zlib",
+            Self::Gzip => "gzip",
+            Self::Lz4 => "lz4",
+            Self::Other(name) => name,
+        }
+    }
+}
+
+pub fn align_to_aes_block(size: u64) -> u64 {
+    size.next_multiple_of(AES_BLOCK_SIZE)
+}
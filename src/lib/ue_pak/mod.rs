@@ -0,0 +1,9 @@
+// https://github.com/trumank/repak
+
+mod common;
+mod reader;
+mod reader_bits;
+#[cfg(test)]
+mod reader_tests;
+
+pub use reader::{make_reader, probe};
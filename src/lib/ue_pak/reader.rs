@@ -0,0 +1,506 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecrypt, KeyInit},
+    Aes256,
+};
+use sha1::{Digest, Sha1};
+
+use super::{
+    common::{
+        align_to_aes_block, Compression, AES_BLOCK_SIZE, SUPPORTED_VERSIONS,
+        VERSION_PATH_HASH_INDEX,
+    },
+    reader_bits::{
+        normalize_mount_point, read_full_directory_index, read_legacy_index, read_path_hash_index,
+        Block, Entry, Footer, PrimaryIndex,
+    },
+};
+use crate::{io_error, FileType, PathBufUtils, ReadEx};
+
+const KEY_SIZE: usize = 32;
+
+struct File {
+    name: PathBuf,
+    entry: Entry,
+}
+
+pub struct Reader {
+    stm: BufReader<fs::File>,
+    footer: Footer,
+    mount_point: String,
+    files: Vec<File>,
+    cipher: Option<Aes256>,
+    /// Set if names are unknown, files are named by hashes of their paths.
+    hashed_names: bool,
+}
+
+impl Reader {
+    fn open(path: &Path, options: crate::reader::Options) -> crate::reader::Result<Self> {
+        let cipher = match options.params.get("key") {
+            Some(key) => Some(parse_key(key)?),
+            None => None,
+        };
+
+        let file = fs::File::open(path).map_err(crate::reader::Error::OpeningInputFile)?;
+        let file_size = file
+            .metadata()
+            .map_err(crate::reader::Error::ReadingInputFileMetadata)?
+            .len();
+        let mut stm = BufReader::new(file);
+
+        let footer =
+            Footer::read(&mut stm, file_size).map_err(crate::reader::Error::ReadingHeader)?;
+
+        if !SUPPORTED_VERSIONS.contains(&footer.version) {
+            return Err(crate::reader::Error::UnsupportedVersion {
+                version: footer.version,
+                supported_versions: SUPPORTED_VERSIONS,
+            });
+        }
+
+        if footer.frozen_index {
+            return Err(crate::reader::Error::Unsupported(String::from(
+                "frozen index is not supported",
+            )));
+        }
+
+        if footer.encrypted_index && cipher.is_none() {
+            return Err(crate::reader::Error::Other(String::from(
+                "index is encrypted, use `key` option to decrypt it",
+            )));
+        }
+
+        let mut index_reader = IndexReader {
+            stm: &mut stm,
+            file_size,
+            cipher: cipher.as_ref().filter(|_| footer.encrypted_index),
+            strict: options.strict,
+        };
+
+        let index = index_reader.read(
+            "index",
+            footer.index_offset,
+            footer.index_size,
+            &footer.index_hash,
+        )?;
+        let mut index = Cursor::new(index);
+
+        let mut hashed_names = false;
+
+        let (mount_point, entries) = if footer.version < VERSION_PATH_HASH_INDEX {
+            read_legacy_index(&mut index, &footer)
+                .map_err(crate::reader::Error::ReadingFileIndex)?
+        } else {
+            let primary_index = PrimaryIndex::read(&mut index, &footer)
+                .map_err(crate::reader::Error::ReadingFileIndex)?;
+
+            // full directory index is optional, path hash index is enough for the game to find files
+            let locations = if let Some(location) = &primary_index.full_directory_index {
+                let index = index_reader.read(
+                    "full directory index",
+                    location.offset,
+                    location.size,
+                    &location.hash,
+                )?;
+                read_full_directory_index(&mut Cursor::new(index))
+            } else if let Some(location) = &primary_index.path_hash_index {
+                hashed_names = true;
+                let index = index_reader.read(
+                    "path hash index",
+                    location.offset,
+                    location.size,
+                    &location.hash,
+                )?;
+                read_path_hash_index(&mut Cursor::new(index)).map(|locations| {
+                    locations
+                        .into_iter()
+                        .map(|(hash, location)| (format!("{hash:016x}"), location))
+                        .collect()
+                })
+            } else {
+                return Err(crate::reader::Error::InvalidHeader(String::from(
+                    "neither path hash nor full directory index is present",
+                )));
+            }
+            .map_err(crate::reader::Error::ReadingFileIndex)?;
+
+            let entries = locations
+                .into_iter()
+                .map(|(name, location)| Ok((name, primary_index.entry(location, &footer)?)))
+                .collect::<io::Result<_>>()
+                .map_err(crate::reader::Error::ReadingFileIndex)?;
+
+            (primary_index.mount_point, entries)
+        };
+
+        let mut prefix = normalize_mount_point(&mount_point).to_string();
+
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+
+        let files = entries
+            .into_iter()
+            .filter(|(_, entry)| !entry.deleted)
+            .map(|(name, entry)| File {
+                name: PathBuf::from_unix(&format!("{prefix}{name}")),
+                entry,
+            })
+            .collect();
+
+        Ok(Self {
+            stm,
+            footer,
+            mount_point,
+            files,
+            cipher,
+            hashed_names,
+        })
+    }
+}
+
+impl crate::reader::Reader for Reader {
+    fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    fn get_file(&self, index: usize) -> crate::reader::File {
+        let file = self
+            .files
+            .get(index)
+            .expect("`index` should be within boundaries");
+
+        crate::reader::File {
+            name: file.name.clone(),
+            file_type: FileType::RegularFile,
+            size: Some(file.entry.uncompressed_size),
+        }
+    }
+
+    fn create_file_reader<'a>(
+        &'a mut self,
+        index: usize,
+    ) -> crate::reader::Result<Box<dyn io::Read + 'a>> {
+        let entry = &self
+            .files
+            .get(index)
+            .expect("`index` should be within boundaries")
+            .entry;
+
+        if let Compression::Other(name) = &entry.compression {
+            return Err(crate::reader::Error::Unsupported(format!(
+                "unsupported compression method '{name}'"
+            )));
+        }
+
+        let cipher = match (entry.encrypted, &self.cipher) {
+            (false, _) => None,
+            (true, Some(cipher)) => Some(cipher),
+            (true, None) => {
+                return Err(crate::reader::Error::Other(String::from(
+                    "file is encrypted, use `key` option to decrypt it",
+                )))
+            }
+        };
+
+        let data_offset = entry.data_offset(&self.footer);
+
+        if entry.compression == Compression::None && cipher.is_none() {
+            self.stm
+                .seek(SeekFrom::Start(data_offset))
+                .map_err(crate::reader::Error::ReadingInputFile)?;
+
+            return Ok(Box::new((&mut self.stm).take(entry.size)));
+        }
+
+        // stored encrypted data is decrypted as a single block
+        let blocks = match entry.compression {
+            Compression::None => vec![Block {
+                start: data_offset,
+                end: data_offset + entry.size,
+            }],
+            _ => entry.blocks.clone(),
+        };
+
+        Ok(Box::new(BlockReader {
+            stm: &mut self.stm,
+            cipher,
+            compression: entry.compression.clone(),
+            blocks: blocks.into_iter(),
+            block_size: u64::from(entry.block_size),
+            remaining: entry.uncompressed_size,
+            buf: Cursor::new(Vec::new()),
+        }))
+    }
+
+    fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+
+        attrs.insert(String::from("version"), self.footer.version.to_string());
+        attrs.insert(String::from("mount point"), self.mount_point.clone());
+        attrs.insert(
+            String::from("encrypted index"),
+            self.footer.encrypted_index.to_string(),
+        );
+
+        if self.hashed_names {
+            attrs.insert(
+                String::from("names"),
+                String::from("path hashes, no full directory index"),
+            );
+        }
+
+        attrs
+    }
+
+    fn file_attrs(&self, index: usize) -> Vec<(&'static str, String)> {
+        let entry = &self
+            .files
+            .get(index)
+            .expect("`index` should be within boundaries")
+            .entry;
+
+        let mut attrs = vec![
+            ("compression", entry.compression.name().to_string()),
+            ("packed", entry.size.to_string()),
+        ];
+
+        if entry.encrypted {
+            attrs.push(("encrypted", String::from("true")));
+        }
+
+        attrs
+    }
+}
+
+/// Reads, decrypts and verifies the index and secondary indices of version 10+.
+struct IndexReader<'a> {
+    stm: &'a mut BufReader<fs::File>,
+    file_size: u64,
+    /// Set if the index is encrypted.
+    cipher: Option<&'a Aes256>,
+    strict: bool,
+}
+
+impl IndexReader<'_> {
+    fn read(
+        &mut self,
+        what: &str,
+        offset: u64,
+        size: u64,
+        hash: &[u8; 20],
+    ) -> crate::reader::Result<Vec<u8>> {
+        if offset
+            .checked_add(size)
+            .map_or(true, |end| end > self.file_size)
+        {
+            return Err(crate::reader::Error::InvalidHeader(format!(
+                "{what} at {offset} of {size} bytes is out of bounds"
+            )));
+        }
+
+        if self.cipher.is_some() && size % AES_BLOCK_SIZE != 0 {
+            return Err(crate::reader::Error::InvalidHeader(format!(
+                "encrypted {what} size {size} is not a multiple of {AES_BLOCK_SIZE}"
+            )));
+        }
+
+        self.stm
+            .seek(SeekFrom::Start(offset))
+            .map_err(crate::reader::Error::ReadingFileIndex)?;
+        let mut data = self
+            .stm
+            .read_u8_vec(size as usize)
+            .map_err(crate::reader::Error::ReadingFileIndex)?;
+
+        if let Some(cipher) = self.cipher {
+            decrypt(cipher, &mut data);
+        }
+
+        if self.strict {
+            let checksum: [u8; 20] = Sha1::digest(&data).into();
+
+            if checksum != *hash {
+                return Err(crate::reader::Error::InvalidChecksum {
+                    what: what.to_string(),
+                    checksum: to_hex(&checksum),
+                    expected_checksum: to_hex(hash),
+                });
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Reads compression blocks one by one, decrypting and decompressing them.
+struct BlockReader<'a> {
+    stm: &'a mut BufReader<fs::File>,
+    cipher: Option<&'a Aes256>,
+    compression: Compression,
+    blocks: std::vec::IntoIter<Block>,
+    /// Uncompressed size of all blocks but the last one.
+    block_size: u64,
+    /// Uncompressed size of the blocks left.
+    remaining: u64,
+    buf: Cursor<Vec<u8>>,
+}
+
+impl BlockReader<'_> {
+    fn read_block(&mut self, block: Block) -> io::Result<Vec<u8>> {
+        let size = block
+            .end
+            .checked_sub(block.start)
+            .ok_or_else(|| io_error!(InvalidData, "invalid compression block"))?;
+        // encrypted blocks are padded
+        let padded_size = match self.cipher {
+            Some(_) => align_to_aes_block(size),
+            None => size,
+        };
+
+        self.stm.seek(SeekFrom::Start(block.start))?;
+        let mut data = self.stm.read_u8_vec(padded_size as usize)?;
+
+        if let Some(cipher) = self.cipher {
+            decrypt(cipher, &mut data);
+            data.truncate(size as usize);
+        }
+
+        let unpacked_size = match self.block_size {
+            0 => self.remaining,
+            block_size => block_size.min(self.remaining),
+        };
+
+        let unpacked = match self.compression {
+            Compression::None => data,
+            Compression::Zlib => {
+                let mut unpacked = Vec::with_capacity(unpacked_size as usize);
+                flate2::read::ZlibDecoder::new(data.as_slice()).read_to_end(&mut unpacked)?;
+                unpacked
+            }
+            Compression::Gzip => {
+                let mut unpacked = Vec::with_capacity(unpacked_size as usize);
+                flate2::read::GzDecoder::new(data.as_slice()).read_to_end(&mut unpacked)?;
+                unpacked
+            }
+            Compression::Lz4 => lz4_flex::block::decompress(&data, unpacked_size as usize)
+                .map_err(|err| io_error!(InvalidData, "{err}"))?,
+            Compression::Other(_) => unreachable!("unsupported compression should be rejected"),
+        };
+
+        if unpacked.len() as u64 != unpacked_size {
+            return Err(io_error!(
+                InvalidData,
+                "compression block size is {}, expected {unpacked_size}",
+                unpacked.len()
+            ));
+        }
+
+        self.remaining -= unpacked_size;
+
+        Ok(unpacked)
+    }
+}
+
+impl Read for BlockReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let size = self.buf.read(buf)?;
+
+            if size > 0 || buf.is_empty() {
+                return Ok(size);
+            }
+
+            let Some(block) = self.blocks.next() else {
+                return Ok(0);
+            };
+
+            self.buf = Cursor::new(self.read_block(block)?);
+        }
+    }
+}
+
+/// AES-256 key as 64 hexadecimal digits, optionally prefixed with `0x`.
+fn parse_key(key: &str) -> crate::reader::Result<Aes256> {
+    let digits = key.trim_start_matches("0x");
+
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|pos| {
+            digits
+                .get(pos..pos + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|bytes| bytes.len() == KEY_SIZE)
+        .ok_or_else(|| {
+            crate::reader::Error::InvalidParameter(
+                "key",
+                format!("expected {} hexadecimal digits, got '{key}'", KEY_SIZE * 2),
+            )
+        })?;
+
+    Ok(Aes256::new(GenericArray::from_slice(&bytes)))
+}
+
+/// Data is encrypted with AES-256 in ECB mode.
+fn decrypt(cipher: &Aes256, data: &mut [u8]) {
+    for block in data.chunks_exact_mut(AES_BLOCK_SIZE as usize) {
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn make_reader(
+    path: &Path,
+    options: crate::reader::Options,
+) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
+    Ok(Box::new(Reader::open(path, options)?))
+}
+
+/// Pak files have no header, the footer is at the end of the file.
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let mut stm = BufReader::new(file);
+
+    let footer = match Footer::read(&mut stm, file_size) {
+        Ok(footer) => footer,
+        Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+            return Ok(crate::Probe::Invalid(String::from("footer not found")))
+        }
+        Err(err) => return Err(err),
+    };
+
+    if !SUPPORTED_VERSIONS.contains(&footer.version) {
+        return Ok(crate::Probe::Invalid(format!(
+            "unsupported version {}",
+            footer.version
+        )));
+    }
+
+    if footer
+        .index_offset
+        .checked_add(footer.index_size)
+        .map_or(true, |end| end > file_size)
+    {
+        return Ok(crate::Probe::Invalid(format!(
+            "index at {} of {} bytes is out of bounds",
+            footer.index_offset, footer.index_size
+        )));
+    }
+
+    Ok(crate::Probe::Valid(format!(
+        "version {} footer, index at {}",
+        footer.version, footer.index_offset
+    )))
+}
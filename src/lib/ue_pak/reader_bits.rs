@@ -0,0 +1,485 @@
+use std::io::{self, BufRead, Cursor, Read, Result, Seek, SeekFrom};
+
+use encoding_rs::WINDOWS_1252;
+
+use super::common::{
+    align_to_aes_block, Compression, COMPRESSION_METHOD_NAME_SIZE, FLAG_DELETED, FLAG_ENCRYPTED,
+    MAGIC, VERSION_COMPRESSION_ENCRYPTION, VERSION_FNAME_BASED_COMPRESSION_METHOD,
+    VERSION_FROZEN_INDEX, VERSION_INDEX_ENCRYPTION, VERSION_RELATIVE_CHUNK_OFFSETS,
+};
+use crate::{io_error, ReadEx};
+
+/// Longest string accepted in the index, protects from allocating memory for garbage.
+const MAX_STRING_LENGTH: usize = 0x10000;
+
+pub struct Footer {
+    pub version: u32,
+    /// Version 8 archives of UE 4.22 have 4 compression methods and 1-byte method indices.
+    pub v8a: bool,
+    pub encrypted_index: bool,
+    pub index_offset: u64,
+    pub index_size: u64,
+    pub index_hash: [u8; 20],
+    pub frozen_index: bool,
+    pub compression_methods: Vec<Compression>,
+}
+
+impl Footer {
+    /// Footer layout depends on the version, which is stored after the magic number. Every layout
+    /// is tried, the magic number is followed by version, index offset, size and hash (40 bytes),
+    /// then by frozen index flag in version 9 and names of compression methods in version 8+.
+    /// Footers of other versions are read with the shortest layout, the caller checks the version.
+    pub fn read(r: &mut (impl Read + Seek), file_size: u64) -> Result<Self> {
+        const LAYOUTS: [(u64, &[u32], bool); 4] = [
+            (40 + 1 + 5 * 32, &[9], false),
+            (40 + 5 * 32, &[8, 10, 11], false),
+            (40 + 4 * 32, &[8], true),
+            (40, &[], false),
+        ];
+
+        for (size_after_magic, versions, v8a) in LAYOUTS {
+            let Some(magic_offset) = file_size.checked_sub(4 + size_after_magic) else {
+                continue;
+            };
+
+            r.seek(SeekFrom::Start(magic_offset))?;
+            let mut r = io::BufReader::new(r.by_ref().take(4 + size_after_magic));
+
+            if r.read_u32_le()? != MAGIC {
+                continue;
+            }
+
+            let version = r.read_u32_le()?;
+
+            if !versions.is_empty() && !versions.contains(&version) {
+                continue;
+            }
+
+            let index_offset = r.read_u64_le()?;
+            let index_size = r.read_u64_le()?;
+            let mut index_hash = [0; 20];
+            r.read_exact(&mut index_hash)?;
+
+            let frozen_index = version == VERSION_FROZEN_INDEX && r.read_u8()? != 0;
+
+            let mut compression_methods = Vec::new();
+
+            if version >= VERSION_FNAME_BASED_COMPRESSION_METHOD {
+                let count = if v8a { 4 } else { 5 };
+
+                for _ in 0..count {
+                    let name = r.read_u8_vec(COMPRESSION_METHOD_NAME_SIZE)?;
+                    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+                    let name = String::from_utf8_lossy(&name[..len]);
+                    compression_methods.push(Compression::from_name(&name));
+                }
+            }
+
+            let encrypted_index = if version >= VERSION_INDEX_ENCRYPTION && magic_offset > 0 {
+                let r = r.into_inner().into_inner();
+                r.seek(SeekFrom::Start(magic_offset - 1))?;
+                let mut flag = [0];
+                r.read_exact(&mut flag)?;
+                flag[0] != 0
+            } else {
+                false
+            };
+
+            return Ok(Self {
+                version,
+                v8a,
+                encrypted_index,
+                index_offset,
+                index_size,
+                index_hash,
+                frozen_index,
+                compression_methods,
+            });
+        }
+
+        Err(io_error!(InvalidData, "footer not found"))
+    }
+
+    /// Compression method by its index in the footer, 0 means no compression.
+    fn compression_method(&self, index: u32) -> Result<Compression> {
+        match index {
+            0 => Ok(Compression::None),
+            _ => self
+                .compression_methods
+                .get(index as usize - 1)
+                .filter(|method| **method != Compression::Other(String::new()))
+                .cloned()
+                .ok_or_else(|| io_error!(InvalidData, "invalid compression method index {index}")),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Block {
+    /// Absolute offset in the archive.
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    /// Offset of the entry header preceding the data.
+    pub offset: u64,
+    /// Size of the stored data.
+    pub size: u64,
+    pub uncompressed_size: u64,
+    pub compression: Compression,
+    pub blocks: Vec<Block>,
+    pub block_size: u32,
+    pub encrypted: bool,
+    pub deleted: bool,
+}
+
+impl Entry {
+    /// Reads an entry serialized the way versions before 10 store it in the index, and all versions
+    /// before the data.
+    pub fn read(r: &mut impl BufRead, footer: &Footer) -> Result<Self> {
+        let offset = r.read_u64_le()?;
+        let size = r.read_u64_le()?;
+        let uncompressed_size = r.read_u64_le()?;
+
+        let compression = if footer.version < VERSION_FNAME_BASED_COMPRESSION_METHOD {
+            Compression::from_flags(r.read_u32_le()?)
+        } else if footer.v8a {
+            footer.compression_method(u32::from(r.read_u8()?))?
+        } else {
+            footer.compression_method(r.read_u32_le()?)?
+        };
+
+        // SHA-1 of the data
+        r.read_u8_vec(20)?;
+
+        let mut entry = Self {
+            offset,
+            size,
+            uncompressed_size,
+            compression,
+            blocks: Vec::new(),
+            block_size: 0,
+            encrypted: false,
+            deleted: false,
+        };
+
+        if footer.version >= VERSION_COMPRESSION_ENCRYPTION {
+            if entry.compression != Compression::None {
+                let block_count = r.read_u32_le()?;
+                // relative offsets are converted into absolute ones
+                let base = match footer.version >= VERSION_RELATIVE_CHUNK_OFFSETS {
+                    true => offset,
+                    false => 0,
+                };
+
+                for _ in 0..block_count {
+                    let start = r.read_u64_le()?;
+                    let end = r.read_u64_le()?;
+                    entry.blocks.push(Block {
+                        start: checked_add(base, start)?,
+                        end: checked_add(base, end)?,
+                    });
+                }
+            }
+
+            let flags = r.read_u8()?;
+            entry.encrypted = flags & FLAG_ENCRYPTED != 0;
+            entry.deleted = flags & FLAG_DELETED != 0;
+            entry.block_size = r.read_u32_le()?;
+        }
+
+        entry.check_data_range(footer)?;
+        Ok(entry)
+    }
+
+    /// Decodes an entry of the compact form versions 10+ use in the primary index.
+    pub fn decode(encoded: &[u8], footer: &Footer) -> Result<Self> {
+        let mut r = Cursor::new(encoded);
+        let value = r.read_u32_le()?;
+
+        let block_size = match value & 0x3F {
+            0x3F => r.read_u32_le()?,
+            bits => bits << 11,
+        };
+        let block_count = (value >> 6) & 0xFFFF;
+        let encrypted = value & (1 << 22) != 0;
+        let compression = footer.compression_method((value >> 23) & 0x3F)?;
+
+        let mut read_size = |is_32_bit: bool| match is_32_bit {
+            true => r.read_u32_le().map(u64::from),
+            false => r.read_u64_le(),
+        };
+
+        let offset = read_size(value & (1 << 31) != 0)?;
+        let uncompressed_size = read_size(value & (1 << 30) != 0)?;
+        let size = match compression {
+            Compression::None => uncompressed_size,
+            _ => read_size(value & (1 << 29) != 0)?,
+        };
+
+        let mut entry = Self {
+            offset,
+            size,
+            uncompressed_size,
+            compression,
+            blocks: Vec::new(),
+            block_size,
+            encrypted,
+            deleted: false,
+        };
+
+        let mut start = checked_add(offset, entry.header_size(footer, block_count))?;
+
+        if block_count == 1 && !encrypted {
+            entry.blocks.push(Block {
+                start,
+                end: checked_add(start, size)?,
+            });
+        } else {
+            for _ in 0..block_count {
+                let size = u64::from(r.read_u32_le()?);
+                entry.blocks.push(Block {
+                    start,
+                    end: checked_add(start, size)?,
+                });
+                start = checked_add(
+                    start,
+                    match encrypted {
+                        true => align_to_aes_block(size),
+                        false => size,
+                    },
+                )?;
+            }
+        }
+
+        entry.check_data_range(footer)?;
+        Ok(entry)
+    }
+
+    /// Size of the entry header preceding the data.
+    pub fn header_size(&self, footer: &Footer, block_count: u32) -> u64 {
+        // offset, sizes and SHA-1
+        let mut size = 8 + 8 + 8 + 20;
+
+        size += match footer.v8a {
+            true => 1,
+            false => 4,
+        };
+
+        if footer.version >= VERSION_COMPRESSION_ENCRYPTION {
+            // flags and block size
+            size += 1 + 4;
+
+            if self.compression != Compression::None {
+                size += 4 + 16 * u64::from(block_count);
+            }
+        }
+
+        size
+    }
+
+    pub fn data_offset(&self, footer: &Footer) -> u64 {
+        self.offset + self.header_size(footer, self.blocks.len() as u32)
+    }
+
+    /// Makes sure [`Self::data_offset`] and the end of the data fit in `u64`.
+    fn check_data_range(&self, footer: &Footer) -> Result<()> {
+        let header_size = self.header_size(footer, self.blocks.len() as u32);
+        checked_add(checked_add(self.offset, header_size)?, self.size)?;
+        Ok(())
+    }
+}
+
+/// Adds file-controlled offsets and sizes, garbage values must not overflow.
+fn checked_add(offset: u64, size: u64) -> Result<u64> {
+    offset
+        .checked_add(size)
+        .ok_or_else(|| io_error!(InvalidData, "offset {offset} + {size} is out of range"))
+}
+
+/// Entries of the primary index of versions before 10, names are relative to the mount point.
+pub fn read_legacy_index(
+    r: &mut impl BufRead,
+    footer: &Footer,
+) -> Result<(String, Vec<(String, Entry)>)> {
+    let mount_point = read_fstring(r)?;
+    let count = r.read_u32_le()?;
+    let mut entries = Vec::new();
+
+    for _ in 0..count {
+        let name = read_fstring(r)?;
+        let entry = Entry::read(r, footer)?;
+        entries.push((name, entry));
+    }
+
+    Ok((mount_point, entries))
+}
+
+/// Location of a secondary index of versions 10+.
+pub struct IndexLocation {
+    pub offset: u64,
+    pub size: u64,
+    pub hash: [u8; 20],
+}
+
+impl IndexLocation {
+    /// Location preceded by a flag whether the index exists.
+    fn read(r: &mut impl BufRead) -> Result<Option<Self>> {
+        if r.read_u32_le()? == 0 {
+            return Ok(None);
+        }
+
+        let offset = r.read_u64_le()?;
+        let size = r.read_u64_le()?;
+        let mut hash = [0; 20];
+        r.read_exact(&mut hash)?;
+
+        Ok(Some(Self { offset, size, hash }))
+    }
+}
+
+pub struct PrimaryIndex {
+    pub mount_point: String,
+    pub path_hash_index: Option<IndexLocation>,
+    pub full_directory_index: Option<IndexLocation>,
+    pub encoded_entries: Vec<u8>,
+    /// Entries that cannot be encoded.
+    pub files: Vec<Entry>,
+}
+
+impl PrimaryIndex {
+    pub fn read(r: &mut impl BufRead, footer: &Footer) -> Result<Self> {
+        let mount_point = read_fstring(r)?;
+        let _entry_count = r.read_u32_le()?;
+        let _path_hash_seed = r.read_u64_le()?;
+
+        let path_hash_index = IndexLocation::read(r)?;
+        let full_directory_index = IndexLocation::read(r)?;
+
+        let encoded_entries_size = r.read_u32_le()? as usize;
+        let encoded_entries = r.read_u8_vec(encoded_entries_size)?;
+
+        let file_count = r.read_u32_le()?;
+        let mut files = Vec::new();
+
+        for _ in 0..file_count {
+            files.push(Entry::read(r, footer)?);
+        }
+
+        Ok(Self {
+            mount_point,
+            path_hash_index,
+            full_directory_index,
+            encoded_entries,
+            files,
+        })
+    }
+
+    /// Entry by its location in the path hash or full directory index: non-negative locations are
+    /// offsets of encoded entries, negative ones are indices of entries that cannot be encoded.
+    pub fn entry(&self, location: i32, footer: &Footer) -> Result<Entry> {
+        match usize::try_from(location) {
+            Ok(offset) => Entry::decode(
+                self.encoded_entries
+                    .get(offset..)
+                    .ok_or_else(|| io_error!(InvalidData, "invalid entry location {location}"))?,
+                footer,
+            ),
+            Err(_) => {
+                let index = usize::try_from(-(i64::from(location) + 1))
+                    .expect("negative location should be a valid index");
+                self.files
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| io_error!(InvalidData, "invalid entry location {location}"))
+            }
+        }
+    }
+}
+
+/// Names relative to the mount point and entry locations.
+pub fn read_full_directory_index(r: &mut impl BufRead) -> Result<Vec<(String, i32)>> {
+    let dir_count = r.read_u32_le()?;
+    let mut files = Vec::new();
+
+    for _ in 0..dir_count {
+        let dir = read_fstring(r)?;
+        // root directory is "/"
+        let dir = dir.trim_start_matches('/');
+        let file_count = r.read_u32_le()?;
+
+        for _ in 0..file_count {
+            let name = read_fstring(r)?;
+            let location = r.read_u32_le()? as i32;
+            files.push((format!("{dir}{name}"), location));
+        }
+    }
+
+    Ok(files)
+}
+
+/// FNV-64 hashes of names and entry locations.
+pub fn read_path_hash_index(r: &mut impl BufRead) -> Result<Vec<(u64, i32)>> {
+    let count = r.read_u32_le()?;
+    let mut files = Vec::new();
+
+    for _ in 0..count {
+        let hash = r.read_u64_le()?;
+        let location = r.read_u32_le()? as i32;
+        files.push((hash, location));
+    }
+
+    Ok(files)
+}
+
+/// `FString`: length including the terminating null, negative for UTF-16 strings.
+pub fn read_fstring(r: &mut impl BufRead) -> Result<String> {
+    let len = r.read_u32_le()? as i32;
+
+    if len.unsigned_abs() as usize > MAX_STRING_LENGTH {
+        return Err(io_error!(InvalidData, "string is too long ({len})"));
+    }
+
+    let s = if len >= 0 {
+        let buf = r.read_u8_vec(len as usize)?;
+        WINDOWS_1252
+            .decode_without_bom_handling(&buf)
+            .0
+            .into_owned()
+    } else {
+        let buf = r.read_u8_vec(len.unsigned_abs() as usize * 2)?;
+        let chars: Vec<u16> = buf
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16(&chars).map_err(|err| io_error!(InvalidData, "{err}"))?
+    };
+
+    Ok(s.trim_end_matches('\0').to_string())
+}
+
+/// Mount points are usually relative to the engine binaries, like `../../../Game/`.
+pub fn normalize_mount_point(mount_point: &str) -> &str {
+    let mut mount_point = mount_point;
+
+    while let Some(rest) = mount_point.strip_prefix("../") {
+        mount_point = rest;
+    }
+
+    mount_point.trim_start_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn normalize_mount_point() {
+        assert_eq!(super::normalize_mount_point("../../../Game/"), "Game/");
+        assert_eq!(
+            super::normalize_mount_point("/Game/Content/"),
+            "Game/Content/"
+        );
+        assert_eq!(super::normalize_mount_point("../../../"), "");
+    }
+}
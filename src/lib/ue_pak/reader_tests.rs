@@ -0,0 +1,221 @@
+use std::path::Path;
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+fn make_reader(
+    name: &str,
+    params: &[(&str, &str)],
+) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
+    let options = crate::reader::Options {
+        strict: true,
+        params: params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    };
+
+    super::make_reader(&Path::new("./samples/ue_pak").join(name), options)
+}
+
+fn read_files(name: &str, params: &[(&str, &str)]) -> Vec<(String, Vec<u8>)> {
+    use std::io::Read;
+
+    let mut rdr = make_reader(name, params).unwrap();
+    let mut files = Vec::new();
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(file.size, Some(data.len() as u64));
+        files.push((file.name.to_str().unwrap().replace('\\', "/"), data));
+    }
+
+    files
+}
+
+fn expected_files() -> Vec<(String, Vec<u8>)> {
+    let mut big: Vec<u8> = (0..150000u32).map(|i| (i * 7 % 251) as u8).collect();
+    big.extend(b"tail".repeat(1000));
+
+    vec![
+        (
+            String::from("MyGame/Content/hello.txt"),
+            b"Hello from Unreal!\n".to_vec(),
+        ),
+        (String::from("MyGame/Content/Maps/big.bin"), big),
+        (String::from("MyGame/Config/empty.ini"), Vec::new()),
+    ]
+}
+
+#[rstest::rstest]
+#[case("v3.pak", &[], "3")]
+#[case("v5_gzip.pak", &[], "5")]
+#[case("v7_encrypted.pak", &[("key", KEY)], "7")]
+#[case("v8a.pak", &[], "8")]
+#[case("v8b.pak", &[], "8")]
+#[case("v9.pak", &[], "9")]
+#[case("v11.pak", &[], "11")]
+#[case("v11_encrypted.pak", &[("key", "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")], "11")]
+fn versions(#[case] name: &str, #[case] params: &[(&str, &str)], #[case] version: &str) {
+    assert_eq!(read_files(name, params), expected_files());
+    assert_eq!(
+        make_reader(name, params).unwrap().attrs()["version"],
+        version
+    );
+}
+
+/// Seed of path hashes in `v11_path_hash.pak`, see `generate.py`.
+const PATH_HASH_SEED: u64 = 0x1234;
+
+/// FNV-64 of lower case UTF-16 path relative to the mount point, as UnrealPak hashes paths.
+fn path_hash(path: &str, seed: u64) -> u64 {
+    path.to_lowercase()
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .fold(0xcbf2_9ce4_8422_2325u64.wrapping_add(seed), |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+#[test]
+fn path_hash_index() {
+    let names: Vec<_> = read_files("v11_path_hash.pak", &[])
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "MyGame/28790a87c5e4068b",
+            "MyGame/919cdf56b0f8d990",
+            "MyGame/4f46ebd3a18b4249"
+        ]
+    );
+
+    let hashed: Vec<_> = [
+        "Content/hello.txt",
+        "Content/Maps/big.bin",
+        "Config/empty.ini",
+    ]
+    .iter()
+    .map(|path| format!("MyGame/{:016x}", path_hash(path, PATH_HASH_SEED)))
+    .collect();
+    assert_eq!(names, hashed);
+}
+
+#[rstest::rstest]
+#[case("v7_encrypted.pak")]
+#[case("v11_encrypted.pak")]
+fn encrypted_index_without_key(#[case] name: &str) {
+    let err = make_reader(name, &[]).err().unwrap();
+
+    assert!(matches!(err, crate::reader::Error::Other(msg) if msg.contains("`key`")));
+}
+
+#[test]
+fn invalid_key() {
+    let err = make_reader("v7_encrypted.pak", &[("key", &KEY.replace("00", "ff"))])
+        .err()
+        .unwrap();
+
+    assert!(matches!(
+        err,
+        crate::reader::Error::InvalidChecksum { what, .. } if what == "index"
+    ));
+
+    let err = make_reader("v7_encrypted.pak", &[("key", "0123")])
+        .err()
+        .unwrap();
+
+    assert!(matches!(
+        err,
+        crate::reader::Error::InvalidParameter("key", _)
+    ));
+}
+
+#[rstest::rstest]
+// offset + entry header
+#[case(u64::MAX - 10, 0, 0, false)]
+// data end
+#[case(u64::MAX - 100, 0, 0, false)]
+// end of the first block
+#[case(u64::MAX - 100, 2, 50, false)]
+// start of the second block, aligned to AES block size
+#[case(u64::MAX - 100, 2, 1, true)]
+fn overflowing_entry(
+    #[case] offset: u64,
+    #[case] block_count: u32,
+    #[case] block_size: u32,
+    #[case] encrypted: bool,
+) {
+    use super::reader_bits::{Entry, Footer};
+
+    let footer = Footer {
+        version: 11,
+        v8a: false,
+        encrypted_index: false,
+        index_offset: 0,
+        index_size: 0,
+        index_hash: [0; 20],
+        frozen_index: false,
+        compression_methods: vec![super::common::Compression::Zlib],
+    };
+
+    // 64-bit offset, 32-bit sizes, zlib compression when there are blocks
+    let compression = u32::from(block_count != 0);
+    let value = (1 << 30)
+        | (1 << 29)
+        | (compression << 23)
+        | (u32::from(encrypted) << 22)
+        | (block_count << 6);
+    let mut encoded = value.to_le_bytes().to_vec();
+    encoded.extend(offset.to_le_bytes());
+    encoded.extend(60u32.to_le_bytes());
+
+    if block_count != 0 {
+        encoded.extend(60u32.to_le_bytes());
+    }
+
+    for _ in 0..block_count {
+        encoded.extend(block_size.to_le_bytes());
+    }
+
+    let err = Entry::decode(&encoded, &footer).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn oodle() {
+    let mut rdr = make_reader("v7_oodle.pak", &[]).unwrap();
+
+    assert_eq!(rdr.file_count(), 3);
+    assert!(matches!(
+        rdr.create_file_reader(0).err().unwrap(),
+        crate::reader::Error::Unsupported(_)
+    ));
+    // stored files are still readable
+    assert!(rdr.create_file_reader(2).is_ok());
+}
+
+#[rstest::rstest]
+#[case("v3.pak")]
+#[case("v8a.pak")]
+#[case("v9.pak")]
+#[case("v11.pak")]
+fn probe(#[case] name: &str) {
+    let res = super::probe(&Path::new("./samples/ue_pak").join(name)).unwrap();
+
+    assert!(matches!(res, crate::Probe::Valid(_)));
+}
+
+#[test]
+fn probe_quake_pak() {
+    let res = super::probe(Path::new("./samples/pak/correct.pak")).unwrap();
+
+    assert!(matches!(res, crate::Probe::Invalid(_)));
+}
@@ -14,7 +14,10 @@ pub fn buffer_to_zstring<'a>(
     encoding: &'static Encoding,
 ) -> io::Result<Cow<'a, str>> {
     let Some(null_byte_position) = buf.iter().position(|&x| x == 0) else {
-        return Err(io_error!(UnexpectedEof, "should be a null-terminated string"));
+        return Err(io_error!(
+            UnexpectedEof,
+            "should be a null-terminated string"
+        ));
     };
 
     let (cow, _, had_error) = encoding.decode(&buf[..null_byte_position]);
@@ -0,0 +1,142 @@
+use std::{
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+const DIR_SUFFIX: &str = "_dir.vpk";
+/// Chunk files kept open at the same time. Large games have hundreds of chunks.
+const MAX_OPEN_CHUNKS: usize = 16;
+
+/// Returns `name` for `name_dir.vpk`, `None` for archives that are not split into chunks.
+pub fn chunk_prefix(dir_path: &Path) -> Option<&str> {
+    dir_path
+        .file_name()?
+        .to_str()?
+        .strip_suffix(DIR_SUFFIX)
+        .filter(|prefix| !prefix.is_empty())
+}
+
+/// Returns `name_NNN.vpk` path for `name_dir.vpk`.
+pub fn chunk_path(dir_path: &Path, index: u16) -> Option<PathBuf> {
+    let prefix = chunk_prefix(dir_path)?;
+    Some(dir_path.with_file_name(format!("{prefix}_{index:03}.vpk")))
+}
+
+/// Returns `name_dir.vpk` path if `path` points to a chunk file `name_NNN.vpk`.
+pub fn dir_path_of_chunk(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_name()?.to_str()?.strip_suffix(".vpk")?;
+    let (prefix, index) = stem.rsplit_once('_')?;
+
+    if prefix.is_empty() || index.len() < 3 || !index.bytes().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(path.with_file_name(format!("{prefix}{DIR_SUFFIX}")))
+}
+
+/// Opens chunk files on demand, keeping a limited number of them open.
+pub struct ChunkFiles {
+    dir_path: PathBuf,
+    /// Open files, the most recently used one is the last.
+    open_files: Vec<(u16, BufReader<fs::File>)>,
+}
+
+impl ChunkFiles {
+    pub fn new(dir_path: &Path) -> Self {
+        Self {
+            dir_path: dir_path.to_path_buf(),
+            open_files: Vec::new(),
+        }
+    }
+
+    pub fn get(&mut self, index: u16) -> crate::reader::Result<&mut BufReader<fs::File>> {
+        match self.open_files.iter().position(|(i, _)| *i == index) {
+            Some(pos) => {
+                let file = self.open_files.remove(pos);
+                self.open_files.push(file);
+            }
+            None => {
+                let path = chunk_path(&self.dir_path, index).ok_or_else(|| {
+                    crate::reader::Error::Other(format!(
+                        "file data is in chunk {index:03}, but archive name does not end with '{DIR_SUFFIX}'"
+                    ))
+                })?;
+
+                let file = fs::File::open(&path)
+                    .map_err(|err| crate::reader::Error::OpeningChunkFile(path, err))?;
+
+                if self.open_files.len() >= MAX_OPEN_CHUNKS {
+                    self.open_files.remove(0);
+                }
+
+                self.open_files.push((index, BufReader::new(file)));
+            }
+        }
+
+        let (_, file) = self
+            .open_files
+            .last_mut()
+            .expect("chunk file should be open");
+        Ok(file)
+    }
+
+    #[cfg(test)]
+    pub fn open_count(&self) -> usize {
+        self.open_files.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempdir::TempDir;
+
+    use super::{chunk_path, chunk_prefix, dir_path_of_chunk, ChunkFiles, MAX_OPEN_CHUNKS};
+
+    #[test]
+    fn paths() {
+        let dir_path = Path::new("hl2/pak01_dir.vpk");
+        assert_eq!(chunk_prefix(dir_path), Some("pak01"));
+        assert_eq!(
+            chunk_path(dir_path, 7),
+            Some(Path::new("hl2/pak01_007.vpk").to_path_buf())
+        );
+        assert_eq!(chunk_path(Path::new("hl2/single.vpk"), 0), None);
+        assert_eq!(chunk_path(Path::new("hl2/_dir.vpk"), 0), None);
+
+        assert_eq!(
+            dir_path_of_chunk(Path::new("hl2/pak01_007.vpk")),
+            Some(dir_path.to_path_buf())
+        );
+        assert_eq!(dir_path_of_chunk(dir_path), None);
+        assert_eq!(dir_path_of_chunk(Path::new("hl2/pak01_7.vpk")), None);
+    }
+
+    #[test]
+    fn bounded_open_files() {
+        let dir = TempDir::new("flpak-tests").unwrap();
+        let dir_path = dir.path().join("pak01_dir.vpk");
+
+        for index in 0..20 {
+            fs::write(chunk_path(&dir_path, index).unwrap(), b"").unwrap();
+        }
+
+        let mut chunk_files = ChunkFiles::new(&dir_path);
+
+        for index in 0..20 {
+            assert!(chunk_files.get(index).is_ok());
+            assert!(chunk_files.open_count() <= MAX_OPEN_CHUNKS);
+        }
+
+        assert!(chunk_files.get(0).is_ok());
+
+        match chunk_files.get(20) {
+            Err(crate::reader::Error::OpeningChunkFile(path, _)) => {
+                assert_eq!(path, dir.path().join("pak01_020.vpk"));
+            }
+            _ => panic!("expected missing chunk error"),
+        }
+    }
+}
@@ -1 +1,10 @@
 pub const VPK_SIGNATURE: &[u8; 4] = &[0x34, 0x12, 0xaa, 0x55];
+/// Archive index of files stored in the directory file right after the file tree.
+pub const EMBEDDED_ARCHIVE_INDEX: u16 = 0x7fff;
+pub const ENTRY_TERMINATOR: u16 = 0xffff;
+/// CRC, preload size, archive index, offset, length and terminator of a file in the tree.
+pub const DIRECTORY_ENTRY_SIZE: u64 = 18;
+pub const HEADER_V1_SIZE: u64 = 12;
+pub const HEADER_V2_SIZE: u64 = 28;
+pub const ARCHIVE_MD5_ENTRY_SIZE: u32 = 28;
+pub const OTHER_MD5_SECTION_SIZE: u32 = 48;
@@ -1,7 +1,15 @@
 // https://developer.valvesoftware.com/wiki/VPK_File_Format
 
+mod chunks;
 mod common;
 mod reader;
 mod reader_bits;
+#[cfg(test)]
+mod reader_tests;
+mod writer;
 
-pub use reader::make_reader;
+#[cfg(test)]
+mod writer_tests;
+
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
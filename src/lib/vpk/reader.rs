@@ -1,27 +1,49 @@
 use std::collections::HashMap;
 use std::fs;
-use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
-use crate::FileType;
+use md5::{Digest, Md5};
+
+use crate::{io_error, FileType};
 
 use super::{
-    common::VPK_SIGNATURE,
-    reader_bits::{read_file_tree, File, Header},
+    chunks::{dir_path_of_chunk, ChunkFiles},
+    common::{
+        ARCHIVE_MD5_ENTRY_SIZE, EMBEDDED_ARCHIVE_INDEX, OTHER_MD5_SECTION_SIZE, VPK_SIGNATURE,
+    },
+    reader_bits::{
+        read_file_tree, ArchiveMd5Entry, File, Header, OtherMd5Section, SignatureSection,
+    },
 };
 
 const VALID_VERSIONS: [u32; 2] = [1, 2];
 
 pub struct Reader {
     dir_file: BufReader<fs::File>,
-    dat_files: HashMap<u16, BufReader<fs::File>>,
+    chunk_files: ChunkFiles,
     files: Vec<File>,
+    strict: bool,
+    version: u32,
+    header_size: u64,
+    file_tree_size: u64,
     file_data_offset: u64,
+    /// Offset of the archive MD5 section, v2 only.
+    archive_md5_offset: u64,
+    archive_md5_entries: Vec<ArchiveMd5Entry>,
+    other_md5: Option<OtherMd5Section>,
+    signature: Option<SignatureSection>,
 }
 
 impl Reader {
-    fn open(path: &Path, _options: crate::reader::Options) -> crate::reader::Result<Self> {
-        let file = fs::File::open(path).map_err(crate::reader::Error::OpeningInputFile)?;
+    fn open(path: &Path, options: crate::reader::Options) -> crate::reader::Result<Self> {
+        // chunk files have no file tree, the directory file is opened instead
+        let path = match dir_path_of_chunk(path) {
+            Some(dir_path) if dir_path.exists() => dir_path,
+            _ => path.to_path_buf(),
+        };
+
+        let file = fs::File::open(&path).map_err(crate::reader::Error::OpeningInputFile)?;
         let mut stm = BufReader::new(file);
 
         // header
@@ -45,32 +67,93 @@ impl Reader {
             .stream_position()
             .map_err(crate::reader::Error::ReadingInputFile)?;
 
-        let mut dat_files = HashMap::new();
         let files = read_file_tree(&mut stm).map_err(crate::reader::Error::ReadingFileIndex)?;
 
-        for file in &files {
-            if let Some(archive_index) = file.archive_index {
-                if dat_files.get(&archive_index).is_none() {
-                    let file_name = path.to_str().unwrap();
-                    let mut archive_path = file_name[..file_name.len() - 7].to_string();
-                    archive_path.push_str(&format!("{archive_index:03}.vpk"));
-                    let file = fs::File::open(archive_path)
-                        .map_err(crate::reader::Error::ReadingInputFile)?;
-                    let stm = BufReader::new(file);
-                    dat_files.insert(archive_index, stm);
+        let file_tree_size = u64::from(hdr.file_tree_size);
+        let file_data_offset = header_size + file_tree_size;
+        let archive_md5_offset = file_data_offset + u64::from(hdr.file_data_section_size);
+
+        // checksum and signature sections
+        let mut archive_md5_entries = Vec::new();
+        let mut other_md5 = None;
+        let mut signature = None;
+
+        if hdr.version >= 2 {
+            if hdr.archive_md5_section_size % ARCHIVE_MD5_ENTRY_SIZE != 0 {
+                return Err(crate::reader::Error::InvalidHeader(format!(
+                    "archive MD5 section size {} is not a multiple of {ARCHIVE_MD5_ENTRY_SIZE}",
+                    hdr.archive_md5_section_size
+                )));
+            }
+
+            stm.seek(SeekFrom::Start(archive_md5_offset))
+                .map_err(crate::reader::Error::ReadingInputFile)?;
+
+            for _ in 0..hdr.archive_md5_section_size / ARCHIVE_MD5_ENTRY_SIZE {
+                let entry = ArchiveMd5Entry::read(&mut stm)
+                    .map_err(crate::reader::Error::ReadingFileIndex)?;
+                archive_md5_entries.push(entry);
+            }
+
+            match hdr.other_md5_section_size {
+                0 => {}
+                OTHER_MD5_SECTION_SIZE => {
+                    let section = OtherMd5Section::read(&mut stm)
+                        .map_err(crate::reader::Error::ReadingFileIndex)?;
+                    other_md5 = Some(section);
+                }
+                size => {
+                    return Err(crate::reader::Error::InvalidHeader(format!(
+                        "other MD5 section size {size}, expected {OTHER_MD5_SECTION_SIZE}"
+                    )));
                 }
             }
-        }
 
-        let file_data_offset = header_size + u64::from(hdr.file_tree_size);
+            if hdr.signature_section_size > 0 {
+                let section = SignatureSection::read(&mut stm, hdr.signature_section_size)
+                    .map_err(crate::reader::Error::ReadingFileIndex)?;
+                signature = Some(section);
+            }
+        }
 
         Ok(Self {
             dir_file: stm,
-            dat_files,
+            chunk_files: ChunkFiles::new(&path),
             files,
+            strict: options.strict,
+            version: hdr.version,
+            header_size,
+            file_tree_size,
             file_data_offset,
+            archive_md5_offset,
+            archive_md5_entries,
+            other_md5,
+            signature,
         })
     }
+
+    /// Calculates MD5 of `count` bytes of a stream starting at `offset`.
+    fn md5_of(
+        stm: &mut BufReader<fs::File>,
+        offset: u64,
+        count: u64,
+    ) -> crate::reader::Result<[u8; 16]> {
+        stm.seek(SeekFrom::Start(offset))
+            .map_err(crate::reader::Error::ReadingInputFile)?;
+
+        let mut hasher = Md5::new();
+        let copied = io::copy(&mut stm.by_ref().take(count), &mut hasher)
+            .map_err(crate::reader::Error::ReadingInputFile)?;
+
+        if copied != count {
+            return Err(crate::reader::Error::ReadingInputFile(io_error!(
+                UnexpectedEof,
+                "expected {count} bytes at offset {offset}, got {copied}"
+            )));
+        }
+
+        Ok(hasher.finalize().into())
+    }
 }
 
 impl crate::reader::Reader for Reader {
@@ -83,7 +166,7 @@ impl crate::reader::Reader for Reader {
             .files
             .get(index)
             .expect("`index` should be within boundaries");
-        let size = u64::try_from(file.preload_bytes.len()).unwrap() + u64::from(file.entry_offset);
+        let size = u64::try_from(file.preload_bytes.len()).unwrap() + u64::from(file.entry_length);
 
         crate::reader::File {
             name: file.name.clone(),
@@ -100,12 +183,15 @@ impl crate::reader::Reader for Reader {
             .files
             .get(index)
             .expect("`index` should be within boundaries");
-        let mut output = file.preload_bytes.clone();
+        let preload_bytes = file.preload_bytes.as_slice();
 
-        if file.entry_length > 0 {
+        // preload bytes are followed by the rest of the data, which is streamed from the archive
+        let rdr: Box<dyn Read + 'a> = if file.entry_length == 0 {
+            Box::new(preload_bytes)
+        } else {
             let (stm, entry_offset) = match file.archive_index {
                 Some(archive_index) => (
-                    self.dat_files.get_mut(&archive_index).unwrap(),
+                    self.chunk_files.get(archive_index)?,
                     u64::from(file.entry_offset),
                 ),
                 None => (
@@ -114,18 +200,148 @@ impl crate::reader::Reader for Reader {
                 ),
             };
 
-            let mut entry_buf = vec![0; file.entry_length as usize];
-
             stm.seek(SeekFrom::Start(entry_offset))
                 .map_err(crate::reader::Error::ReadingInputFile)?;
-            stm.read_exact(&mut entry_buf)
-                .map_err(crate::reader::Error::ReadingInputFile)?;
 
-            output.append(&mut entry_buf);
+            Box::new(preload_bytes.chain(stm.take(u64::from(file.entry_length))))
+        };
+
+        if self.strict {
+            return Ok(Box::new(CrcReader {
+                inner: rdr,
+                hasher: crc32fast::Hasher::new(),
+                expected_crc: file.crc,
+            }));
         }
 
-        let c = Cursor::new(output);
-        Ok(Box::new(c))
+        Ok(rdr)
+    }
+
+    fn verify(&mut self) -> crate::reader::Result<()> {
+        let Some(other_md5) = &self.other_md5 else {
+            return Ok(());
+        };
+
+        let archive_md5_section_size =
+            self.archive_md5_entries.len() as u64 * u64::from(ARCHIVE_MD5_ENTRY_SIZE);
+
+        let checksums = [
+            (
+                "file tree MD5",
+                Self::md5_of(&mut self.dir_file, self.header_size, self.file_tree_size)?,
+                other_md5.tree_md5,
+            ),
+            (
+                "archive MD5 section MD5",
+                Self::md5_of(
+                    &mut self.dir_file,
+                    self.archive_md5_offset,
+                    archive_md5_section_size,
+                )?,
+                other_md5.archive_md5_section_md5,
+            ),
+            (
+                "whole file MD5",
+                // everything up to the whole file checksum itself
+                Self::md5_of(
+                    &mut self.dir_file,
+                    0,
+                    self.archive_md5_offset + archive_md5_section_size + 32,
+                )?,
+                other_md5.whole_file_md5,
+            ),
+        ];
+
+        for (what, checksum, expected_checksum) in checksums {
+            if checksum != expected_checksum {
+                return Err(invalid_md5(what.to_string(), checksum, expected_checksum));
+            }
+        }
+
+        for entry in &self.archive_md5_entries {
+            let archive_index = u16::try_from(entry.archive_index).ok();
+            let (stm, offset) = match archive_index {
+                Some(EMBEDDED_ARCHIVE_INDEX) => (&mut self.dir_file, self.file_data_offset),
+                Some(archive_index) => (self.chunk_files.get(archive_index)?, 0),
+                None => {
+                    return Err(crate::reader::Error::Other(format!(
+                        "archive MD5 section refers to invalid chunk {}",
+                        entry.archive_index
+                    )))
+                }
+            };
+
+            let checksum = Self::md5_of(
+                stm,
+                offset + u64::from(entry.starting_offset),
+                u64::from(entry.count),
+            )?;
+
+            if checksum != entry.md5 {
+                return Err(invalid_md5(
+                    format!(
+                        "chunk {:03} MD5 at offset {}",
+                        entry.archive_index, entry.starting_offset
+                    ),
+                    checksum,
+                    entry.md5,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("version"), self.version.to_string());
+
+        if let Some(signature) = &self.signature {
+            attrs.insert(String::from("public key"), to_hex(&signature.public_key));
+            attrs.insert(String::from("signature"), to_hex(&signature.signature));
+        }
+
+        attrs
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn invalid_md5(what: String, checksum: [u8; 16], expected: [u8; 16]) -> crate::reader::Error {
+    crate::reader::Error::InvalidChecksum {
+        what,
+        checksum: to_hex(&checksum),
+        expected_checksum: to_hex(&expected),
+    }
+}
+
+/// Calculates CRC32 of the data read and fails at the end of the data if it does not match the expected one.
+struct CrcReader<R: Read> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+    expected_crc: u32,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.hasher.update(&buf[..len]);
+
+        if len == 0 && !buf.is_empty() {
+            let crc = self.hasher.clone().finalize();
+
+            if crc != self.expected_crc {
+                return Err(io_error!(
+                    InvalidData,
+                    "invalid CRC32 {crc:08x}, expected {:08x}",
+                    self.expected_crc
+                ));
+            }
+        }
+
+        Ok(len)
     }
 }
 
@@ -135,3 +351,35 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    // chunk files have no header
+    if let Some(dir_path) = dir_path_of_chunk(path).filter(|dir_path| dir_path.exists()) {
+        let dir_name = dir_path.file_name().unwrap_or_default().to_string_lossy();
+        return Ok(crate::Probe::Valid(format!("chunk of '{dir_name}'")));
+    }
+
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let hdr = Header::read(&mut BufReader::new(file))?;
+
+    if !hdr.signature.eq(VPK_SIGNATURE) {
+        return Ok(crate::Probe::Invalid(String::from("invalid signature")));
+    }
+
+    if !VALID_VERSIONS.contains(&hdr.version) {
+        return Ok(crate::Probe::Invalid(format!(
+            "unsupported version {}",
+            hdr.version
+        )));
+    }
+
+    if u64::from(hdr.file_tree_size) > file_size {
+        return Ok(crate::Probe::Invalid(format!(
+            "file tree size {} is out of bounds",
+            hdr.file_tree_size
+        )));
+    }
+
+    Ok(crate::Probe::Valid(format!("version {}", hdr.version)))
+}
@@ -1,10 +1,12 @@
 use std::{
-    io::{BufRead, Result},
+    io::{BufRead, Read, Result, Write},
     path::PathBuf,
     str,
 };
 
-use crate::{io_error, PathBufUtils, ReadEx};
+use crate::{io_error, PathBufUtils, ReadEx, WriteEx};
+
+use super::common::{EMBEDDED_ARCHIVE_INDEX, ENTRY_TERMINATOR};
 
 pub struct Header {
     pub signature: [u8; 4],
@@ -41,6 +43,101 @@ impl Header {
 
         Ok(hdr)
     }
+
+    pub fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(&self.signature)?;
+        w.write_u32_le(self.version)?;
+        w.write_u32_le(self.file_tree_size)?;
+
+        if self.version >= 2 {
+            w.write_u32_le(self.file_data_section_size)?;
+            w.write_u32_le(self.archive_md5_section_size)?;
+            w.write_u32_le(self.other_md5_section_size)?;
+            w.write_u32_le(self.signature_section_size)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checksum of a part of a chunk file.
+pub struct ArchiveMd5Entry {
+    pub archive_index: u32,
+    pub starting_offset: u32,
+    pub count: u32,
+    pub md5: [u8; 16],
+}
+
+impl ArchiveMd5Entry {
+    pub fn read(r: &mut impl BufRead) -> Result<Self> {
+        let archive_index = r.read_u32_le()?;
+        let starting_offset = r.read_u32_le()?;
+        let count = r.read_u32_le()?;
+        let mut md5 = [0; 16];
+        r.read_exact(&mut md5)?;
+
+        Ok(Self {
+            archive_index,
+            starting_offset,
+            count,
+            md5,
+        })
+    }
+}
+
+pub struct OtherMd5Section {
+    pub tree_md5: [u8; 16],
+    pub archive_md5_section_md5: [u8; 16],
+    /// Checksum of the directory file from its beginning up to this field.
+    pub whole_file_md5: [u8; 16],
+}
+
+impl OtherMd5Section {
+    pub fn read(r: &mut impl BufRead) -> Result<Self> {
+        let mut section = Self {
+            tree_md5: [0; 16],
+            archive_md5_section_md5: [0; 16],
+            whole_file_md5: [0; 16],
+        };
+
+        r.read_exact(&mut section.tree_md5)?;
+        r.read_exact(&mut section.archive_md5_section_md5)?;
+        r.read_exact(&mut section.whole_file_md5)?;
+
+        Ok(section)
+    }
+}
+
+pub struct SignatureSection {
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignatureSection {
+    pub fn read(r: &mut impl BufRead, section_size: u32) -> Result<Self> {
+        let mut r = r.by_ref().take(u64::from(section_size));
+
+        let public_key_size = r.read_u32_le()?;
+        let public_key = r.read_u8_vec(bounded_size(public_key_size, section_size)?)?;
+        let signature_size = r.read_u32_le()?;
+        let signature = r.read_u8_vec(bounded_size(signature_size, section_size)?)?;
+
+        Ok(Self {
+            public_key,
+            signature,
+        })
+    }
+}
+
+fn bounded_size(size: u32, section_size: u32) -> Result<usize> {
+    if size > section_size {
+        return Err(io_error!(
+            InvalidData,
+            "field size {size} exceeds signature section size {section_size}"
+        ));
+    }
+
+    Ok(size as usize)
 }
 
 pub struct File {
@@ -77,13 +174,13 @@ pub fn read_file_tree(r: &mut impl BufRead) -> Result<Vec<File>> {
                 };
 
                 let crc = r.read_u32_le()?;
-                let preload_len = usize::try_from(r.read_u16_le()?).unwrap();
+                let preload_len = usize::from(r.read_u16_le()?);
                 let archive_index = r.read_u16_le()?;
                 let entry_offset = r.read_u32_le()?;
                 let entry_length = r.read_u32_le()?;
                 let terminator = r.read_u16_le()?;
 
-                if terminator != 0xffff {
+                if terminator != ENTRY_TERMINATOR {
                     return Err(io_error!(InvalidData, "invalid file entry terminator",));
                 }
 
@@ -96,7 +193,7 @@ pub fn read_file_tree(r: &mut impl BufRead) -> Result<Vec<File>> {
                         name: format_filepath(&basepath, &filename, &ext),
                         crc,
                         preload_bytes,
-                        archive_index: if archive_index == 0x7fff {
+                        archive_index: if archive_index == EMBEDDED_ARCHIVE_INDEX {
                             None
                         } else {
                             Some(archive_index)
@@ -112,16 +209,21 @@ pub fn read_file_tree(r: &mut impl BufRead) -> Result<Vec<File>> {
     Ok(files)
 }
 
+/// Joins tree parts into a path. A single space stands for the root directory or a missing extension.
 fn format_filepath(basepath: &str, filename: &str, ext: &str) -> PathBuf {
     let mut path = String::new();
 
-    if !basepath.is_empty() {
-        path.push_str(basepath);
+    if basepath != " " {
+        path.push_str(basepath.trim_end_matches('/'));
+        path.push('/');
     }
 
     path.push_str(filename);
-    path.push('.');
-    path.push_str(ext);
+
+    if ext != " " {
+        path.push('.');
+        path.push_str(ext);
+    }
 
     PathBuf::from_unix(&path)
 }
@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use rstest::*;
+use tempdir::TempDir;
+
+use crate::{
+    reader::{Error, Options},
+    InputFileListBuilder,
+};
+
+fn create_archive(dir: &TempDir, name: &str) -> std::path::PathBuf {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let path = dir.path().join(name);
+    super::create_archive(input_files, &path, &HashMap::new()).unwrap();
+    path
+}
+
+fn read_all_files(path: &Path, strict: bool) -> crate::reader::Result<()> {
+    let mut rdr = super::make_reader(
+        path,
+        Options {
+            strict,
+            ..Default::default()
+        },
+    )?;
+
+    for index in 0..rdr.file_count() {
+        let mut stm = rdr.create_file_reader(index)?;
+        io::copy(&mut stm, &mut io::sink()).map_err(Error::ReadingInputFile)?;
+    }
+
+    rdr.verify()
+}
+
+#[test]
+fn correct() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = create_archive(&dir, "archive.vpk");
+    assert!(read_all_files(&path, true).is_ok());
+
+    let path = create_archive(&dir, "pak01_dir.vpk");
+    assert!(read_all_files(&path, true).is_ok());
+}
+
+#[test]
+fn invalid_crc() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = create_archive(&dir, "pak01_dir.vpk");
+
+    // damage file data in the chunk
+    let chunk_path = dir.path().join("pak01_000.vpk");
+    let mut data = fs::read(&chunk_path).unwrap();
+    data[100] ^= 0xFF;
+    fs::write(&chunk_path, data).unwrap();
+
+    let mut rdr = super::make_reader(
+        &path,
+        Options {
+            strict: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for index in 0..rdr.file_count() {
+        let mut stm = rdr.create_file_reader(index).unwrap();
+        assert!(io::copy(&mut stm, &mut io::sink()).is_ok());
+    }
+
+    let mut rdr = super::make_reader(
+        &path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let failed = (0..rdr.file_count()).find(|&index| {
+        let mut stm = rdr.create_file_reader(index).unwrap();
+        io::copy(&mut stm, &mut io::sink()).is_err()
+    });
+    assert_eq!(
+        failed.map(|index| rdr.get_file(index).name),
+        Some(Path::new("dir1/img002.jpg").to_path_buf())
+    );
+}
+
+#[rstest]
+#[case::tree(28, "file tree MD5")]
+#[case::whole_file(-1, "whole file MD5")]
+#[case::tree_checksum(-48, "file tree MD5")]
+fn invalid_md5(#[case] offset: i64, #[case] what: &str) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = create_archive(&dir, "archive.vpk");
+
+    let mut data = fs::read(&path).unwrap();
+    let offset = if offset < 0 {
+        (data.len() as i64 + offset) as usize
+    } else {
+        offset as usize
+    };
+    data[offset] ^= 0x01;
+    fs::write(&path, data).unwrap();
+
+    let mut rdr = super::make_reader(
+        &path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    match rdr.verify() {
+        Err(Error::InvalidChecksum { what: actual, .. }) => assert_eq!(actual, what),
+        _ => panic!("expected {what} mismatch"),
+    }
+}
+
+#[test]
+fn invalid_chunk_md5() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = create_archive(&dir, "pak01_dir.vpk");
+
+    let chunk_path = dir.path().join("pak01_000.vpk");
+    let mut data = fs::read(&chunk_path).unwrap();
+    data[100] ^= 0xFF;
+    fs::write(&chunk_path, data).unwrap();
+
+    let mut rdr = super::make_reader(
+        &path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    match rdr.verify() {
+        Err(Error::InvalidChecksum { what, .. }) => assert_eq!(what, "chunk 000 MD5 at offset 0"),
+        _ => panic!("expected chunk MD5 mismatch"),
+    }
+}
+
+#[test]
+fn signature_section() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = create_archive(&dir, "archive.vpk");
+
+    // append signature section and set its size in the header
+    let mut data = fs::read(&path).unwrap();
+    let section = [
+        &2u32.to_le_bytes()[..],
+        &[0xAB, 0xCD],
+        &3u32.to_le_bytes(),
+        &[0x01, 0x02, 0x03],
+    ]
+    .concat();
+    data[24..28].copy_from_slice(&(section.len() as u32).to_le_bytes());
+    data.extend_from_slice(&section);
+    fs::write(&path, data).unwrap();
+
+    let rdr = super::make_reader(
+        &path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let attrs = rdr.attrs();
+
+    assert_eq!(attrs.get("public key").map(String::as_str), Some("abcd"));
+    assert_eq!(attrs.get("signature").map(String::as_str), Some("010203"));
+}
+
+#[test]
+fn open_chunk_file() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    create_archive(&dir, "pak01_dir.vpk");
+
+    let rdr = super::make_reader(&dir.path().join("pak01_000.vpk"), Options::default()).unwrap();
+    assert_eq!(rdr.file_count(), 5);
+}
+
+#[test]
+fn missing_chunk() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = create_archive(&dir, "pak01_dir.vpk");
+    fs::remove_file(dir.path().join("pak01_000.vpk")).unwrap();
+
+    // chunks are opened when file data is read
+    let mut rdr = super::make_reader(&path, Options::default()).unwrap();
+    let index = (0..rdr.file_count())
+        .find(|&index| rdr.get_file(index).name == Path::new("img001.png"))
+        .unwrap();
+
+    let res = rdr.create_file_reader(index);
+
+    match res {
+        Err(Error::OpeningChunkFile(chunk_path, _)) => {
+            assert_eq!(chunk_path, dir.path().join("pak01_000.vpk"));
+        }
+        _ => panic!("expected missing chunk error"),
+    }
+}
+
+#[test]
+fn read_in_small_parts() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("preload-bytes"), String::from("10"));
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let path = dir.path().join("pak01_dir.vpk");
+    super::create_archive(input_files, &path, &params).unwrap();
+
+    let mut rdr = super::make_reader(
+        &path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    for index in 0..rdr.file_count() {
+        let name = rdr.get_file(index).name;
+        let expected = fs::read(Path::new("./samples/unpacked").join(&name)).unwrap();
+
+        let mut stm = rdr.create_file_reader(index).unwrap();
+        let mut data = Vec::new();
+        let mut buf = [0; 7];
+
+        loop {
+            let len = stm.read(&mut buf).unwrap();
+
+            if len == 0 {
+                break;
+            }
+
+            data.extend_from_slice(&buf[..len]);
+        }
+
+        assert_eq!(data, expected, "{}", name.display());
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use md5::{Digest, Md5};
+
+use crate::{
+    io_error, parse_size, writer, FileType, InputFileList, Overhead, PathBufUtils, WriteEx,
+};
+
+use super::{
+    chunks::{chunk_path, chunk_prefix},
+    common::{
+        ARCHIVE_MD5_ENTRY_SIZE, DIRECTORY_ENTRY_SIZE, EMBEDDED_ARCHIVE_INDEX, ENTRY_TERMINATOR,
+        HEADER_V1_SIZE, HEADER_V2_SIZE, OTHER_MD5_SECTION_SIZE, VPK_SIGNATURE,
+    },
+    reader_bits::Header,
+};
+
+/// Estimate for `max-size`: v2 header with checksums and the tree terminator; directory entry,
+/// name terminator, extension and directory terminators of the tree for every file.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: HEADER_V2_SIZE + OTHER_MD5_SECTION_SIZE as u64 + 1,
+    entry_size: DIRECTORY_ENTRY_SIZE + 1 + 2 + 2,
+    name_copies: 1,
+};
+
+/// Chunk size used by Valve tools.
+const DEFAULT_CHUNK_SIZE: u64 = 200 << 20;
+/// Chunk files are checksummed in fractions of this size.
+const MD5_FRACTION_SIZE: u64 = 1 << 20;
+
+struct Options {
+    version: u32,
+    chunk_size: u64,
+    preload_bytes: u16,
+}
+
+struct Entry {
+    src_path: PathBuf,
+    crc: u32,
+    preload_bytes: Vec<u8>,
+    archive_index: u16,
+    entry_offset: u32,
+    entry_length: u32,
+}
+
+/// Files grouped by extension, then by directory, then by name, the way the file tree stores them.
+type Tree = BTreeMap<String, BTreeMap<String, BTreeMap<String, Entry>>>;
+
+pub fn create_archive(
+    input_files: InputFileList,
+    path: &Path,
+    params: &HashMap<String, String>,
+) -> writer::Result<()> {
+    let options = parse_options(params)?;
+    let mut tree = build_tree(input_files, &options)?;
+
+    let header_size = match options.version {
+        1 => HEADER_V1_SIZE,
+        _ => HEADER_V2_SIZE,
+    };
+    let tree_size = tree_size(&tree);
+    let file_tree_size =
+        u32::try_from(tree_size).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+
+    // the file is read back to calculate its checksum
+    let out = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(writer::Error::CreatingOutputFile)?;
+    let mut out = BufWriter::new(out);
+
+    // header and file tree placeholder
+    out.seek(SeekFrom::Start(header_size + tree_size))
+        .map_err(writer::Error::WritingHeader)?;
+
+    // `name_dir.vpk` keeps file data in `name_000.vpk`, `name_001.vpk`...
+    let (file_data_size, chunk_paths) = match chunk_prefix(path) {
+        Some(_) => {
+            let chunk_paths = write_chunks(&mut tree, path, &options)?;
+            (0, chunk_paths)
+        }
+        None => (write_embedded_data(&mut tree, &mut out)?, Vec::new()),
+    };
+
+    let file_data_section_size =
+        u32::try_from(file_data_size).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+
+    let mut tree_buf = Vec::with_capacity(tree_size as usize);
+    write_tree(&mut tree_buf, &tree).expect("writing to memory buffer");
+    debug_assert_eq!(tree_buf.len() as u64, tree_size);
+
+    let archive_md5_section = match options.version {
+        1 => Vec::new(),
+        _ => make_archive_md5_section(&chunk_paths)?,
+    };
+
+    let hdr = Header {
+        signature: *VPK_SIGNATURE,
+        version: options.version,
+        file_tree_size,
+        file_data_section_size,
+        archive_md5_section_size: u32::try_from(archive_md5_section.len())
+            .map_err(|_| writer::Error::OutputFileLarger4GiB)?,
+        other_md5_section_size: OTHER_MD5_SECTION_SIZE,
+        signature_section_size: 0,
+    };
+
+    out.rewind().map_err(writer::Error::WritingHeader)?;
+    hdr.write(&mut out).map_err(writer::Error::WritingHeader)?;
+    out.write_all(&tree_buf)
+        .map_err(writer::Error::WritingFileIndex)?;
+
+    if options.version >= 2 {
+        out.seek(SeekFrom::End(0))
+            .map_err(writer::Error::WritingFileIndex)?;
+        out.write_all(&archive_md5_section)
+            .map_err(writer::Error::WritingFileIndex)?;
+
+        let tree_md5 = Md5::digest(&tree_buf);
+        let archive_md5_section_md5 = Md5::digest(&archive_md5_section);
+        out.write_all(&tree_md5)
+            .map_err(writer::Error::WritingFileIndex)?;
+        out.write_all(&archive_md5_section_md5)
+            .map_err(writer::Error::WritingFileIndex)?;
+
+        // checksum of everything written so far
+        let mut out = out
+            .into_inner()
+            .map_err(|err| writer::Error::WritingFileIndex(err.into_error()))?;
+        out.rewind().map_err(writer::Error::WritingFileIndex)?;
+        let mut hasher = Md5::new();
+        io::copy(&mut out, &mut hasher).map_err(writer::Error::WritingFileIndex)?;
+        out.write_all(&hasher.finalize())
+            .map_err(writer::Error::WritingFileIndex)?;
+    } else {
+        out.flush().map_err(writer::Error::WritingFileIndex)?;
+    }
+
+    Ok(())
+}
+
+fn parse_options(params: &HashMap<String, String>) -> writer::Result<Options> {
+    let version = match params.get("version").map(String::as_str) {
+        None | Some("2") => 2,
+        Some("1") => 1,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "version",
+                format!("expected '1' or '2', got '{value}'"),
+            ))
+        }
+    };
+    let chunk_size = parse_size(params, "chunk-size")?.unwrap_or(DEFAULT_CHUNK_SIZE);
+    let preload_bytes = match params.get("preload-bytes") {
+        None => 0,
+        Some(value) => value.parse().map_err(|_| {
+            writer::Error::InvalidParameter(
+                "preload-bytes",
+                format!("expected a number from 0 to {}, got '{value}'", u16::MAX),
+            )
+        })?,
+    };
+
+    Ok(Options {
+        version,
+        chunk_size,
+        preload_bytes,
+    })
+}
+
+fn build_tree(input_files: InputFileList, options: &Options) -> writer::Result<Tree> {
+    let mut tree = Tree::new();
+
+    for input_file in input_files {
+        if input_file.file_type != FileType::RegularFile {
+            continue;
+        }
+
+        let path = input_file
+            .dst_path
+            .try_to_unix()
+            .map_err(|err| writer::Error::InvalidInputFileName(input_file.dst_path.clone(), err))?;
+
+        let (dir, file_name) = path.rsplit_once('/').unwrap_or((" ", &path));
+        let (name, ext) = match file_name.rsplit_once('.') {
+            Some((name, ext)) if !name.is_empty() && !ext.is_empty() => (name, ext),
+            _ => (file_name, " "),
+        };
+
+        let size = input_file
+            .src_path
+            .metadata()
+            .map_err(|err| {
+                writer::Error::ReadingInputFileMetadata(input_file.src_path.clone(), err)
+            })?
+            .len();
+
+        if size > u64::from(u32::MAX) {
+            return Err(writer::Error::InputFileLarger4GiB(input_file.src_path));
+        }
+
+        // CRC and preload bytes are read along with the data later
+        let preload_len = size.min(u64::from(options.preload_bytes)) as usize;
+        let entry = Entry {
+            src_path: input_file.src_path,
+            crc: 0,
+            preload_bytes: vec![0; preload_len],
+            archive_index: EMBEDDED_ARCHIVE_INDEX,
+            entry_offset: 0,
+            entry_length: (size - preload_len as u64) as u32,
+        };
+
+        tree.entry(ext.to_string())
+            .or_default()
+            .entry(dir.to_string())
+            .or_default()
+            .insert(name.to_string(), entry);
+    }
+
+    Ok(tree)
+}
+
+fn tree_size(tree: &Tree) -> u64 {
+    let mut size = 1;
+
+    for (ext, dirs) in tree {
+        size += ext.len() as u64 + 2;
+
+        for (dir, files) in dirs {
+            size += dir.len() as u64 + 2;
+
+            for (name, entry) in files {
+                size +=
+                    name.len() as u64 + 1 + DIRECTORY_ENTRY_SIZE + entry.preload_bytes.len() as u64;
+            }
+        }
+    }
+
+    size
+}
+
+fn write_tree(w: &mut impl Write, tree: &Tree) -> io::Result<()> {
+    for (ext, dirs) in tree {
+        w.write_zstring(ext)?;
+
+        for (dir, files) in dirs {
+            w.write_zstring(dir)?;
+
+            for (name, entry) in files {
+                w.write_zstring(name)?;
+                w.write_u32_le(entry.crc)?;
+                w.write_u16_le(entry.preload_bytes.len() as u16)?;
+                w.write_u16_le(entry.archive_index)?;
+                w.write_u32_le(entry.entry_offset)?;
+                w.write_u32_le(entry.entry_length)?;
+                w.write_u16_le(ENTRY_TERMINATOR)?;
+                w.write_all(&entry.preload_bytes)?;
+            }
+
+            w.write_u8(0)?;
+        }
+
+        w.write_u8(0)?;
+    }
+
+    w.write_u8(0)
+}
+
+/// Copies input file into `out`, keeping preload bytes in the entry. Calculates CRC of the whole file.
+fn write_entry_data(entry: &mut Entry, out: &mut impl Write) -> writer::Result<()> {
+    let mut file = fs::File::open(&entry.src_path)
+        .map_err(|err| writer::Error::OpeningInputFile(entry.src_path.clone(), err))?;
+    let mut hasher = crc32fast::Hasher::new();
+
+    let res = file.read_exact(&mut entry.preload_bytes).and_then(|_| {
+        hasher.update(&entry.preload_bytes);
+
+        let mut buf = vec![0; 64 * 1024];
+        let mut written = 0;
+
+        loop {
+            let len = file.read(&mut buf)?;
+
+            if len == 0 {
+                break;
+            }
+
+            hasher.update(&buf[..len]);
+            out.write_all(&buf[..len])?;
+            written += len as u64;
+        }
+
+        if written == u64::from(entry.entry_length) {
+            Ok(())
+        } else {
+            Err(io_error!(InvalidData, "file size changed while archiving"))
+        }
+    });
+
+    res.map_err(|err| writer::Error::ArchivingInputFile(entry.src_path.clone(), err))?;
+    entry.crc = hasher.finalize();
+
+    Ok(())
+}
+
+/// Writes file data right after the file tree. Returns size of the data.
+fn write_embedded_data(tree: &mut Tree, out: &mut impl Write) -> writer::Result<u64> {
+    let mut offset = 0u64;
+
+    for files in tree.values_mut().flat_map(BTreeMap::values_mut) {
+        for entry in files.values_mut() {
+            entry.entry_offset =
+                u32::try_from(offset).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+            write_entry_data(entry, out)?;
+            offset += u64::from(entry.entry_length);
+        }
+    }
+
+    Ok(offset)
+}
+
+/// Writes file data into `name_000.vpk`, `name_001.vpk`... starting a new chunk when the current one
+/// would grow beyond the chunk size. Returns paths of the chunks.
+fn write_chunks(
+    tree: &mut Tree,
+    dir_path: &Path,
+    options: &Options,
+) -> writer::Result<Vec<PathBuf>> {
+    let mut chunk_paths = Vec::new();
+    let mut chunk: Option<BufWriter<fs::File>> = None;
+    let mut chunk_size = 0u64;
+
+    for files in tree.values_mut().flat_map(BTreeMap::values_mut) {
+        for entry in files.values_mut() {
+            if entry.entry_length == 0 {
+                write_entry_data(entry, &mut io::sink())?;
+                continue;
+            }
+
+            let length = u64::from(entry.entry_length);
+
+            if chunk.is_none() || chunk_size > 0 && chunk_size + length > options.chunk_size {
+                if let Some(mut chunk) = chunk.take() {
+                    chunk.flush().map_err(writer::Error::WritingFileData)?;
+                }
+
+                let index = chunk_paths.len();
+
+                if index >= usize::from(EMBEDDED_ARCHIVE_INDEX) {
+                    return Err(writer::Error::Other(String::from("too many chunk files")));
+                }
+
+                let chunk_path = chunk_path(dir_path, index as u16)
+                    .expect("directory file name should end with `_dir.vpk`");
+
+                let file =
+                    fs::File::create(&chunk_path).map_err(writer::Error::CreatingOutputFile)?;
+                chunk = Some(BufWriter::new(file));
+                chunk_paths.push(chunk_path);
+                chunk_size = 0;
+            }
+
+            entry.archive_index = (chunk_paths.len() - 1) as u16;
+            entry.entry_offset =
+                u32::try_from(chunk_size).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+            write_entry_data(entry, chunk.as_mut().expect("chunk should be open"))?;
+            chunk_size += length;
+        }
+    }
+
+    if let Some(mut chunk) = chunk {
+        chunk.flush().map_err(writer::Error::WritingFileData)?;
+    }
+
+    Ok(chunk_paths)
+}
+
+/// Checksums every chunk file in 1 MiB fractions.
+fn make_archive_md5_section(chunk_paths: &[PathBuf]) -> writer::Result<Vec<u8>> {
+    let mut section = Vec::new();
+
+    for (index, chunk_path) in chunk_paths.iter().enumerate() {
+        let mut file = fs::File::open(chunk_path).map_err(writer::Error::WritingFileIndex)?;
+        let mut offset = 0u32;
+
+        loop {
+            let mut fraction = Vec::new();
+            (&mut file)
+                .take(MD5_FRACTION_SIZE)
+                .read_to_end(&mut fraction)
+                .map_err(writer::Error::WritingFileIndex)?;
+
+            if fraction.is_empty() {
+                break;
+            }
+
+            let len = fraction.len() as u32;
+            section
+                .write_u32_le(index as u32)
+                .expect("writing to memory buffer");
+            section
+                .write_u32_le(offset)
+                .expect("writing to memory buffer");
+            section.write_u32_le(len).expect("writing to memory buffer");
+            section
+                .write_all(&Md5::digest(&fraction))
+                .expect("writing to memory buffer");
+
+            offset += len;
+        }
+    }
+
+    debug_assert_eq!(section.len() % ARCHIVE_MD5_ENTRY_SIZE as usize, 0);
+    Ok(section)
+}
@@ -0,0 +1,82 @@
+use std::{collections::HashMap, fs, io, io::Read, path::Path};
+
+use hex_literal::hex;
+use rstest::*;
+use sha1::{Digest, Sha1};
+use tempdir::TempDir;
+
+use crate::{reader::Options, InputFileListBuilder};
+
+#[rstest]
+#[case("1", hex!("c50875367b8b77595a6dff1462e55a53f946899e"))]
+#[case("2", hex!("c9b9f2c7dfe3be189ccdc4f48d304a753df3d345"))]
+fn correct(#[case] version: &str, #[case] sha1: [u8; 20]) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("version"), version.to_string());
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.vpk");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_ok());
+
+    let mut file = fs::File::open(&output_path).unwrap();
+    let mut hasher = Sha1::new();
+    io::copy(&mut file, &mut hasher).unwrap();
+    let hash = hasher.finalize();
+
+    assert_eq!(hash[..], sha1);
+}
+
+#[test]
+fn chunks_with_preload_bytes() {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let mut params = HashMap::new();
+    params.insert(String::from("chunk-size"), String::from("12K"));
+    params.insert(String::from("preload-bytes"), String::from("16"));
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("pak01_dir.vpk");
+    let res = super::create_archive(input_files, &output_path, &params);
+    assert!(res.is_ok());
+
+    // img002.jpg (11590 bytes) and img001.png (22290 bytes) do not fit into the same 12 KiB chunk
+    for index in 0..3 {
+        assert!(dir.path().join(format!("pak01_{index:03}.vpk")).exists());
+    }
+    assert!(!dir.path().join("pak01_003.vpk").exists());
+
+    let mut rdr = super::make_reader(
+        &output_path,
+        Options {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(rdr.file_count(), 5);
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let expected = fs::read(Path::new("./samples/unpacked").join(&file.name)).unwrap();
+
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(file.size, Some(expected.len() as u64));
+        assert_eq!(data, expected, "{}", file.name.display());
+    }
+}
@@ -0,0 +1,116 @@
+pub const IWAD_SIGNATURE: &[u8; 4] = b"IWAD";
+pub const PWAD_SIGNATURE: &[u8; 4] = b"PWAD";
+pub const HEADER_SIZE: u32 = 12;
+pub const ENTRY_SIZE: usize = 16;
+/// Lump names are padded with nulls, 8-character names are not terminated.
+pub const NAME_SIZE: usize = 8;
+
+/// Extension of extracted lumps.
+pub const LUMP_EXTENSION: &str = "lmp";
+/// Lumps are numbered with at least this many digits.
+const MIN_NUMBER_WIDTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WadType {
+    /// Main game data.
+    Iwad,
+    /// Patches and mods.
+    Pwad,
+}
+
+impl WadType {
+    /// Name used in `type=` option.
+    pub fn name(self) -> &'static str {
+        match self {
+            WadType::Iwad => "iwad",
+            WadType::Pwad => "pwad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [WadType::Iwad, WadType::Pwad]
+            .into_iter()
+            .find(|wad_type| wad_type.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn from_signature(signature: &[u8]) -> Option<Self> {
+        match signature {
+            _ if signature == IWAD_SIGNATURE => Some(WadType::Iwad),
+            _ if signature == PWAD_SIGNATURE => Some(WadType::Pwad),
+            _ => None,
+        }
+    }
+
+    pub fn signature(self) -> &'static [u8; 4] {
+        match self {
+            WadType::Iwad => IWAD_SIGNATURE,
+            WadType::Pwad => PWAD_SIGNATURE,
+        }
+    }
+}
+
+/// Lumps are prefixed with their number, so names are unique even for duplicate lumps, and sorting
+/// them keeps the order of the directory, like `0000_E1M1.lmp`, `0001_THINGS.lmp`.
+pub fn lump_file_name(index: usize, lump_count: usize, name: &str) -> String {
+    let width = number_width(lump_count);
+
+    format!("{index:0width$}_{name}.{LUMP_EXTENSION}")
+}
+
+fn number_width(lump_count: usize) -> usize {
+    lump_count
+        .saturating_sub(1)
+        .to_string()
+        .len()
+        .max(MIN_NUMBER_WIDTH)
+}
+
+/// Lump name of an extracted lump, without the extension and the number prefix. The prefix is
+/// only stripped if it has the width [`lump_file_name`] gives it, so `1_INTRO.lmp` is `1_INTRO`.
+pub fn lump_name(file_name: &str, lump_count: usize) -> &str {
+    let name = match file_name.rsplit_once('.') {
+        Some((name, ext)) if ext.eq_ignore_ascii_case(LUMP_EXTENSION) => name,
+        _ => file_name,
+    };
+
+    match name.split_once('_') {
+        Some((number, lump_name))
+            if number.len() == number_width(lump_count)
+                && number.bytes().all(|c| c.is_ascii_digit()) =>
+        {
+            lump_name
+        }
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[rstest::rstest]
+    #[case(0, 1, "E1M1", "0000_E1M1.lmp")]
+    #[case(9999, 10000, "F_START", "9999_F_START.lmp")]
+    #[case(42, 10001, "THINGS", "00042_THINGS.lmp")]
+    fn lump_file_name(
+        #[case] index: usize,
+        #[case] lump_count: usize,
+        #[case] name: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(super::lump_file_name(index, lump_count, name), expected);
+    }
+
+    #[rstest::rstest]
+    #[case("0000_E1M1.lmp", 3, "E1M1")]
+    #[case("0001_F_START.LMP", 3, "F_START")]
+    #[case("PLAYPAL.lmp", 3, "PLAYPAL")]
+    #[case("F_END", 3, "F_END")]
+    #[case("0002_.lmp", 3, "")]
+    #[case("DEMO1.txt", 3, "DEMO1.txt")]
+    #[case("1_INTRO.lmp", 3, "1_INTRO")]
+    #[case("00001_INTRO.lmp", 3, "00001_INTRO")]
+    #[case("00001_INTRO.lmp", 10001, "INTRO")]
+    #[case("0001_INTRO.lmp", 10001, "0001_INTRO")]
+    fn lump_name(#[case] file_name: &str, #[case] lump_count: usize, #[case] expected: &str) {
+        assert_eq!(super::lump_name(file_name, lump_count), expected);
+    }
+}
@@ -0,0 +1,13 @@
+// https://doomwiki.org/wiki/WAD
+
+mod common;
+mod reader;
+mod reader_bits;
+#[cfg(test)]
+mod reader_tests;
+mod writer;
+#[cfg(test)]
+mod writer_tests;
+
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use super::{
+    common::{lump_file_name, WadType, ENTRY_SIZE, HEADER_SIZE},
+    reader_bits::{Header, Lump},
+};
+use crate::{FileType, ReadEx};
+
+pub struct Reader {
+    stm: BufReader<fs::File>,
+    /// In the directory order, which is significant.
+    lumps: Vec<Lump>,
+    wad_type: WadType,
+}
+
+impl Reader {
+    fn open(path: &Path) -> crate::reader::Result<Self> {
+        let file = fs::File::open(path).map_err(crate::reader::Error::OpeningInputFile)?;
+        let file_size = file
+            .metadata()
+            .map_err(crate::reader::Error::ReadingInputFileMetadata)?
+            .len();
+        let mut stm = BufReader::new(file);
+
+        let signature = stm
+            .read_u8_vec(4)
+            .map_err(crate::reader::Error::ReadingSignature)?;
+
+        let Some(wad_type) = WadType::from_signature(&signature) else {
+            return Err(crate::reader::Error::InvalidSignature {
+                signature,
+                expected_signature: WadType::Pwad.signature().to_vec(),
+            });
+        };
+
+        let hdr = Header::read(&mut stm).map_err(crate::reader::Error::ReadingHeader)?;
+
+        let directory_end =
+            u64::from(hdr.directory_offset) + u64::from(hdr.lump_count) * ENTRY_SIZE as u64;
+
+        if directory_end > file_size {
+            return Err(crate::reader::Error::InvalidHeader(format!(
+                "directory of {} lumps at {} is out of bounds",
+                hdr.lump_count, hdr.directory_offset
+            )));
+        }
+
+        stm.seek(SeekFrom::Start(u64::from(hdr.directory_offset)))
+            .map_err(crate::reader::Error::ReadingFileIndex)?;
+
+        let mut lumps = Vec::with_capacity(hdr.lump_count as usize);
+
+        for _ in 0..hdr.lump_count {
+            let lump = Lump::read(&mut stm).map_err(crate::reader::Error::ReadingFileIndex)?;
+
+            // markers like `F_START` have no data, their offset does not matter, data of other lumps
+            // is checked even when not strict, reading it would be silently truncated otherwise
+            if lump.size > 0
+                && (lump.offset < HEADER_SIZE
+                    || u64::from(lump.offset) + u64::from(lump.size) > file_size)
+            {
+                return Err(crate::reader::Error::InvalidHeader(format!(
+                    "lump '{}' at {} of {} bytes is out of bounds",
+                    lump.name, lump.offset, lump.size
+                )));
+            }
+
+            lumps.push(lump);
+        }
+
+        Ok(Reader {
+            stm,
+            lumps,
+            wad_type,
+        })
+    }
+}
+
+impl crate::reader::Reader for Reader {
+    fn file_count(&self) -> usize {
+        self.lumps.len()
+    }
+
+    fn get_file(&self, index: usize) -> crate::reader::File {
+        let lump = self
+            .lumps
+            .get(index)
+            .expect("`index` should be within boundaries");
+
+        crate::reader::File {
+            name: PathBuf::from(lump_file_name(index, self.lumps.len(), &lump.name)),
+            file_type: FileType::RegularFile,
+            size: Some(u64::from(lump.size)),
+        }
+    }
+
+    fn create_file_reader<'a>(
+        &'a mut self,
+        index: usize,
+    ) -> crate::reader::Result<Box<dyn io::Read + 'a>> {
+        let lump = self
+            .lumps
+            .get(index)
+            .expect("`index` should be within boundaries");
+
+        if lump.size == 0 {
+            return Ok(Box::new(io::empty()));
+        }
+
+        self.stm
+            .seek(SeekFrom::Start(u64::from(lump.offset)))
+            .map_err(crate::reader::Error::ReadingInputFile)?;
+
+        Ok(Box::new(self.stm.by_ref().take(u64::from(lump.size))))
+    }
+
+    fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("type"), self.wad_type.name().to_string());
+        attrs
+    }
+}
+
+pub fn make_reader(
+    path: &Path,
+    _options: crate::reader::Options,
+) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
+    Ok(Box::new(Reader::open(path)?))
+}
+
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let mut stm = BufReader::new(file);
+
+    let signature = stm.read_u8_vec(4)?;
+
+    let Some(wad_type) = WadType::from_signature(&signature) else {
+        return Ok(crate::Probe::Invalid(String::from("invalid signature")));
+    };
+
+    let hdr = Header::read(&mut stm)?;
+    let directory_size = u64::from(hdr.lump_count) * ENTRY_SIZE as u64;
+
+    if (hdr.lump_count > 0 && hdr.directory_offset < HEADER_SIZE)
+        || u64::from(hdr.directory_offset) + directory_size > file_size
+    {
+        return Ok(crate::Probe::Invalid(format!(
+            "directory of {} lumps at {} is out of bounds",
+            hdr.lump_count, hdr.directory_offset
+        )));
+    }
+
+    Ok(crate::Probe::Valid(format!(
+        "{} of {} lumps",
+        wad_type.name().to_uppercase(),
+        hdr.lump_count
+    )))
+}
@@ -0,0 +1,43 @@
+use std::io::{BufRead, Result};
+
+use encoding_rs::WINDOWS_1252;
+
+use super::common::NAME_SIZE;
+use crate::ReadEx;
+
+pub struct Header {
+    pub lump_count: u32,
+    pub directory_offset: u32,
+}
+
+impl Header {
+    #[inline]
+    pub fn read(r: &mut impl BufRead) -> Result<Self> {
+        Ok(Self {
+            lump_count: r.read_u32_le()?,
+            directory_offset: r.read_u32_le()?,
+        })
+    }
+}
+
+pub struct Lump {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+impl Lump {
+    #[inline]
+    pub fn read(r: &mut impl BufRead) -> Result<Self> {
+        let offset = r.read_u32_le()?;
+        let size = r.read_u32_le()?;
+        let name_buf = r.read_u8_vec(NAME_SIZE)?;
+        let len = name_buf.iter().position(|&c| c == 0).unwrap_or(NAME_SIZE);
+        let name = WINDOWS_1252
+            .decode_without_bom_handling(&name_buf[..len])
+            .0
+            .into_owned();
+
+        Ok(Self { name, offset, size })
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+use std::path::Path;
+
+#[cfg(test)]
+fn read_lumps(path: &Path, strict: bool) -> crate::reader::Result<Vec<(String, Vec<u8>)>> {
+    use std::io::Read;
+
+    let options = crate::reader::Options {
+        strict,
+        ..Default::default()
+    };
+    let mut rdr = super::make_reader(path, options)?;
+    let mut lumps = Vec::new();
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)?
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(file.size, Some(data.len() as u64));
+        lumps.push((file.name.to_str().unwrap().to_string(), data));
+    }
+
+    Ok(lumps)
+}
+
+#[test]
+fn lumps() {
+    let lumps = read_lumps(Path::new("./samples/wad/doom.wad"), true).unwrap();
+    let names: Vec<_> = lumps.iter().map(|(name, _)| name.as_str()).collect();
+
+    // duplicates and markers are kept in the directory order
+    assert_eq!(
+        names,
+        [
+            "0000_PLAYPAL.lmp",
+            "0001_E1M1.lmp",
+            "0002_THINGS.lmp",
+            "0003_LINEDEFS.lmp",
+            "0004_E1M2.lmp",
+            "0005_THINGS.lmp",
+            "0006_LINEDEFS.lmp",
+            "0007_F_START.lmp",
+            "0008_FLOOR0_1.lmp",
+            "0009_F_END.lmp",
+            "0010_DSPISTOL.lmp",
+        ]
+    );
+    assert_eq!(lumps[2].1, vec![1; 10]);
+    assert_eq!(lumps[5].1, vec![3; 20]);
+    assert!(lumps[7].1.is_empty());
+    assert_eq!(lumps[10].1, [0x03, 0x00, 0x11, 0x2b]);
+}
+
+#[test]
+fn wad_type() {
+    let rdr = super::make_reader(Path::new("./samples/wad/doom.wad"), Default::default()).unwrap();
+
+    assert_eq!(rdr.attrs()["type"], "iwad");
+}
+
+#[test]
+fn markers_with_any_offset() {
+    let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+    let path = dir.path().join("markers.wad");
+
+    // zero-length lumps at offset 0, some tools write them this way
+    let mut data = b"PWAD".to_vec();
+    data.extend(2u32.to_le_bytes());
+    data.extend(16u32.to_le_bytes());
+    data.extend(b"DATA");
+    data.extend(0u32.to_le_bytes());
+    data.extend(0u32.to_le_bytes());
+    data.extend(b"S_START\0");
+    data.extend(12u32.to_le_bytes());
+    data.extend(4u32.to_le_bytes());
+    data.extend(b"MYLUMP\0\0");
+    std::fs::write(&path, data).unwrap();
+
+    assert_eq!(
+        read_lumps(&path, true).unwrap(),
+        [
+            (String::from("0000_S_START.lmp"), Vec::new()),
+            (String::from("0001_MYLUMP.lmp"), b"DATA".to_vec()),
+        ]
+    );
+}
+
+#[rstest::rstest]
+#[case(false)]
+#[case(true)]
+fn lump_out_of_bounds(#[case] strict: bool) {
+    let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+    let path = dir.path().join("invalid.wad");
+
+    let mut data = b"PWAD".to_vec();
+    data.extend(1u32.to_le_bytes());
+    data.extend(12u32.to_le_bytes());
+    data.extend(100u32.to_le_bytes());
+    data.extend(4u32.to_le_bytes());
+    data.extend(b"MYLUMP\0\0");
+    std::fs::write(&path, data).unwrap();
+
+    assert!(matches!(
+        read_lumps(&path, strict),
+        Err(crate::reader::Error::InvalidHeader(_))
+    ));
+}
+
+#[rstest::rstest]
+#[case("./samples/wad/doom.wad", true)]
+#[case("./samples/pak/correct.pak", false)]
+fn probe(#[case] path: &str, #[case] valid: bool) {
+    let res = super::probe(Path::new(path)).unwrap();
+
+    assert_eq!(matches!(res, crate::Probe::Valid(_)), valid);
+}
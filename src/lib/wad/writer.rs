@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{io_error, writer, FileType, InputFileList, Overhead, PathBufUtils, WriteEx};
+
+use super::common::{lump_name, WadType, ENTRY_SIZE, HEADER_SIZE, NAME_SIZE};
+
+/// Estimate for `max-size`: directory records with fixed-size names.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: HEADER_SIZE as u64,
+    entry_size: ENTRY_SIZE as u64,
+    name_copies: 0,
+};
+
+struct Options {
+    wad_type: WadType,
+}
+
+/// Lumps are written in the order of input files, extracted lumps are numbered to keep it.
+pub fn create_archive(
+    input_files: InputFileList,
+    path: &Path,
+    params: &HashMap<String, String>,
+) -> writer::Result<()> {
+    let options = parse_options(params)?;
+    let mut out = fs::File::create(path).map_err(writer::Error::CreatingOutputFile)?;
+
+    out.write_all(options.wad_type.signature())
+        .map_err(writer::Error::WritingHeader)?;
+
+    // header placeholder
+    out.seek(SeekFrom::Start(u64::from(HEADER_SIZE)))
+        .map_err(writer::Error::WritingHeader)?;
+
+    let input_files: Vec<_> = input_files
+        .iter()
+        .filter(|f| f.file_type == FileType::RegularFile)
+        .collect();
+    let mut directory = Vec::with_capacity(input_files.len() * ENTRY_SIZE);
+
+    for input_file in &input_files {
+        let path = input_file
+            .dst_path
+            .try_to_unix()
+            .map_err(|err| writer::Error::InvalidInputFileName(input_file.dst_path.clone(), err))?;
+
+        if path.contains('/') {
+            return Err(writer::Error::InvalidInputFileName(
+                input_file.dst_path.clone(),
+                io_error!(InvalidInput, "lumps cannot be in folders"),
+            ));
+        }
+
+        // the engine looks lumps up by upper case names
+        let name = lump_name(&path, input_files.len()).to_ascii_uppercase();
+
+        if !name.is_ascii() {
+            return Err(writer::Error::InputFileNotAscii(path));
+        }
+
+        if name.len() > NAME_SIZE {
+            return Err(writer::Error::InputFileNameTooLong(path, NAME_SIZE));
+        }
+
+        let offset = out
+            .stream_position()
+            .map_err(|err| writer::Error::ArchivingInputFile(input_file.src_path.clone(), err))?;
+        let offset = u32::try_from(offset).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+
+        let mut file = fs::File::open(&input_file.src_path)
+            .map_err(|err| writer::Error::OpeningInputFile(input_file.src_path.clone(), err))?;
+        let size = io::copy(&mut file, &mut out)
+            .map_err(|err| writer::Error::ArchivingInputFile(input_file.src_path.clone(), err))?;
+        let size = u32::try_from(size)
+            .map_err(|_| writer::Error::InputFileLarger4GiB(input_file.src_path.clone()))?;
+
+        directory
+            .write_u32_le(offset)
+            .expect("writing to memory buffer");
+        directory
+            .write_u32_le(size)
+            .expect("writing to memory buffer");
+        directory
+            .write_all(format!("{name:\0<NAME_SIZE$}").as_bytes())
+            .expect("writing to memory buffer");
+    }
+
+    let directory_offset = out
+        .stream_position()
+        .map_err(writer::Error::WritingFileIndex)?;
+    let directory_offset =
+        u32::try_from(directory_offset).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+    let lump_count =
+        u32::try_from(input_files.len()).map_err(|_| writer::Error::OutputFileLarger4GiB)?;
+
+    out.write_all(&directory)
+        .map_err(writer::Error::WritingFileIndex)?;
+
+    // write real header
+    out.seek(SeekFrom::Start(4))
+        .map_err(writer::Error::WritingHeader)?;
+    out.write_u32_le(lump_count)
+        .map_err(writer::Error::WritingHeader)?;
+    out.write_u32_le(directory_offset)
+        .map_err(writer::Error::WritingHeader)?;
+
+    Ok(())
+}
+
+fn parse_options(params: &HashMap<String, String>) -> writer::Result<Options> {
+    let wad_type = match params.get("type") {
+        None => WadType::Pwad,
+        Some(value) => WadType::from_name(value).ok_or_else(|| {
+            writer::Error::InvalidParameter(
+                "type",
+                format!("expected 'iwad' or 'pwad', got '{value}'"),
+            )
+        })?,
+    };
+
+    Ok(Options { wad_type })
+}
@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fs, io::Read, path::Path};
+
+use rstest::rstest;
+use tempdir::TempDir;
+
+use crate::InputFileListBuilder;
+
+/// Extracts lumps the way `extract` command does.
+fn extract(path: &Path, dir: &Path) {
+    let mut rdr = super::make_reader(path, Default::default()).unwrap();
+
+    for index in 0..rdr.file_count() {
+        let file = rdr.get_file(index);
+        let mut data = Vec::new();
+        rdr.create_file_reader(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        fs::write(dir.join(file.name), data).unwrap();
+    }
+}
+
+#[test]
+fn round_trip() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let lumps_dir = dir.path().join("lumps");
+    fs::create_dir(&lumps_dir).unwrap();
+    extract(Path::new("./samples/wad/doom.wad"), &lumps_dir);
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&lumps_dir)
+        .unwrap()
+        .build();
+    let output_path = dir.path().join("doom.wad");
+    let params = HashMap::from([(String::from("type"), String::from("iwad"))]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    assert_eq!(
+        fs::read(&output_path).unwrap(),
+        fs::read("./samples/wad/doom.wad").unwrap()
+    );
+}
+
+#[test]
+fn plain_names() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let lumps_dir = dir.path().join("lumps");
+    fs::create_dir(&lumps_dir).unwrap();
+    fs::write(lumps_dir.join("DEHACKED"), b"Patch File for DeHackEd").unwrap();
+    fs::write(lumps_dir.join("MAPINFO.lmp"), b"map MAP01").unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&lumps_dir)
+        .unwrap()
+        .build();
+    let output_path = dir.path().join("mod.wad");
+    super::create_archive(input_files, &output_path, &HashMap::new()).unwrap();
+
+    let rdr = super::make_reader(&output_path, Default::default()).unwrap();
+    let names: Vec<_> = (0..rdr.file_count())
+        .map(|index| rdr.get_file(index).name)
+        .collect();
+
+    assert_eq!(rdr.attrs()["type"], "pwad");
+    assert_eq!(
+        names,
+        [
+            Path::new("0000_DEHACKED.lmp"),
+            Path::new("0001_MAPINFO.lmp")
+        ]
+    );
+}
+
+#[test]
+fn lower_case_names() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let lumps_dir = dir.path().join("lumps");
+    fs::create_dir(&lumps_dir).unwrap();
+    fs::write(lumps_dir.join("0001_things.lmp"), b"things").unwrap();
+    fs::write(lumps_dir.join("dehacked"), b"Patch File for DeHackEd").unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&lumps_dir)
+        .unwrap()
+        .build();
+    let output_path = dir.path().join("mod.wad");
+    super::create_archive(input_files, &output_path, &HashMap::new()).unwrap();
+
+    let rdr = super::make_reader(&output_path, Default::default()).unwrap();
+    let names: Vec<_> = (0..rdr.file_count())
+        .map(|index| rdr.get_file(index).name)
+        .collect();
+
+    assert_eq!(
+        names,
+        [Path::new("0000_THINGS.lmp"), Path::new("0001_DEHACKED.lmp")]
+    );
+}
+
+#[test]
+fn numbers_in_names() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let lumps_dir = dir.path().join("lumps");
+    fs::create_dir(&lumps_dir).unwrap();
+    fs::write(lumps_dir.join("0000_E1M1.lmp"), b"").unwrap();
+    // not a prefix of an extracted lump, which has 4 digits
+    fs::write(lumps_dir.join("1_INTRO.lmp"), b"intro").unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&lumps_dir)
+        .unwrap()
+        .build();
+    let output_path = dir.path().join("mod.wad");
+    super::create_archive(input_files, &output_path, &HashMap::new()).unwrap();
+
+    let rdr = super::make_reader(&output_path, Default::default()).unwrap();
+    let names: Vec<_> = (0..rdr.file_count())
+        .map(|index| rdr.get_file(index).name)
+        .collect();
+
+    assert_eq!(
+        names,
+        [Path::new("0000_E1M1.lmp"), Path::new("0001_1_INTRO.lmp")]
+    );
+}
+
+#[rstest]
+#[case("0000_TOOLONGNAME.lmp")]
+#[case("dir/THINGS")]
+fn invalid_lump_name(#[case] name: &str) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let lumps_dir = dir.path().join("lumps");
+    let path = lumps_dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, b"data").unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&lumps_dir)
+        .unwrap()
+        .build();
+    let res = super::create_archive(input_files, &dir.path().join("mod.wad"), &HashMap::new());
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn invalid_type() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([(String::from("type"), String::from("wad2"))]);
+    let res = super::create_archive(Vec::new(), &dir.path().join("mod.wad"), &params);
+
+    assert!(matches!(
+        res,
+        Err(crate::writer::Error::InvalidParameter("type", _))
+    ));
+}
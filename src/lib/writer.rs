@@ -1,4 +1,4 @@
-use std::{io, path::PathBuf, result};
+use std::{collections::HashMap, io, path::PathBuf, result, str::FromStr};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -51,6 +51,9 @@ pub enum Error {
         hash: String,
     },
 
+    #[error("failed to archive file '{0}': file size is larger than max-size {1}")]
+    InputFileLargerMaxSize(PathBuf, u64),
+
     #[error("total size of files cannot be larger than 4GiB")]
     TotalInputLarger4GiB,
 
@@ -65,3 +68,123 @@ pub enum Error {
 }
 
 pub type Result<T> = result::Result<T, Error>;
+
+/// Compression level requested with the `level` writer option, read by `bsa`, `ba2`, `rpa` and `zip`.
+///
+/// Accepts `fast`, `default`, `best` or a number from 0 to 9. Every codec maps it on its own scale:
+/// - zlib/deflate (`bsa` v103/v104, `ba2`, `rpa` index, `zip` deflate): `fast` is 1, `default` is 6,
+///   `best` is 9, numbers are used as is.
+/// - bzip2 (`zip`): same as zlib.
+/// - zstd (`zip`): `fast` is 1, `default` is 3, `best` is 19. Numbers are used as is, so they only
+///   reach levels 1-9 of zstd's 1-22 scale, `best` is the only way to get a higher one.
+/// - LZ4 frame (`bsa` v105): there is only one compressor, so the level selects the frame layout.
+///   Levels below 7 (including `fast` and `default`) use independent 64 KiB blocks,
+///   7 and above (including `best`) use linked 4 MiB blocks, which compress better.
+/// - LZ4 block (`ba2` with `compression=lz4`): the level is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionLevel {
+    Fast,
+    Default,
+    Best,
+    Numeric(u32),
+}
+
+impl CompressionLevel {
+    pub const MAX: u32 = 9;
+
+    /// Reads the `level` option. Returns `None` if the option is not set.
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Option<Self>> {
+        params.get("level").map(|s| s.parse()).transpose()
+    }
+
+    /// Level on zlib's 0-9 scale.
+    pub fn numeric(self) -> u32 {
+        match self {
+            Self::Fast => 1,
+            Self::Default => 6,
+            Self::Best => Self::MAX,
+            Self::Numeric(level) => level,
+        }
+    }
+
+    pub fn to_flate2(self) -> flate2::Compression {
+        flate2::Compression::new(self.numeric())
+    }
+
+    /// zstd has its own 1-22 scale, numeric levels are used as is and stay within 1-9.
+    pub fn to_zstd(self) -> i32 {
+        match self {
+            Self::Fast => 1,
+            Self::Default => 3,
+            Self::Best => 19,
+            Self::Numeric(level) => level as i32,
+        }
+    }
+
+    pub fn to_lz4_frame_info(self) -> lz4_flex::frame::FrameInfo {
+        let mut frame_info = lz4_flex::frame::FrameInfo::new();
+
+        if self.numeric() >= 7 {
+            frame_info.block_size = lz4_flex::frame::BlockSize::Max4MB;
+            frame_info.block_mode = lz4_flex::frame::BlockMode::Linked;
+        }
+
+        frame_info
+    }
+}
+
+impl FromStr for CompressionLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fast" => Ok(Self::Fast),
+            "default" => Ok(Self::Default),
+            "best" => Ok(Self::Best),
+            _ => match s.parse::<u32>() {
+                Ok(level) if level <= Self::MAX => Ok(Self::Numeric(level)),
+                _ => Err(Error::InvalidParameter(
+                    "level",
+                    format!("expected fast, default, best or a number from 0 to 9, got '{s}'"),
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::CompressionLevel;
+
+    #[test]
+    fn compression_level_from_params() {
+        let mut params = HashMap::new();
+        assert_eq!(CompressionLevel::from_params(&params).unwrap(), None);
+
+        params.insert(String::from("level"), String::from("fast"));
+        assert_eq!(
+            CompressionLevel::from_params(&params).unwrap(),
+            Some(CompressionLevel::Fast),
+        );
+
+        params.insert(String::from("level"), String::from("3"));
+        assert_eq!(
+            CompressionLevel::from_params(&params).unwrap(),
+            Some(CompressionLevel::Numeric(3)),
+        );
+    }
+
+    #[test]
+    fn invalid_compression_level() {
+        let mut params = HashMap::new();
+        params.insert(String::from("level"), String::from("10"));
+
+        let err = CompressionLevel::from_params(&params).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid option `level`: expected fast, default, best or a number from 0 to 9, got '10'"
+        );
+    }
+}
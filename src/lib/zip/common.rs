@@ -0,0 +1,50 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use time::OffsetDateTime;
+
+/// Extra field holding WinZip AES encryption parameters.
+pub const AES_EXTRA_FIELD_ID: u16 = 0x9901;
+/// Info-ZIP extended timestamp extra field, holds modification time as UTC Unix time.
+pub const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+/// Flag of the extended timestamp marking that modification time is present.
+pub const EXTENDED_TIMESTAMP_MTIME: u8 = 1;
+
+// sizes of records, extra fields with their headers
+pub const LOCAL_HEADER_SIZE: u64 = 30;
+pub const CENTRAL_HEADER_SIZE: u64 = 46;
+pub const END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 56;
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE: u64 = 20;
+/// Sizes in a local header.
+pub const ZIP64_LOCAL_EXTRA_FIELD_SIZE: u64 = 20;
+/// Sizes and offset in a central header.
+pub const ZIP64_CENTRAL_EXTRA_FIELD_SIZE: u64 = 28;
+pub const ZIP64_DATA_DESCRIPTOR_SIZE: u64 = 24;
+/// Modification time only.
+pub const EXTENDED_TIMESTAMP_SIZE: u64 = 9;
+pub const AES_EXTRA_FIELD_SIZE: u64 = 11;
+/// Salt, password verifier and authentication code of AES-256 encrypted data.
+pub const AES_256_DATA_OVERHEAD: u64 = 28;
+
+/// MS-DOS date and time are local time by convention, but they are treated as UTC, so archives do
+/// not depend on the time zone of the machine. The extended timestamp is written along and is
+/// preferred when reading. Falls back to 1980-01-01 if the time is out of MS-DOS range.
+pub fn to_dos_time(time: SystemTime) -> zip::DateTime {
+    OffsetDateTime::from(time).try_into().unwrap_or_default()
+}
+
+/// Reads MS-DOS date and time as UTC, entries without extended timestamp made in other time zones
+/// are shifted by their UTC offset.
+pub fn from_dos_time(time: zip::DateTime) -> Option<SystemTime> {
+    OffsetDateTime::try_from(time).ok().map(SystemTime::from)
+}
+
+/// Unix time as stored in the extended timestamp, `None` if it does not fit.
+pub fn to_unix_time(time: SystemTime) -> Option<u32> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    u32::try_from(secs).ok()
+}
+
+pub fn from_unix_time(secs: u32) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(u64::from(secs))
+}
@@ -1,5 +1,7 @@
 // https://en.wikipedia.org/wiki/ZIP_(file_format)
 
+mod common;
+mod name_encoding;
 mod reader;
 #[cfg(test)]
 mod reader_tests;
@@ -7,5 +9,5 @@ mod writer;
 #[cfg(test)]
 mod writer_tests;
 
-pub use reader::make_reader;
-pub use writer::create_archive;
+pub use reader::{make_reader, probe};
+pub use writer::{create_archive, SPLIT_OVERHEAD};
@@ -0,0 +1,145 @@
+use encoding_rs::{Encoding, IBM866, SHIFT_JIS, UTF_8};
+
+/// Upper half of code page 437, the original IBM PC character set. ZIP names are in CP437 unless
+/// the UTF-8 flag is set, but many archivers just use the system code page.
+const CP437: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+    '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+#[derive(Clone, Copy)]
+pub enum NameEncoding {
+    Cp437,
+    Other(&'static Encoding),
+}
+
+impl NameEncoding {
+    /// Candidates of auto-detection besides UTF-8, ties are resolved in favour of earlier ones.
+    const AUTO_CANDIDATES: [NameEncoding; 3] = [
+        NameEncoding::Other(SHIFT_JIS),
+        NameEncoding::Cp437,
+        NameEncoding::Other(IBM866),
+    ];
+
+    /// `cp437` or any WHATWG encoding label, like `shift_jis`, `cp866` or `windows-1251`.
+    pub fn from_label(label: &str) -> Option<Self> {
+        if label.eq_ignore_ascii_case("cp437") || label.eq_ignore_ascii_case("ibm437") {
+            return Some(Self::Cp437);
+        }
+
+        Encoding::for_label(label.as_bytes()).map(Self::Other)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cp437 => "IBM437",
+            Self::Other(encoding) => encoding.name(),
+        }
+    }
+
+    /// Replaces invalid sequences with U+FFFD.
+    pub fn decode(self, raw: &[u8]) -> String {
+        match self {
+            Self::Cp437 => raw
+                .iter()
+                .map(|&byte| match byte {
+                    0..=0x7F => char::from(byte),
+                    _ => CP437[usize::from(byte - 0x80)],
+                })
+                .collect(),
+            Self::Other(encoding) => encoding.decode_without_bom_handling(raw).0.into_owned(),
+        }
+    }
+
+    fn decode_strict(self, raw: &[u8]) -> Option<String> {
+        match self {
+            Self::Cp437 => Some(self.decode(raw)),
+            Self::Other(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(raw)
+                .map(String::from),
+        }
+    }
+
+    /// Names are in UTF-8 if they all are valid UTF-8. Otherwise picks the encoding under which
+    /// names decode without errors into the most letters of a single script, rather than box
+    /// drawing characters and symbols.
+    pub fn detect<'a>(names: impl Iterator<Item = &'a [u8]> + Clone) -> Self {
+        if names.clone().all(|name| std::str::from_utf8(name).is_ok()) {
+            return Self::Other(UTF_8);
+        }
+
+        let mut best = (Self::Cp437, f64::MIN);
+
+        for candidate in Self::AUTO_CANDIDATES {
+            let mut score = 0.0;
+            let mut count = 0;
+
+            for name in names.clone() {
+                let Some(name) = candidate.decode_strict(name) else {
+                    score = f64::MIN;
+                    break;
+                };
+
+                let (name_score, name_count) = plausibility(&name);
+                score += name_score;
+                count += name_count;
+            }
+
+            let score = if count == 0 {
+                score
+            } else {
+                score / count as f64
+            };
+
+            if score > best.1 {
+                best = (candidate, score);
+            }
+        }
+
+        best.0
+    }
+}
+
+/// Sum of weights of non-ASCII characters and their number.
+fn plausibility(name: &str) -> (f64, usize) {
+    let chars: Vec<char> = name.chars().collect();
+    let mut score = 0.0;
+    let mut count = 0;
+
+    for (index, &ch) in chars.iter().enumerate() {
+        if ch.is_ascii() {
+            continue;
+        }
+
+        count += 1;
+        score += match ch {
+            // a Cyrillic letter right next to a Latin one is most likely an accented Latin letter
+            '\u{0400}'..='\u{04FF}'
+                if index
+                    .checked_sub(1)
+                    .and_then(|prev| chars.get(prev))
+                    .into_iter()
+                    .chain(chars.get(index + 1))
+                    .any(char::is_ascii_alphabetic) =>
+            {
+                0.0
+            }
+            // Latin-1 letters except multiplication and division signs, Latin Extended-A
+            '\u{00C0}'..='\u{00D6}' | '\u{00D8}'..='\u{00F6}' | '\u{00F8}'..='\u{017F}' => 1.0,
+            // Cyrillic
+            '\u{0400}'..='\u{04FF}' => 1.0,
+            // CJK punctuation, hiragana, katakana, CJK ideographs, fullwidth forms
+            '\u{3000}'..='\u{30FF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF01}'..='\u{FF5E}' => 1.0,
+            // halfwidth katakana, rarely used, but random bytes decode into it
+            '\u{FF61}'..='\u{FF9F}' => 0.5,
+            _ => 0.0,
+        };
+    }
+
+    (score, count)
+}
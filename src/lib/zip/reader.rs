@@ -1,27 +1,60 @@
 use std::{
-    fs, io,
+    collections::HashMap,
+    fs,
+    io::{self, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
 };
 
+use super::{
+    common::{from_dos_time, from_unix_time, AES_EXTRA_FIELD_ID},
+    name_encoding::NameEncoding,
+};
 use crate::{FileType, PathBufUtils};
-use zip::ZipArchive;
+use zip::{extra_fields::ExtraField, read::ZipFile, result::ZipError, HasZipMetadata, ZipArchive};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Encryption {
+    None,
+    ZipCrypto,
+    Aes,
+}
 
 pub struct Reader {
     zip: ZipArchive<fs::File>,
     files: Vec<crate::reader::File>,
+    encryption: Vec<Encryption>,
+    metadata: Vec<crate::reader::FileMetadata>,
+    password: Option<String>,
+    /// Encoding of names without UTF-8 flag, set if there are non-ASCII ones.
+    name_encoding: Option<NameEncoding>,
 }
 
 impl Reader {
-    fn open(path: &Path, _options: crate::reader::Options) -> crate::reader::Result<Self> {
+    fn open(path: &Path, options: crate::reader::Options) -> crate::reader::Result<Self> {
+        let name_encoding = match options.params.get("name-encoding").map(String::as_str) {
+            None | Some("auto") => None,
+            Some(label) => Some(NameEncoding::from_label(label).ok_or_else(|| {
+                crate::reader::Error::InvalidParameter(
+                    "name-encoding",
+                    format!("expected 'auto', 'cp437' or an encoding label, got '{label}'"),
+                )
+            })?),
+        };
+
         let file = fs::File::open(path).map_err(crate::reader::Error::OpeningInputFile)?;
         let mut zip =
             ZipArchive::new(file).map_err(|err| crate::reader::Error::Other(err.to_string()))?;
 
         let mut files = Vec::new();
+        let mut encryption = Vec::new();
+        let mut metadata = Vec::new();
+        // indices and raw names of files with non-ASCII names without UTF-8 flag
+        let mut legacy_names = Vec::new();
 
         for index in 0..zip.len() {
+            // raw access does not require a password for encrypted files
             let file = zip
-                .by_index(index)
+                .by_index_raw(index)
                 .map_err(|err| crate::reader::Error::Other(err.to_string()))?;
 
             let file_type = if file.is_dir() {
@@ -35,9 +68,43 @@ impl Reader {
                 file_type,
                 size: Some(file.size()),
             });
+
+            encryption.push(if !file.encrypted() {
+                Encryption::None
+            } else if has_extra_field(file.extra_data().unwrap_or_default(), AES_EXTRA_FIELD_ID) {
+                Encryption::Aes
+            } else {
+                Encryption::ZipCrypto
+            });
+
+            metadata.push(read_metadata(&file));
+
+            if !file.get_metadata().is_utf8 && !file.name_raw().is_ascii() {
+                legacy_names.push((index, file.name_raw().to_vec()));
+            }
+        }
+
+        let name_encoding = match legacy_names.is_empty() {
+            true => None,
+            false => Some(name_encoding.unwrap_or_else(|| {
+                NameEncoding::detect(legacy_names.iter().map(|(_, name)| name.as_slice()))
+            })),
+        };
+
+        if let Some(name_encoding) = name_encoding {
+            for (index, name) in legacy_names {
+                files[index].name = PathBuf::from_unix(&name_encoding.decode(&name));
+            }
         }
 
-        Ok(Self { zip, files })
+        Ok(Self {
+            zip,
+            files,
+            encryption,
+            metadata,
+            password: options.params.get("password").cloned(),
+            name_encoding,
+        })
     }
 }
 
@@ -63,13 +130,84 @@ impl crate::reader::Reader for Reader {
         &'a mut self,
         index: usize,
     ) -> crate::reader::Result<Box<dyn io::Read + 'a>> {
-        let file = self
-            .zip
-            .by_index(index)
-            .expect("should be able to get file by index");
+        let encrypted = self.encryption[index] != Encryption::None;
+
+        let file = match (&self.password, encrypted) {
+            (Some(password), true) => self.zip.by_index_decrypt(index, password.as_bytes()),
+            (None, true) => {
+                return Err(crate::reader::Error::Other(String::from(
+                    "file is encrypted, use `password` option to decrypt it",
+                )))
+            }
+            (_, false) => self.zip.by_index(index),
+        }
+        .map_err(|err| match err {
+            ZipError::InvalidPassword => {
+                crate::reader::Error::Other(String::from("invalid password"))
+            }
+            ZipError::UnsupportedArchive(msg) => crate::reader::Error::Unsupported(msg.to_string()),
+            err => crate::reader::Error::Other(err.to_string()),
+        })?;
 
         Ok(Box::new(file))
     }
+
+    fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+
+        if let Some(name_encoding) = self.name_encoding {
+            attrs.insert(
+                String::from("name-encoding"),
+                name_encoding.name().to_string(),
+            );
+        }
+
+        attrs
+    }
+
+    fn file_attrs(&self, index: usize) -> Vec<(&'static str, String)> {
+        match self.encryption[index] {
+            Encryption::None => Vec::new(),
+            Encryption::ZipCrypto => vec![("encryption", String::from("zipcrypto"))],
+            Encryption::Aes => vec![("encryption", String::from("aes"))],
+        }
+    }
+
+    fn file_metadata(&self, index: usize) -> crate::reader::FileMetadata {
+        self.metadata[index].clone()
+    }
+}
+
+/// Prefers UTC modification time of the extended timestamp over MS-DOS time.
+fn read_metadata<R: Read>(file: &ZipFile<R>) -> crate::reader::FileMetadata {
+    let extended_mtime = file.extra_data_fields().find_map(|field| match field {
+        ExtraField::ExtendedTimestamp(timestamp) => timestamp.mod_time(),
+        _ => None,
+    });
+
+    crate::reader::FileMetadata {
+        modified: match extended_mtime {
+            Some(secs) => Some(from_unix_time(secs)),
+            None => file.last_modified().and_then(from_dos_time),
+        },
+        mode: file.unix_mode().map(|mode| mode & 0o777),
+    }
+}
+
+/// Checks whether `id` is among the `id, size, data` records of an extra field.
+fn has_extra_field(mut extra: &[u8], id: u16) -> bool {
+    while extra.len() >= 4 {
+        let field_id = u16::from_le_bytes([extra[0], extra[1]]);
+        let size = usize::from(u16::from_le_bytes([extra[2], extra[3]]));
+
+        if field_id == id {
+            return true;
+        }
+
+        extra = extra.get(4 + size..).unwrap_or_default();
+    }
+
+    false
 }
 
 pub fn make_reader(
@@ -78,3 +216,59 @@ pub fn make_reader(
 ) -> crate::reader::Result<Box<dyn crate::reader::Reader>> {
     Ok(Box::new(Reader::open(path, options)?))
 }
+
+/// End of central directory record signature.
+const EOCD_SIGNATURE: &[u8; 4] = b"PK\x05\x06";
+const EOCD_SIZE: u64 = 22;
+/// ZIP64 end of central directory locator signature, the locator precedes the end of central directory record.
+const ZIP64_LOCATOR_SIGNATURE: &[u8; 4] = b"PK\x06\x07";
+const ZIP64_LOCATOR_SIZE: usize = 20;
+
+/// Looks for the end of central directory record, which is followed by a comment of up to 64 KiB.
+/// Archives may have data prepended, like self-extracting ones.
+pub fn probe(path: &Path) -> io::Result<crate::Probe> {
+    let mut file = fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let tail_size = file_size.min(EOCD_SIZE + u64::from(u16::MAX));
+
+    file.seek(SeekFrom::Start(file_size - tail_size))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+
+    let Some(pos) = tail
+        .windows(EOCD_SIGNATURE.len())
+        .rposition(|window| window == EOCD_SIGNATURE)
+        .filter(|&pos| (tail.len() - pos) as u64 >= EOCD_SIZE)
+    else {
+        return Ok(crate::Probe::Invalid(String::from(
+            "end of central directory not found",
+        )));
+    };
+
+    let eocd = &tail[pos..];
+    let eocd_offset = file_size - tail_size + pos as u64;
+    let cd_size = u64::from(u32::from_le_bytes(eocd[12..16].try_into().unwrap()));
+    let cd_offset = u32::from_le_bytes(eocd[16..20].try_into().unwrap());
+
+    let has_zip64_locator = pos
+        .checked_sub(ZIP64_LOCATOR_SIZE)
+        .is_some_and(|locator_pos| tail[locator_pos..].starts_with(ZIP64_LOCATOR_SIGNATURE));
+
+    // sizes and offsets are in ZIP64 end of central directory record
+    if has_zip64_locator || cd_offset == u32::MAX {
+        return Ok(crate::Probe::Valid(format!(
+            "end of central directory at {eocd_offset}, ZIP64"
+        )));
+    }
+
+    let Some(prepended) = eocd_offset.checked_sub(cd_size + u64::from(cd_offset)) else {
+        return Ok(crate::Probe::Invalid(String::from(
+            "central directory is out of bounds",
+        )));
+    };
+
+    Ok(crate::Probe::Valid(match prepended {
+        0 => format!("end of central directory at {eocd_offset}"),
+        _ => format!("end of central directory at {eocd_offset}, {prepended} bytes prepended"),
+    }))
+}
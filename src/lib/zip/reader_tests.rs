@@ -5,9 +5,175 @@ use std::path::Path;
 fn new_reader() {
     let res = super::make_reader(
         Path::new("./samples/zip/correct.zip"),
-        crate::reader::Options { strict: false },
+        crate::reader::Options {
+            strict: false,
+            ..Default::default()
+        },
     );
 
     let rdr = res.ok().unwrap();
     assert_eq!(rdr.file_count(), 8);
 }
+
+#[cfg(test)]
+fn open_encrypted(password: Option<&str>) -> Box<dyn crate::reader::Reader> {
+    let mut params = std::collections::HashMap::new();
+
+    if let Some(password) = password {
+        params.insert(String::from("password"), String::from(password));
+    }
+
+    super::make_reader(
+        Path::new("./samples/zip/zipcrypto.zip"),
+        crate::reader::Options {
+            params,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn zipcrypto() {
+    let mut rdr = open_encrypted(Some("secret"));
+    assert_eq!(rdr.file_count(), 2);
+    assert_eq!(
+        rdr.file_attrs(0),
+        vec![("encryption", String::from("zipcrypto"))]
+    );
+    // no extended timestamp, MS-DOS time is used
+    assert_eq!(
+        rdr.file_metadata(0),
+        crate::reader::FileMetadata {
+            modified: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1792346480)),
+            mode: Some(0o644),
+        }
+    );
+
+    let mut data = String::new();
+    std::io::Read::read_to_string(&mut rdr.create_file_reader(0).unwrap(), &mut data).unwrap();
+    assert_eq!(data, "Hello, world!\n");
+}
+
+#[test]
+fn zipcrypto_invalid_password() {
+    let mut rdr = open_encrypted(Some("wrong"));
+    let err = rdr.create_file_reader(0).err().unwrap();
+    assert_eq!(err.to_string(), "invalid password");
+}
+
+#[test]
+fn zipcrypto_no_password() {
+    let mut rdr = open_encrypted(None);
+    assert_eq!(rdr.file_count(), 2);
+
+    let err = rdr.create_file_reader(0).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "file is encrypted, use `password` option to decrypt it"
+    );
+}
+
+#[test]
+fn zip64_size() {
+    let rdr = super::make_reader(Path::new("./samples/zip/zip64.zip"), Default::default()).unwrap();
+    assert_eq!(rdr.file_count(), 1);
+    assert_eq!(rdr.get_file(0).size, Some(5 * 1024 * 1024 * 1024));
+}
+
+#[test]
+fn probe_zip64() {
+    let probe = super::probe(Path::new("./samples/zip/zip64.zip")).unwrap();
+    assert!(matches!(
+        probe,
+        crate::Probe::Valid(reason) if reason == "end of central directory at 208, ZIP64"
+    ));
+}
+
+#[rstest::rstest]
+#[case("shift_jis.zip", None, &["データ/テクスチャ.txt", "セーブ.dat"], "Shift_JIS")]
+#[case("cp866.zip", None, &["Моды/текстура.txt", "сохранение.dat"], "IBM866")]
+#[case("cp437.zip", None, &["café/résumé.txt", "naïve.dat"], "IBM437")]
+#[case("cp866.zip", Some("cp866"), &["Моды/текстура.txt", "сохранение.dat"], "IBM866")]
+#[case("cp866.zip", Some("cp437"), &["î«ñδ/ΓÑ¬ßΓπαá.txt", "ß«σαá¡Ñ¡¿Ñ.dat"], "IBM437")]
+fn name_encodings(
+    #[case] filename: &str,
+    #[case] name_encoding: Option<&str>,
+    #[case] expected_names: &[&str],
+    #[case] expected_encoding: &str,
+) {
+    let mut params = std::collections::HashMap::new();
+
+    if let Some(name_encoding) = name_encoding {
+        params.insert(String::from("name-encoding"), String::from(name_encoding));
+    }
+
+    let rdr = super::make_reader(
+        &Path::new("./samples/zip").join(filename),
+        crate::reader::Options {
+            params,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let names: Vec<_> = (0..rdr.file_count())
+        .map(|index| rdr.get_file(index).name)
+        .collect();
+    let expected_names: Vec<_> = expected_names
+        .iter()
+        .map(|name| <std::path::PathBuf as crate::PathBufUtils>::from_unix(name))
+        .collect();
+    assert_eq!(names, expected_names);
+    assert_eq!(rdr.attrs()["name-encoding"], expected_encoding);
+}
+
+#[test]
+fn invalid_name_encoding() {
+    let res = super::make_reader(
+        Path::new("./samples/zip/cp437.zip"),
+        crate::reader::Options {
+            params: [(String::from("name-encoding"), String::from("klingon"))].into(),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        res.err().unwrap(),
+        crate::reader::Error::InvalidParameter("name-encoding", _)
+    ));
+}
+
+#[test]
+fn utf8_names_have_no_name_encoding() {
+    let rdr =
+        super::make_reader(Path::new("./samples/zip/correct.zip"), Default::default()).unwrap();
+    assert!(rdr.attrs().is_empty());
+}
+
+#[test]
+fn special_mode_bits_are_dropped() {
+    use std::io::Write;
+
+    let dir = tempdir::TempDir::new("flpak-tests").unwrap();
+    let path = dir.path().join("archive.zip");
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    zip.start_file(
+        "run.sh",
+        zip::write::SimpleFileOptions::default().unix_permissions(0o755),
+    )
+    .unwrap();
+    zip.write_all(b"#!/bin/sh\n").unwrap();
+    let mut data = zip.finish().unwrap().into_inner();
+
+    // the writer masks permissions, set setuid bit in external attributes of the central directory
+    let cd = data
+        .windows(4)
+        .position(|window| window == b"PK\x01\x02")
+        .unwrap();
+    data[cd + 38..cd + 42].copy_from_slice(&(0o104755u32 << 16).to_le_bytes());
+    std::fs::write(&path, data).unwrap();
+
+    let rdr = super::make_reader(&path, Default::default()).unwrap();
+    assert_eq!(rdr.file_metadata(0).mode, Some(0o755));
+}
@@ -1,12 +1,80 @@
-use std::{collections::HashMap, fs, io, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::{io_error, writer, FileType, InputFileList, PathBufUtils};
+use glob::Pattern;
+use zip::{write::FullFileOptions, AesMode, CompressionMethod};
+
+use super::common::{
+    to_dos_time, to_unix_time, AES_256_DATA_OVERHEAD, AES_EXTRA_FIELD_SIZE, CENTRAL_HEADER_SIZE,
+    END_OF_CENTRAL_DIRECTORY_SIZE, EXTENDED_TIMESTAMP_ID, EXTENDED_TIMESTAMP_MTIME,
+    EXTENDED_TIMESTAMP_SIZE, LOCAL_HEADER_SIZE, ZIP64_CENTRAL_EXTRA_FIELD_SIZE,
+    ZIP64_DATA_DESCRIPTOR_SIZE, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE, ZIP64_LOCAL_EXTRA_FIELD_SIZE,
+};
+use crate::{
+    io_error, writer, writer::CompressionLevel, FileType, InputFileList, Overhead, PathBufUtils,
+};
+
+/// Estimate for `max-size`: ZIP64 end of central directory; local and central headers with
+/// timestamp, ZIP64 and AES extra fields, data descriptor and AES data overhead of every file.
+/// Names are in local and central headers.
+pub const SPLIT_OVERHEAD: Overhead = Overhead {
+    header_size: ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE
+        + ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE
+        + END_OF_CENTRAL_DIRECTORY_SIZE,
+    entry_size: LOCAL_HEADER_SIZE
+        + CENTRAL_HEADER_SIZE
+        + 2 * EXTENDED_TIMESTAMP_SIZE
+        + ZIP64_LOCAL_EXTRA_FIELD_SIZE
+        + ZIP64_CENTRAL_EXTRA_FIELD_SIZE
+        + ZIP64_DATA_DESCRIPTOR_SIZE
+        + 2 * AES_EXTRA_FIELD_SIZE
+        + AES_256_DATA_OVERHEAD,
+    name_copies: 2,
+};
+
+/// Size of the beginning of a file that is compressed to find out whether the file is compressible.
+const SAMPLE_SIZE: u64 = 64 * 1024;
+/// Files whose sample does not shrink below this share of its size are stored.
+const INCOMPRESSIBLE_RATIO: f64 = 0.97;
+/// Files of this size and larger get ZIP64 sizes, below 4 GiB to leave room for compressed data
+/// growing larger than the file itself.
+const LARGE_FILE_SIZE: u64 = 0xF000_0000;
+
+struct Options<'a> {
+    method: CompressionMethod,
+    level: Option<CompressionLevel>,
+    /// `method:<glob>=<method>` overrides, the longest pattern first.
+    rules: Vec<(Pattern, CompressionMethod)>,
+    store_incompressible: bool,
+    password: Option<&'a str>,
+    aes_mode: AesMode,
+    /// Modification time of all files, modification time of source files is used if not set.
+    timestamp: Option<SystemTime>,
+}
 
 pub fn create_archive(
     input_files: InputFileList,
     path: &Path,
-    _params: &HashMap<String, String>,
+    params: &HashMap<String, String>,
 ) -> writer::Result<()> {
+    write_archive(input_files, path, params, LARGE_FILE_SIZE)
+}
+
+/// Files of `large_file_size` and larger get ZIP64 sizes, tests lower it from [`LARGE_FILE_SIZE`].
+pub(super) fn write_archive(
+    input_files: InputFileList,
+    path: &Path,
+    params: &HashMap<String, String>,
+    large_file_size: u64,
+) -> writer::Result<()> {
+    let options = parse_options(params)?;
+
     let out = fs::File::create(path).map_err(writer::Error::CreatingOutputFile)?;
     let mut zip = zip::ZipWriter::new(out);
 
@@ -18,7 +86,14 @@ pub fn create_archive(
                     .try_to_unix()
                     .map_err(|err| writer::Error::InvalidInputFileName(input_file.dst_path, err))?;
                 path.push('/');
-                zip.add_directory(path, Default::default())
+
+                let metadata = fs::metadata(&input_file.src_path).map_err(|err| {
+                    writer::Error::ReadingInputFileMetadata(input_file.src_path.clone(), err)
+                })?;
+                let file_options =
+                    with_metadata(FullFileOptions::default(), &metadata, options.timestamp);
+
+                zip.add_directory(path, file_options)
                     .map_err(|err| writer::Error::WritingFileData(io_error!(Other, "{}", err,)))?;
             }
             FileType::RegularFile => {
@@ -30,10 +105,28 @@ pub fn create_archive(
                     .dst_path
                     .try_to_unix()
                     .map_err(|err| writer::Error::InvalidInputFileName(input_file.dst_path, err))?;
-                let options = zip::write::FileOptions::default()
-                    .compression_method(zip::CompressionMethod::Deflated);
 
-                zip.start_file(path, options)
+                let method = choose_method(&options, &path, &mut file).map_err(|err| {
+                    writer::Error::ArchivingInputFile(input_file.src_path.clone(), err)
+                })?;
+
+                let metadata = file.metadata().map_err(|err| {
+                    writer::Error::ReadingInputFileMetadata(input_file.src_path.clone(), err)
+                })?;
+                // ZIP64 end of central directory is written by the zip crate when there are too
+                // many entries or the archive gets larger than 4 GiB
+                let file_options = with_metadata(
+                    make_file_options(method, options.level),
+                    &metadata,
+                    options.timestamp,
+                )
+                .large_file(metadata.len() >= large_file_size);
+                let file_options = match options.password {
+                    Some(password) => file_options.with_aes_encryption(options.aes_mode, password),
+                    None => file_options,
+                };
+
+                zip.start_file(path, file_options)
                     .map_err(|err| writer::Error::WritingFileData(io_error!(Other, "{}", err,)))?;
 
                 io::copy(&mut file, &mut zip)
@@ -47,3 +140,185 @@ pub fn create_archive(
 
     Ok(())
 }
+
+fn parse_options(params: &HashMap<String, String>) -> writer::Result<Options<'_>> {
+    let method = match params.get("method") {
+        Some(value) => parse_method(value)?,
+        None => CompressionMethod::Deflated,
+    };
+    let level = CompressionLevel::from_params(params)?;
+
+    let mut rules = Vec::new();
+
+    for (key, value) in params {
+        if let Some(pattern) = key.strip_prefix("method:") {
+            let pattern = Pattern::new(pattern).map_err(|err| {
+                writer::Error::InvalidParameter("method", format!("invalid pattern '{key}': {err}"))
+            })?;
+            rules.push((pattern, parse_method(value)?));
+        }
+    }
+
+    rules.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.as_str().len()));
+
+    let store_incompressible = match params.get("store-incompressible").map(String::as_str) {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "store-incompressible",
+                format!("expected 'true' or 'false', got '{value}'"),
+            ))
+        }
+    };
+
+    let password = params.get("password").map(String::as_str);
+    // legacy ZipCrypto is only supported for reading
+    let aes_mode = match params.get("encryption").map(String::as_str) {
+        None | Some("aes256") => AesMode::Aes256,
+        Some("aes192") => AesMode::Aes192,
+        Some("aes128") => AesMode::Aes128,
+        Some(value) => {
+            return Err(writer::Error::InvalidParameter(
+                "encryption",
+                format!("expected 'aes128', 'aes192' or 'aes256', got '{value}'"),
+            ))
+        }
+    };
+
+    if password.is_none() && params.contains_key("encryption") {
+        return Err(writer::Error::InvalidParameter(
+            "encryption",
+            String::from("`password` option is required"),
+        ));
+    }
+
+    let timestamp = match params.get("timestamp") {
+        Some(value) => Some(
+            value
+                .parse()
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+                .map_err(|_| {
+                    writer::Error::InvalidParameter(
+                        "timestamp",
+                        format!("expected Unix time in seconds, got '{value}'"),
+                    )
+                })?,
+        ),
+        None => None,
+    };
+
+    Ok(Options {
+        method,
+        level,
+        rules,
+        store_incompressible,
+        password,
+        aes_mode,
+        timestamp,
+    })
+}
+
+fn parse_method(value: &str) -> writer::Result<CompressionMethod> {
+    match value {
+        "stored" => Ok(CompressionMethod::Stored),
+        "deflate" => Ok(CompressionMethod::Deflated),
+        "bzip2" => Ok(CompressionMethod::Bzip2),
+        "zstd" => Ok(CompressionMethod::Zstd),
+        _ => Err(writer::Error::InvalidParameter(
+            "method",
+            format!("expected 'stored', 'deflate', 'bzip2' or 'zstd', got '{value}'"),
+        )),
+    }
+}
+
+/// Per-glob rules take precedence, then incompressible files are stored.
+fn choose_method(
+    options: &Options,
+    path: &str,
+    file: &mut fs::File,
+) -> io::Result<CompressionMethod> {
+    if let Some(&(_, method)) = options
+        .rules
+        .iter()
+        .find(|(pattern, _)| pattern.matches(path))
+    {
+        return Ok(method);
+    }
+
+    if options.store_incompressible
+        && options.method != CompressionMethod::Stored
+        && !is_compressible(file)?
+    {
+        return Ok(CompressionMethod::Stored);
+    }
+
+    Ok(options.method)
+}
+
+/// `level=0` stores files whatever the method is.
+fn make_file_options(
+    method: CompressionMethod,
+    level: Option<CompressionLevel>,
+) -> FullFileOptions<'static> {
+    let level = match level.map(CompressionLevel::numeric) {
+        Some(0) => return FullFileOptions::default().compression_method(CompressionMethod::Stored),
+        _ => level,
+    };
+    let level = match method {
+        CompressionMethod::Stored => None,
+        CompressionMethod::Zstd => level.map(|level| i64::from(level.to_zstd())),
+        _ => level.map(|level| i64::from(level.numeric())),
+    };
+
+    FullFileOptions::default()
+        .compression_method(method)
+        .compression_level(level)
+}
+
+/// Deflates the beginning of the file to see if it shrinks, already compressed files (images,
+/// sounds, archives) do not. Rewinds the file afterwards.
+fn is_compressible(file: &mut fs::File) -> io::Result<bool> {
+    let mut sample = Vec::new();
+    file.by_ref().take(SAMPLE_SIZE).read_to_end(&mut sample)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if sample.is_empty() {
+        return Ok(true);
+    }
+
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+    io::Write::write_all(&mut encoder, &sample)?;
+    let compressed = encoder.finish()?;
+
+    Ok((compressed.len() as f64) < sample.len() as f64 * INCOMPRESSIBLE_RATIO)
+}
+
+/// Records modification time, as MS-DOS time and as UTC extended timestamp, and Unix permissions.
+fn with_metadata<'a>(
+    file_options: FullFileOptions<'a>,
+    metadata: &fs::Metadata,
+    timestamp: Option<SystemTime>,
+) -> FullFileOptions<'a> {
+    let mut file_options = file_options;
+
+    if let Some(modified) = timestamp.or_else(|| metadata.modified().ok()) {
+        file_options = file_options.last_modified_time(to_dos_time(modified));
+
+        if let Some(secs) = to_unix_time(modified) {
+            let mut data = vec![EXTENDED_TIMESTAMP_MTIME];
+            data.extend_from_slice(&secs.to_le_bytes());
+            file_options
+                .add_extra_data(EXTENDED_TIMESTAMP_ID, data.into_boxed_slice(), false)
+                .expect("extended timestamp should fit into extra field");
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file_options = file_options.unix_permissions(metadata.permissions().mode());
+    }
+
+    file_options
+}
@@ -1,6 +1,11 @@
 // use hex_literal::hex;
 use sha1::{Digest, Sha1};
-use std::{collections::HashMap, fs, io, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
 use tempdir::TempDir;
 
 use crate::InputFileListBuilder;
@@ -26,3 +31,280 @@ fn correct() {
     // hmm... resulting zip is different every time
     // assert_eq!(hash[..], hex!("fb977e5f705d8d9603d61aa00af3c23c00c36a11"));
 }
+
+#[rstest::rstest]
+#[case("aes128")]
+#[case("aes192")]
+#[case("aes256")]
+fn encrypted(#[case] encryption: &str) {
+    let input_files = InputFileListBuilder::new()
+        .add_dir(Path::new("./samples/unpacked"))
+        .unwrap()
+        .exclude_pattern("empty_dir/.gitkeep")
+        .build();
+
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let output_path = dir.path().join("archive.zip");
+    let params = HashMap::from([
+        (String::from("password"), String::from("secret")),
+        (String::from("encryption"), String::from(encryption)),
+    ]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let mut rdr = crate::zip::make_reader(
+        &output_path,
+        crate::reader::Options {
+            params: HashMap::from([(String::from("password"), String::from("secret"))]),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let index = (0..rdr.file_count())
+        .find(|&index| rdr.get_file(index).name == Path::new("file001.txt"))
+        .unwrap();
+    assert_eq!(
+        rdr.file_attrs(index),
+        vec![("encryption", String::from("aes"))]
+    );
+
+    let mut data = Vec::new();
+    rdr.create_file_reader(index)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, fs::read("./samples/unpacked/file001.txt").unwrap());
+}
+
+#[test]
+fn invalid_encryption() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([
+        (String::from("password"), String::from("secret")),
+        (String::from("encryption"), String::from("zipcrypto")),
+    ]);
+    let res = super::create_archive(
+        InputFileListBuilder::new().build(),
+        &dir.path().join("archive.zip"),
+        &params,
+    );
+    assert!(matches!(
+        res,
+        Err(crate::writer::Error::InvalidParameter("encryption", _))
+    ));
+}
+
+/// Archives compressible `file001.txt` and incompressible `img001.png`.
+#[cfg(test)]
+fn create_with_params(params: &[(&str, &str)]) -> (TempDir, std::path::PathBuf) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let input_dir = dir.path().join("input");
+    fs::create_dir(&input_dir).unwrap();
+    fs::write(
+        input_dir.join("file001.txt"),
+        "some sample text\n".repeat(256),
+    )
+    .unwrap();
+    fs::copy(
+        "./samples/unpacked/img001.png",
+        input_dir.join("img001.png"),
+    )
+    .unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&input_dir)
+        .unwrap()
+        .build();
+
+    let output_path = dir.path().join("archive.zip");
+    let params = params
+        .iter()
+        .map(|&(key, value)| (String::from(key), String::from(value)))
+        .collect();
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    (dir, output_path)
+}
+
+/// Compression methods of `file001.txt` and `img001.png`.
+#[cfg(test)]
+fn methods(path: &Path) -> (zip::CompressionMethod, zip::CompressionMethod) {
+    let mut zip = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+    let txt = zip.by_name("file001.txt").unwrap().compression();
+    let png = zip.by_name("img001.png").unwrap().compression();
+    (txt, png)
+}
+
+#[rstest::rstest]
+#[case("stored", "default", zip::CompressionMethod::Stored)]
+#[case("deflate", "best", zip::CompressionMethod::Deflated)]
+#[case("bzip2", "fast", zip::CompressionMethod::Bzip2)]
+#[case("zstd", "best", zip::CompressionMethod::Zstd)]
+fn compression_method(
+    #[case] method: &str,
+    #[case] level: &str,
+    #[case] expected: zip::CompressionMethod,
+) {
+    let (_dir, output_path) = create_with_params(&[("method", method), ("level", level)]);
+    assert_eq!(methods(&output_path).0, expected);
+
+    let mut rdr = crate::zip::make_reader(&output_path, Default::default()).unwrap();
+    let index = (0..rdr.file_count())
+        .find(|&index| rdr.get_file(index).name == Path::new("file001.txt"))
+        .unwrap();
+
+    let mut data = Vec::new();
+    rdr.create_file_reader(index)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, "some sample text\n".repeat(256).as_bytes());
+}
+
+#[test]
+fn store_incompressible() {
+    use zip::CompressionMethod::{Deflated, Stored};
+
+    let (_dir, output_path) = create_with_params(&[]);
+    assert_eq!(methods(&output_path), (Deflated, Stored));
+
+    let (_dir, output_path) = create_with_params(&[("store-incompressible", "false")]);
+    assert_eq!(methods(&output_path), (Deflated, Deflated));
+}
+
+#[test]
+fn method_rules() {
+    use zip::CompressionMethod::{Bzip2, Deflated, Stored};
+
+    let (_dir, output_path) =
+        create_with_params(&[("method:*.txt", "stored"), ("method:*.png", "deflate")]);
+    assert_eq!(methods(&output_path), (Stored, Deflated));
+
+    // the longest pattern wins
+    let (_dir, output_path) =
+        create_with_params(&[("method:*", "stored"), ("method:file*.txt", "bzip2")]);
+    assert_eq!(methods(&output_path), (Bzip2, Stored));
+}
+
+#[test]
+fn level_0_stores() {
+    let (_dir, output_path) = create_with_params(&[("level", "0")]);
+    assert_eq!(
+        methods(&output_path),
+        (
+            zip::CompressionMethod::Stored,
+            zip::CompressionMethod::Stored
+        )
+    );
+}
+
+#[test]
+fn invalid_method() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let params = HashMap::from([(String::from("method"), String::from("lzma"))]);
+    let res = super::create_archive(
+        InputFileListBuilder::new().build(),
+        &dir.path().join("archive.zip"),
+        &params,
+    );
+    assert!(matches!(
+        res,
+        Err(crate::writer::Error::InvalidParameter("method", _))
+    ));
+}
+
+#[rstest::rstest]
+// before MS-DOS epoch, only the extended timestamp holds it
+#[case(0)]
+#[case(1_000_000_000)]
+fn timestamp(#[case] secs: u64) {
+    let timestamp = secs.to_string();
+    let (dir, output_path) = create_with_params(&[("timestamp", &timestamp)]);
+
+    let rdr = crate::zip::make_reader(&output_path, Default::default()).unwrap();
+
+    for index in 0..rdr.file_count() {
+        let metadata = rdr.file_metadata(index);
+        assert_eq!(
+            metadata.modified,
+            Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let src_path = dir.path().join("input").join(rdr.get_file(index).name);
+            let mode = fs::metadata(src_path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(metadata.mode, Some(mode));
+        }
+    }
+}
+
+#[test]
+fn reproducible() {
+    let (dir, output_path) = create_with_params(&[("timestamp", "1000000000")]);
+    let first = fs::read(&output_path).unwrap();
+
+    let output_path_2 = dir.path().join("archive2.zip");
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&dir.path().join("input"))
+        .unwrap()
+        .build();
+    let params = HashMap::from([(String::from("timestamp"), String::from("1000000000"))]);
+    super::create_archive(input_files, &output_path_2, &params).unwrap();
+
+    assert_eq!(first, fs::read(&output_path_2).unwrap());
+}
+
+#[test]
+fn many_entries_use_zip64() {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let src_path = dir.path().join("empty");
+    fs::write(&src_path, b"").unwrap();
+
+    let input_files = (0..=u16::MAX as usize)
+        .map(|index| crate::InputFile {
+            src_path: src_path.clone(),
+            dst_path: format!("{index:05}").into(),
+            file_type: crate::FileType::RegularFile,
+        })
+        .collect();
+
+    let output_path = dir.path().join("archive.zip");
+    let params = HashMap::from([(String::from("method"), String::from("stored"))]);
+    super::create_archive(input_files, &output_path, &params).unwrap();
+
+    let data = fs::read(&output_path).unwrap();
+    assert!(data.windows(4).any(|window| window == b"PK\x06\x06"));
+
+    let rdr = super::make_reader(&output_path, Default::default()).unwrap();
+    assert_eq!(rdr.file_count(), 65536);
+}
+
+#[rstest::rstest]
+#[case(99, false)]
+#[case(100, true)]
+fn large_file_threshold(#[case] size: usize, #[case] zip64: bool) {
+    let dir = TempDir::new("flpak-tests").unwrap();
+    let input_dir = dir.path().join("input");
+    fs::create_dir(&input_dir).unwrap();
+    fs::write(input_dir.join("file.bin"), vec![b'x'; size]).unwrap();
+
+    let input_files = InputFileListBuilder::new()
+        .add_dir(&input_dir)
+        .unwrap()
+        .build();
+
+    let output_path = dir.path().join("archive.zip");
+    super::writer::write_archive(input_files, &output_path, &HashMap::new(), 100).unwrap();
+
+    // ZIP64 local header has 0xFFFFFFFF sizes, the real ones are in the ZIP64 extra field
+    let data = fs::read(&output_path).unwrap();
+    assert_eq!(&data[..4], b"PK\x03\x04");
+    assert_eq!(data[18..22] == [0xFF; 4], zip64);
+
+    let mut zip = zip::ZipArchive::new(fs::File::open(&output_path).unwrap()).unwrap();
+    let mut unpacked = Vec::new();
+    zip.by_index(0).unwrap().read_to_end(&mut unpacked).unwrap();
+    assert_eq!(unpacked, vec![b'x'; size]);
+}